@@ -0,0 +1,72 @@
+//! Sieve of Atkin on the mod-30 wheel bitset
+//!
+//! Numbers coprime to 30 are exactly the ones Atkin's quadratic forms
+//! classify by their residue mod 60, so the result lands in the same wheel
+//! layout as the Eratosthenes sieve and every query works unchanged.
+
+use crate::isqrt;
+use crate::wheel;
+
+/// Residues mod 60 where 4x^2 + y^2 = n has an odd number of solutions iff
+/// n is prime or has a square factor
+const FORM_4X2_Y2: [u64; 8] = [1, 13, 17, 29, 37, 41, 49, 53];
+/// Residues mod 60 decided by 3x^2 + y^2 = n
+const FORM_3X2_Y2: [u64; 4] = [7, 19, 31, 43];
+/// Residues mod 60 decided by 3x^2 - y^2 = n with x > y
+const FORM_3X2_MINUS_Y2: [u64; 4] = [11, 23, 47, 59];
+
+/// Sieve `bits` (exactly `bytes_for(limit)` bytes) covering `0..=limit`
+/// Every byte must start out as 0
+pub(crate) fn sieve(bits: &mut [u8], limit: u64) {
+    // 4x^2 + y^2
+    for x in 1..=isqrt(limit / 4) {
+        let four_x2 = 4 * x * x;
+        for y in 1..=isqrt(limit - four_x2) {
+            let n = four_x2 + y * y;
+            if FORM_4X2_Y2.contains(&(n % 60)) {
+                wheel::toggle(bits, limit, n);
+            }
+        }
+    }
+
+    // 3x^2 + y^2
+    for x in 1..=isqrt(limit / 3) {
+        let three_x2 = 3 * x * x;
+        for y in 1..=isqrt(limit - three_x2) {
+            let n = three_x2 + y * y;
+            if FORM_3X2_Y2.contains(&(n % 60)) {
+                wheel::toggle(bits, limit, n);
+            }
+        }
+    }
+
+    // 3x^2 - y^2 with x > y; the smallest value for a given x is 2x^2 + 2x - 1
+    // 3x^2 can pass u64::MAX near the top of the range, so work in u128
+    for x in 1..=isqrt(limit / 2) {
+        let three_x2 = 3 * x as u128 * x as u128;
+        for y in (1..x).rev() {
+            let n = three_x2 - y as u128 * y as u128;
+            if n > limit as u128 {
+                break;
+            }
+            let n = n as u64;
+            if FORM_3X2_MINUS_Y2.contains(&(n % 60)) {
+                wheel::toggle(bits, limit, n);
+            }
+        }
+    }
+
+    // The forms also flip numbers with a square factor; clear every multiple
+    // of r^2 for each prime r >= 7 (multiples of 4, 9 and 25 are off the wheel)
+    for r in 7..=isqrt(limit) {
+        if !wheel::is_set(bits, limit, r) {
+            continue;
+        }
+        let square = r * r;
+        let mut next = Some(square);
+        while let Some(m) = next.filter(|&m| m <= limit) {
+            wheel::clear(bits, limit, m);
+            next = m.checked_add(square);
+        }
+    }
+}
@@ -0,0 +1,345 @@
+//! Baillie–PSW probable-prime test for u128
+//!
+//! A strong Fermat test to base 2 followed by a strong Lucas test with
+//! Selfridge's parameters. No composite passing both is known, and none
+//! exists below 2^64, where the deterministic [`is_prime`] is used instead.
+//! Residues are kept in Montgomery form so no 256-bit division is needed.
+
+use crate::is_prime;
+use crate::mersenne::mul_wide;
+use crate::table::PRIME_TABLE;
+
+/// Odd candidates are trial-divided by the primes below this first
+const TRIAL_DIVISION_LIMIT: u128 = 1_000;
+
+/// Strong Fermat bases `is_prime_u128` checks on top of Baillie–PSW
+const EXTRA_BASES: [u128; 11] = [3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// Check whether `n` is a probable prime using Baillie–PSW
+/// Exact for every `n < 2^64`; above that a composite passing would be the
+/// first one ever found
+pub fn is_probable_prime(n: u128) -> bool {
+    if let Ok(small) = u64::try_from(n) {
+        return is_prime(small);
+    }
+    if has_small_factor(n) {
+        return false;
+    }
+
+    let field = Montgomery::new(n);
+    field.is_strong_probable_prime(2) && field.is_strong_lucas_probable_prime()
+}
+
+/// Check whether `n` is prime: Baillie–PSW followed by strong Fermat rounds
+/// to the odd prime bases up to 37
+/// Exact for every `n < 2^64`; above that a composite would have to fool
+/// Baillie–PSW and all eleven extra bases at once
+pub fn is_prime_u128(n: u128) -> bool {
+    if let Ok(small) = u64::try_from(n) {
+        return is_prime(small);
+    }
+    if has_small_factor(n) {
+        return false;
+    }
+
+    let field = Montgomery::new(n);
+    field.is_strong_probable_prime(2)
+        && field.is_strong_lucas_probable_prime()
+        && EXTRA_BASES
+            .iter()
+            .all(|&a| field.is_strong_probable_prime(a))
+}
+
+/// Trial division of `n > 2^64` by the primes below TRIAL_DIVISION_LIMIT
+fn has_small_factor(n: u128) -> bool {
+    PRIME_TABLE
+        .iter()
+        .map(|&p| p as u128)
+        .take_while(|&p| p < TRIAL_DIVISION_LIMIT)
+        .any(|p| n.is_multiple_of(p))
+}
+
+/// Arithmetic modulo an odd `n` on Montgomery residues `a * 2^128 mod n`
+struct Montgomery {
+    n: u128,
+    /// -n^-1 mod 2^128
+    n_neg_inv: u128,
+    /// 2^256 mod n, for converting into Montgomery form
+    r2: u128,
+}
+
+impl Montgomery {
+    /// Set up arithmetic modulo `n`, which must be odd and greater than 1
+    fn new(n: u128) -> Self {
+        debug_assert!(n > 1 && n % 2 == 1);
+
+        // Newton's iteration doubles the correct low bits: 3 -> 6 -> ... -> 192
+        let mut inv = n;
+        for _ in 0..6 {
+            inv = inv.wrapping_mul(2u128.wrapping_sub(n.wrapping_mul(inv)));
+        }
+
+        // 2^128 mod n, doubled 128 more times
+        let mut r2 = n.wrapping_neg() % n;
+        for _ in 0..128 {
+            r2 = add_mod(r2, r2, n);
+        }
+
+        Self {
+            n,
+            n_neg_inv: inv.wrapping_neg(),
+            r2,
+        }
+    }
+
+    /// Montgomery reduction: (hi * 2^128 + lo) / 2^128 mod n, for inputs below n * 2^128
+    fn reduce(&self, (hi, lo): (u128, u128)) -> u128 {
+        let m = lo.wrapping_mul(self.n_neg_inv);
+        let (mn_hi, mn_lo) = mul_wide(m, self.n);
+        // lo + mn_lo is 0 mod 2^128 by construction; only its carry matters
+        let carry = lo.overflowing_add(mn_lo).1 as u128;
+        let (t, overflow_a) = hi.overflowing_add(mn_hi);
+        let (t, overflow_b) = t.overflowing_add(carry);
+        if overflow_a || overflow_b || t >= self.n {
+            t.wrapping_sub(self.n)
+        } else {
+            t
+        }
+    }
+
+    fn to_mont(&self, a: u128) -> u128 {
+        self.reduce(mul_wide(a % self.n, self.r2))
+    }
+
+    fn mul(&self, a: u128, b: u128) -> u128 {
+        self.reduce(mul_wide(a, b))
+    }
+
+    fn add(&self, a: u128, b: u128) -> u128 {
+        add_mod(a, b, self.n)
+    }
+
+    fn sub(&self, a: u128, b: u128) -> u128 {
+        if a >= b {
+            a - b
+        } else {
+            a.wrapping_sub(b).wrapping_add(self.n)
+        }
+    }
+
+    /// a / 2 mod n; halving commutes with the Montgomery scaling
+    fn half(&self, a: u128) -> u128 {
+        if a.is_multiple_of(2) {
+            a / 2
+        } else {
+            // (a + n) / 2 without overflowing: both are odd
+            a / 2 + self.n / 2 + 1
+        }
+    }
+
+    /// base^exp with `base` already in Montgomery form
+    fn pow(&self, base: u128, mut exp: u128) -> u128 {
+        let mut result = self.to_mont(1);
+        let mut base = base;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = self.mul(result, base);
+            }
+            base = self.mul(base, base);
+            exp >>= 1;
+        }
+        result
+    }
+
+    /// Strong Fermat (Miller–Rabin) round to base `a`
+    fn is_strong_probable_prime(&self, a: u128) -> bool {
+        let n = self.n;
+        let s = (n - 1).trailing_zeros();
+        let d = (n - 1) >> s;
+
+        let one = self.to_mont(1);
+        let minus_one = self.to_mont(n - 1);
+        let mut x = self.pow(self.to_mont(a), d);
+        if x == one || x == minus_one {
+            return true;
+        }
+        for _ in 1..s {
+            x = self.mul(x, x);
+            if x == minus_one {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Strong Lucas test with Selfridge's method A: the first D in
+    /// 5, -7, 9, -11, ... with Jacobi(D / n) = -1, P = 1 and Q = (1 - D) / 4
+    fn is_strong_lucas_probable_prime(&self) -> bool {
+        let n = self.n;
+        // A square n has Jacobi(D / n) >= 0 for every D, so the search would not end
+        let root = n.isqrt();
+        if root * root == n {
+            return false;
+        }
+
+        let mut d: i128 = 5;
+        loop {
+            match jacobi(d, n) {
+                -1 => break,
+                0 if d.unsigned_abs() != n => return false, // shares a factor with n
+                _ => d = if d > 0 { -(d + 2) } else { -d + 2 },
+            }
+        }
+
+        let to_residue = |x: i128| {
+            let magnitude = self.to_mont(x.unsigned_abs());
+            if x < 0 {
+                self.sub(0, magnitude)
+            } else {
+                magnitude
+            }
+        };
+        let d_m = to_residue(d);
+        let q_m = to_residue((1 - d) / 4);
+
+        // n + 1 = k * 2^s with k odd; n < u128::MAX as it has no small factor
+        let s = (n + 1).trailing_zeros();
+        let k = (n + 1) >> s;
+
+        // Walk the bits of k: (U_j, V_j, Q^j) -> doubled, then +1 on set bits
+        let one = self.to_mont(1);
+        let (mut u, mut v, mut qk) = (one, one, q_m);
+        for bit in (0..127 - k.leading_zeros()).rev() {
+            u = self.mul(u, v);
+            v = self.sub(self.mul(v, v), self.add(qk, qk));
+            qk = self.mul(qk, qk);
+            if (k >> bit) & 1 == 1 {
+                let next_u = self.half(self.add(u, v));
+                v = self.half(self.add(self.mul(d_m, u), v));
+                u = next_u;
+                qk = self.mul(qk, q_m);
+            }
+        }
+
+        if u == 0 || v == 0 {
+            return true;
+        }
+        for _ in 1..s {
+            v = self.sub(self.mul(v, v), self.add(qk, qk));
+            if v == 0 {
+                return true;
+            }
+            qk = self.mul(qk, qk);
+        }
+        false
+    }
+}
+
+/// (a + b) mod m for `a, b < m`, without overflowing
+fn add_mod(a: u128, b: u128, m: u128) -> u128 {
+    let (sum, overflow) = a.overflowing_add(b);
+    if overflow || sum >= m {
+        sum.wrapping_sub(m)
+    } else {
+        sum
+    }
+}
+
+/// Jacobi symbol (a / n) for odd `n`
+fn jacobi(a: i128, n: u128) -> i32 {
+    let mut a = if a < 0 {
+        (n - a.unsigned_abs() % n) % n
+    } else {
+        a as u128 % n
+    };
+    let mut n = n;
+    let mut result = 1;
+    while a != 0 {
+        while a.is_multiple_of(2) {
+            a /= 2;
+            if n % 8 == 3 || n % 8 == 5 {
+                result = -result;
+            }
+        }
+        (a, n) = (n, a);
+        if a % 4 == 3 && n % 4 == 3 {
+            result = -result;
+        }
+        a %= n;
+    }
+    if n == 1 {
+        result
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_deterministic_below_2_64() {
+        for n in (0..10_000u128).chain(u64::MAX as u128 - 1_000..=u64::MAX as u128) {
+            assert_eq!(is_probable_prime(n), is_prime(n as u64), "n = {n}");
+        }
+    }
+
+    #[test]
+    fn test_large_primes() {
+        assert!(is_probable_prime((1 << 89) - 1));
+        assert!(is_probable_prime((1 << 127) - 1));
+        assert!(is_probable_prime(u128::MAX - 158)); // largest prime below 2^128
+        assert!(is_probable_prime((1 << 64) + 13));
+        assert!(!is_probable_prime(u128::MAX));
+        assert!(!is_probable_prime((1 << 127) + 1));
+    }
+
+    #[test]
+    fn test_products_of_large_primes() {
+        let p = 18_446_744_073_709_551_557u128; // largest u64 prime
+        let q = 18_446_744_073_709_551_533u128;
+        assert!(!is_probable_prime(p * q));
+        assert!(!is_probable_prime(p * p));
+        // 2^64 + 13 squared, and times a small prime past the trial limit
+        assert!(!is_probable_prime(((1 << 64) + 13) * 1_009));
+    }
+
+    #[test]
+    fn test_agrees_with_lucas_lehmer() {
+        for p in 65..=127 {
+            let mersenne = u128::MAX >> (128 - p);
+            assert_eq!(
+                is_probable_prime(mersenne),
+                crate::is_mersenne_prime(p),
+                "p = {p}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_prime_u128_agrees_with_bpsw() {
+        let p = 18_446_744_073_709_551_557u128;
+        for n in [
+            (1 << 89) - 1,
+            (1 << 127) - 1,
+            u128::MAX - 158,
+            p * p,
+            p * 1_009,
+            p,
+        ] {
+            assert_eq!(is_prime_u128(n), is_probable_prime(n), "n = {n}");
+        }
+        assert!(is_prime_u128(u128::MAX - 158));
+        assert!(!is_prime_u128(u128::MAX));
+    }
+
+    #[test]
+    fn test_jacobi() {
+        assert_eq!(jacobi(5, 21), 1);
+        assert_eq!(jacobi(-7, 15), 1);
+        assert_eq!(jacobi(2, 15), 1);
+        assert_eq!(jacobi(5, 3), -1);
+        assert_eq!(jacobi(9, 15), 0);
+    }
+}
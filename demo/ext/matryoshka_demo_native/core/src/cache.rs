@@ -0,0 +1,132 @@
+//! Process-wide sieve shared across calls
+//!
+//! One [`Sieve`] lives behind a global parking_lot RwLock. Queries within
+//! its range only take the read lock; a query past it takes the write lock
+//! and extends the sieve over the missing range, so repeated calls never
+//! re-sieve.
+//!
+//! Every function here may be called from any number of threads at once.
+//! The lock's release and acquire order all access to the sieve: a query
+//! sees the sieve as the last write left it, grown or cleared in full and
+//! never halfway. The lock does not poison: a query that panics while
+//! extending leaves the cache empty rather than half-sieved, as the sieve
+//! is taken out of it while it grows.
+
+use parking_lot::RwLock;
+
+use crate::{CoreError, Sieve};
+
+/// The shared sieve, None until the first query or after [`clear_cache`]
+static CACHE: RwLock<Option<Sieve>> = RwLock::new(None);
+
+/// Count prime numbers up to and including `limit` using the shared sieve,
+/// extending it first if `limit` lies past its range
+/// Returns [`CoreError::LimitTooLarge`] if the sieve cannot grow to `limit`;
+/// the cached range is left as it was
+pub fn cached_count_primes(limit: u64) -> Result<u64, CoreError> {
+    with_cached_sieve(limit, |sieve| sieve.count_up_to(limit))
+}
+
+/// Check whether `n` is prime using the shared sieve, extending it to `n`
+/// if needed
+pub fn cached_is_prime(n: u64) -> Result<bool, CoreError> {
+    with_cached_sieve(n, |sieve| sieve.is_prime(n))
+}
+
+/// Check whether `n` is prime, reading the shared sieve when it already
+/// covers `n` and falling back to Miller–Rabin otherwise
+/// Unlike [`cached_is_prime`] the sieve never grows, so any `n` is cheap
+pub fn is_prime_hybrid(n: u64) -> bool {
+    {
+        let cache = CACHE.read();
+        if let Some(sieve) = cache.as_ref()
+            && n <= sieve.limit()
+        {
+            return sieve.is_prime(n);
+        }
+    }
+    crate::is_prime(n)
+}
+
+/// Upper bound (inclusive) the shared sieve currently covers, or None if empty
+pub fn cached_limit() -> Option<u64> {
+    let cache = CACHE.read();
+    cache.as_ref().map(Sieve::limit)
+}
+
+/// Heap bytes the shared sieve holds, 0 when empty
+pub fn cached_memory_usage() -> usize {
+    let cache = CACHE.read();
+    cache.as_ref().map_or(0, Sieve::memory_usage)
+}
+
+/// Drop the shared sieve and free its memory; the next query starts over
+pub fn clear_cache() {
+    *CACHE.write() = None;
+}
+
+/// Run `f` against the shared sieve once it covers `limit`
+fn with_cached_sieve<R>(limit: u64, f: impl FnOnce(&Sieve) -> R) -> Result<R, CoreError> {
+    {
+        let cache = CACHE.read();
+        if let Some(sieve) = cache.as_ref()
+            && sieve.limit() >= limit
+        {
+            return Ok(f(sieve));
+        }
+    }
+
+    // Another caller may have grown the sieve between the two locks;
+    // try_extend_to is then a no-op
+    let mut cache = CACHE.write();
+    let sieve = match cache.take() {
+        Some(mut sieve) => match sieve.try_extend_to(limit) {
+            Ok(()) => sieve,
+            Err(error) => {
+                *cache = Some(sieve);
+                return Err(error);
+            }
+        },
+        None => Sieve::try_new(limit)?,
+    };
+    Ok(f(cache.insert(sieve)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The cache is global, so everything touching it stays in one test
+    #[test]
+    fn test_cache_grows_and_clears() {
+        clear_cache();
+        assert_eq!(cached_limit(), None);
+        assert_eq!(cached_memory_usage(), 0);
+        assert!(is_prime_hybrid(997));
+        assert_eq!(cached_limit(), None);
+
+        assert_eq!(cached_count_primes(1_000), Ok(168));
+        assert!(is_prime_hybrid(997) && !is_prime_hybrid(999));
+        assert!(is_prime_hybrid(1_000_000_007));
+        assert_eq!(cached_limit(), Some(1_000));
+        assert_eq!(cached_limit(), Some(1_000));
+        assert_eq!(cached_count_primes(100), Ok(25));
+        assert_eq!(cached_limit(), Some(1_000));
+
+        assert_eq!(cached_count_primes(1_000_000), Ok(78_498));
+        assert_eq!(cached_is_prime(999_983), Ok(true));
+        assert_eq!(cached_is_prime(1_000_001), Ok(false));
+        assert_eq!(cached_limit(), Some(1_000_001));
+        assert!(cached_memory_usage() >= 1_000_001 / 30);
+
+        assert_eq!(
+            cached_count_primes(u64::MAX),
+            Err(CoreError::LimitTooLarge { limit: u64::MAX })
+        );
+        assert_eq!(cached_limit(), Some(1_000_001));
+
+        clear_cache();
+        assert_eq!(cached_limit(), None);
+        assert_eq!(cached_memory_usage(), 0);
+    }
+}
@@ -0,0 +1,155 @@
+//! Cooperative cancellation for long computations
+//!
+//! The `_with_cancel` variants check a shared [`CancelToken`] once per
+//! segment (or per sieving prime); another thread, signal handler or FFI
+//! unblock callback sets it, and the computation returns
+//! [`CoreError::Cancelled`](crate::CoreError::Cancelled) at its next check.
+//!
+//! With `std` a token may also carry a deadline, counting as cancelled once
+//! it passes, and follow a parent token, so one call can have a deadline
+//! of its own while still stopping when the caller's shared token is set.
+
+use core::ops::ControlFlow;
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "std")]
+use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+#[cfg(feature = "alloc")]
+use crate::CoreError;
+
+/// Flag a long computation polls between segments
+#[derive(Debug, Default)]
+pub struct CancelToken {
+    cancelled: AtomicBool,
+    #[cfg(feature = "std")]
+    deadline: Option<Instant>,
+    #[cfg(feature = "std")]
+    parent: Option<Arc<CancelToken>>,
+}
+
+impl CancelToken {
+    /// A token that has not been cancelled
+    pub const fn new() -> Self {
+        Self {
+            cancelled: AtomicBool::new(false),
+            #[cfg(feature = "std")]
+            deadline: None,
+            #[cfg(feature = "std")]
+            parent: None,
+        }
+    }
+
+    /// A token that is cancelled whenever `parent` is; cancelling it leaves
+    /// `parent` alone
+    #[cfg(feature = "std")]
+    pub fn child(parent: Arc<CancelToken>) -> Self {
+        Self {
+            parent: Some(parent),
+            ..Self::new()
+        }
+    }
+
+    /// This token, also cancelled once `deadline` has passed
+    #[cfg(feature = "std")]
+    pub fn with_deadline(self, deadline: Instant) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..self
+        }
+    }
+
+    /// Whether the deadline of [`CancelToken::with_deadline`] has passed
+    #[cfg(feature = "std")]
+    pub fn timed_out(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Ask every computation watching this token to stop at its next check
+    /// Safe to call from any thread; no data is published through the flag,
+    /// so relaxed ordering is enough
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`CancelToken::cancel`] has been called, on this token or
+    /// its parent, or its deadline has passed
+    pub fn is_cancelled(&self) -> bool {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return true;
+        }
+        #[cfg(feature = "std")]
+        if self.timed_out()
+            || self
+                .parent
+                .as_ref()
+                .is_some_and(|parent| parent.is_cancelled())
+        {
+            return true;
+        }
+        false
+    }
+
+    /// Break once cancelled, for passing as a count observer's verdict
+    pub fn observe(&self) -> ControlFlow<()> {
+        if self.is_cancelled() {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    /// Fail with [`CoreError::Cancelled`] once cancelled
+    #[cfg(feature = "alloc")]
+    pub(crate) fn check(&self) -> Result<(), CoreError> {
+        if self.is_cancelled() {
+            Err(CoreError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_is_sticky() {
+        let token = CancelToken::new();
+        assert!(!token.is_cancelled());
+        token.cancel();
+        token.cancel();
+        assert!(token.is_cancelled());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_deadline_and_parent() {
+        use std::time::Duration;
+
+        let expired = CancelToken::new().with_deadline(Instant::now());
+        assert!(expired.timed_out() && expired.is_cancelled());
+        let later = CancelToken::new().with_deadline(Instant::now() + Duration::from_secs(60));
+        assert!(!later.timed_out() && !later.is_cancelled());
+
+        let parent = Arc::new(CancelToken::new());
+        let child = CancelToken::child(Arc::clone(&parent));
+        child.cancel();
+        assert!(child.is_cancelled() && !parent.is_cancelled());
+        let child = CancelToken::child(Arc::clone(&parent));
+        parent.cancel();
+        assert!(child.is_cancelled() && !child.timed_out());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_check() {
+        let token = CancelToken::default();
+        assert_eq!(token.check(), Ok(()));
+        token.cancel();
+        assert_eq!(token.check(), Err(CoreError::Cancelled));
+    }
+}
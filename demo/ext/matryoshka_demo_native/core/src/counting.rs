@@ -0,0 +1,159 @@
+//! Counting the bytes allocated, for telling how much memory is held
+//!
+//! A [`MemoryCounter`] is a current/peak pair of relaxed atomics, cheap
+//! enough to update on every allocation. [`CountingAlloc`] wraps a global
+//! allocator and feeds one with every byte it hands out and gets back; a
+//! program (or an extension's cdylib) installs it with `#[global_allocator]`
+//! to see what its native code holds, which the OS only reports as RSS.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Bytes held now and the most held at once since the counter was created
+#[derive(Debug, Default)]
+pub struct MemoryCounter {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl MemoryCounter {
+    /// A counter at zero
+    pub const fn new() -> Self {
+        Self {
+            current: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+
+    /// Record `bytes` more being held
+    pub fn add(&self, bytes: usize) {
+        let now = self.current.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.peak.fetch_max(now, Ordering::Relaxed);
+    }
+
+    /// Record `bytes` fewer being held
+    pub fn sub(&self, bytes: usize) {
+        self.current.fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    /// Bytes held now
+    pub fn current(&self) -> usize {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    /// Most bytes held at once
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+}
+
+/// Global allocator counting the bytes `A` has handed out and not had back
+/// Sizes are the requested ones, not whatever `A` rounds them up to
+#[derive(Debug, Default)]
+pub struct CountingAlloc<A> {
+    inner: A,
+    counter: MemoryCounter,
+}
+
+impl<A> CountingAlloc<A> {
+    /// Count the allocations made through `inner`
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            counter: MemoryCounter::new(),
+        }
+    }
+
+    /// The bytes allocated through this allocator
+    pub fn counter(&self) -> &MemoryCounter {
+        &self.counter
+    }
+}
+
+// SAFETY: every call is forwarded to `inner` unchanged, so its guarantees
+// carry over; the counter is only updated, never consulted
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: the caller upholds `alloc`'s contract for `layout`
+        let ptr = unsafe { self.inner.alloc(layout) };
+        if !ptr.is_null() {
+            self.counter.add(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: the caller upholds `alloc_zeroed`'s contract for `layout`
+        let ptr = unsafe { self.inner.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            self.counter.add(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: `ptr` came from this allocator, hence from `inner`
+        unsafe { self.inner.dealloc(ptr, layout) };
+        self.counter.sub(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // SAFETY: `ptr` came from `inner`, and the caller upholds
+        // `realloc`'s contract for `layout` and `new_size`
+        let new = unsafe { self.inner.realloc(ptr, layout, new_size) };
+        if !new.is_null() {
+            if new_size >= layout.size() {
+                self.counter.add(new_size - layout.size());
+            } else {
+                self.counter.sub(layout.size() - new_size);
+            }
+        }
+        new
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use std::alloc::System;
+
+    #[test]
+    fn test_memory_counter() {
+        let counter = MemoryCounter::new();
+        counter.add(100);
+        counter.add(50);
+        counter.sub(120);
+        assert_eq!((counter.current(), counter.peak()), (30, 150));
+        counter.add(200);
+        assert_eq!((counter.current(), counter.peak()), (230, 230));
+    }
+
+    #[test]
+    fn test_counting_alloc() {
+        let allocator = CountingAlloc::new(System);
+        let layout = Layout::from_size_align(1_000, 8).unwrap();
+        // SAFETY: each pointer is checked, then freed once with its layout
+        unsafe {
+            let ptr = allocator.alloc(layout);
+            assert!(!ptr.is_null());
+            assert_eq!(allocator.counter().current(), 1_000);
+
+            let ptr = allocator.realloc(ptr, layout, 4_000);
+            assert!(!ptr.is_null());
+            assert_eq!(allocator.counter().current(), 4_000);
+            let layout = Layout::from_size_align(4_000, 8).unwrap();
+            let ptr = allocator.realloc(ptr, layout, 10);
+            assert!(!ptr.is_null());
+            assert_eq!(allocator.counter().current(), 10);
+
+            let zeroed = allocator.alloc_zeroed(layout);
+            assert!(!zeroed.is_null());
+            let bytes = core::slice::from_raw_parts(zeroed, 4_000);
+            assert!(bytes.iter().all(|&b| b == 0));
+            allocator.dealloc(zeroed, layout);
+            allocator.dealloc(ptr, Layout::from_size_align(10, 8).unwrap());
+        }
+        assert_eq!(allocator.counter().current(), 0);
+        assert_eq!(allocator.counter().peak(), 4_010);
+    }
+}
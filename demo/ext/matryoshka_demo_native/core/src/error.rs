@@ -0,0 +1,159 @@
+//! Errors reported by the fallible core APIs
+//!
+//! `core_errors!` declares [`CoreError`] along with the metadata bindings
+//! generate their own error types from: every variant's name and code in
+//! [`CoreError::VARIANTS`] and each error's fields through
+//! [`CoreError::for_each_field`]. A variant added here reaches every
+//! binding without any of them listing it; only its message is written by
+//! hand, in the Display impl.
+
+use core::fmt;
+
+/// A field of a [`CoreError`], as [`CoreError::for_each_field`] passes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorField {
+    /// A static description, such as a reason
+    Text(&'static str),
+    /// A number the error concerns, such as a limit
+    Number(u64),
+}
+
+impl From<&'static str> for ErrorField {
+    fn from(text: &'static str) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<u64> for ErrorField {
+    fn from(n: u64) -> Self {
+        Self::Number(n)
+    }
+}
+
+/// Declare `CoreError` with the given variants, each with its stable code,
+/// and the methods listing them
+macro_rules! core_errors {
+    ($(
+        $(#[$doc:meta])*
+        $variant:ident $({ $($field:ident: $ty:ty),* })? => $code:literal,
+    )*) => {
+        /// Why a computation could not produce an answer
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum CoreError {
+            $(
+                $(#[$doc])*
+                $variant $({ $($field: $ty),* })?,
+            )*
+        }
+
+        impl CoreError {
+            /// Name and code of every variant, in declaration order
+            pub const VARIANTS: &'static [(&'static str, &'static str)] =
+                &[$((stringify!($variant), $code)),*];
+
+            /// Name of the variant, such as `LimitTooLarge`
+            pub const fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$variant { .. } => stringify!($variant),)*
+                }
+            }
+
+            /// Stable snake_case name of the variant, for callers and
+            /// bindings to branch on instead of the message
+            pub const fn code(&self) -> &'static str {
+                match self {
+                    $(Self::$variant { .. } => $code,)*
+                }
+            }
+
+            /// Call `f` with the name and value of each of the variant's
+            /// fields, in declaration order
+            pub fn for_each_field(&self, mut f: impl FnMut(&'static str, ErrorField)) {
+                match *self {
+                    $(Self::$variant $({ $($field),* })? => {
+                        $($(f(stringify!($field), ErrorField::from($field));)*)?
+                    })*
+                }
+            }
+        }
+    };
+}
+
+core_errors! {
+    /// An argument is outside the function's domain, e.g. the 0th prime
+    InvalidArgument { reason: &'static str } => "invalid_argument",
+    /// The bitset for `limit` cannot be addressed or allocated on this target,
+    /// or does not fit a [`FixedSieve`](crate::FixedSieve)'s capacity
+    LimitTooLarge { limit: u64 } => "limit_too_large",
+    /// The nth prime was not found below the largest bound searched,
+    /// because it does not fit in a u64
+    EstimateExceeded { n: u64 } => "estimate_exceeded",
+    /// The computation was stopped through its cancellation token
+    Cancelled => "cancelled",
+}
+
+impl fmt::Display for CoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidArgument { reason } => write!(f, "invalid argument: {reason}"),
+            Self::LimitTooLarge { limit } => {
+                write!(f, "limit {limit} is too large to sieve on this target")
+            }
+            Self::EstimateExceeded { n } => {
+                write!(f, "prime number {n} lies beyond the largest bound searched")
+            }
+            Self::Cancelled => write!(f, "computation was cancelled"),
+        }
+    }
+}
+
+impl core::error::Error for CoreError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code() {
+        let reason = "n must be positive";
+        let errors = [
+            CoreError::InvalidArgument { reason },
+            CoreError::LimitTooLarge { limit: 1 },
+            CoreError::EstimateExceeded { n: 1 },
+            CoreError::Cancelled,
+        ];
+        for (error, code) in errors.into_iter().zip([
+            "invalid_argument",
+            "limit_too_large",
+            "estimate_exceeded",
+            "cancelled",
+        ]) {
+            assert_eq!(error.code(), code);
+        }
+        let listed = errors.map(|error| (error.name(), error.code()));
+        assert_eq!(CoreError::VARIANTS, listed);
+    }
+
+    #[test]
+    fn test_for_each_field() {
+        let fields = |error: CoreError| {
+            let mut fields = [None; 2];
+            let mut i = 0;
+            error.for_each_field(|name, value| {
+                fields[i] = Some((name, value));
+                i += 1;
+            });
+            fields
+        };
+        let reason = "n must be positive";
+        assert_eq!(
+            fields(CoreError::InvalidArgument { reason }),
+            [Some(("reason", ErrorField::Text(reason))), None]
+        );
+        assert_eq!(
+            fields(CoreError::LimitTooLarge { limit: 7 }),
+            [Some(("limit", ErrorField::Number(7))), None]
+        );
+        assert_eq!(fields(CoreError::Cancelled), [None, None]);
+    }
+}
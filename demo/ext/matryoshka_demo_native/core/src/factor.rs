@@ -0,0 +1,134 @@
+//! Prime factorization
+
+use alloc::vec::Vec;
+
+use crate::modmath::mul_mod;
+use crate::primality::is_prime;
+use crate::table::PRIME_TABLE;
+
+/// Small primes are stripped by trial division before Pollard's rho kicks in
+const TRIAL_DIVISION_LIMIT: u64 = 1_000;
+
+/// Factor `n` into `(prime, exponent)` pairs sorted by prime
+/// Returns an empty Vec for 0 and 1, which have no prime factorization
+pub fn factorize(n: u64) -> Vec<(u64, u32)> {
+    let mut factors = Vec::new();
+    if n < 2 {
+        return factors;
+    }
+
+    let mut remaining = n;
+
+    for &p in PRIME_TABLE.iter().take_while(|&&p| p < TRIAL_DIVISION_LIMIT) {
+        if p * p > remaining {
+            break;
+        }
+
+        let mut exponent = 0;
+        while remaining.is_multiple_of(p) {
+            remaining /= p;
+            exponent += 1;
+        }
+        if exponent > 0 {
+            factors.push((p, exponent));
+        }
+    }
+
+    // Whatever is left has no factor below the trial limit
+    let mut large = Vec::new();
+    if remaining > 1 {
+        split_large(remaining, &mut large);
+    }
+    large.sort_unstable();
+
+    for p in large {
+        match factors.last_mut() {
+            Some((last, exponent)) if *last == p => *exponent += 1,
+            _ => factors.push((p, 1)),
+        }
+    }
+
+    factors
+}
+
+/// Recursively split `n` into prime factors with Pollard's rho
+fn split_large(n: u64, out: &mut Vec<u64>) {
+    if is_prime(n) {
+        out.push(n);
+        return;
+    }
+
+    let divisor = pollard_rho(n);
+    split_large(divisor, out);
+    split_large(n / divisor, out);
+}
+
+/// Find a non-trivial divisor of the odd composite `n`
+/// Uses Floyd cycle detection on x -> x^2 + c, trying new `c` on failure
+fn pollard_rho(n: u64) -> u64 {
+    let mut c = 1;
+    loop {
+        // Widen before adding so n close to u64::MAX cannot overflow
+        let step = |x: u64| ((mul_mod(x, x, n) as u128 + c as u128) % n as u128) as u64;
+
+        let mut x = 2;
+        let mut y = 2;
+        let mut d = 1;
+        while d == 1 {
+            x = step(x);
+            y = step(step(y));
+            d = gcd(x.abs_diff(y), n);
+        }
+
+        if d != n {
+            return d;
+        }
+        c += 1;
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_factorize_trivial() {
+        assert!(factorize(0).is_empty());
+        assert!(factorize(1).is_empty());
+        assert_eq!(factorize(2), [(2, 1)]);
+        assert_eq!(factorize(97), [(97, 1)]);
+    }
+
+    #[test]
+    fn test_factorize_small() {
+        assert_eq!(factorize(360), [(2, 3), (3, 2), (5, 1)]);
+        assert_eq!(factorize(1024), [(2, 10)]);
+    }
+
+    #[test]
+    fn test_factorize_large_semiprime() {
+        // Both factors are far beyond the trial division limit
+        assert_eq!(
+            factorize(1_000_000_007 * 998_244_353),
+            [(998_244_353, 1), (1_000_000_007, 1)]
+        );
+    }
+
+    #[test]
+    fn test_factorize_large_powers() {
+        assert_eq!(factorize(4_294_967_291 * 4_294_967_291), [(4_294_967_291, 2)]);
+        assert_eq!(
+            factorize(u64::MAX),
+            [(3, 1), (5, 1), (17, 1), (257, 1), (641, 1), (65_537, 1), (6_700_417, 1)]
+        );
+    }
+}
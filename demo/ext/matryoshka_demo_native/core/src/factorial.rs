@@ -0,0 +1,72 @@
+//! Prime factorization of n! via Legendre's formula
+//!
+//! The exponent of a prime p in n! is the sum of floor(n / p^k) for k >= 1,
+//! so n! never has to be computed.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Exponent of the prime `p` in the factorization of `n!`
+///
+/// # Panics
+///
+/// Panics if `p` is less than 2
+pub fn factorial_prime_exponent(n: u64, p: u64) -> u64 {
+    assert!(p >= 2, "factorial_prime_exponent needs a prime, got {p}");
+
+    // floor(n / p^k) = floor(floor(n / p^(k-1)) / p), so p^k is never formed
+    let mut exponent = 0;
+    let mut quotient = n / p;
+    while quotient > 0 {
+        exponent += quotient;
+        quotient /= p;
+    }
+    exponent
+}
+
+/// Factor `n!` into `(prime, exponent)` pairs sorted by prime
+/// Every prime up to `n` appears; 0! and 1! give an empty Vec
+#[cfg(feature = "alloc")]
+pub fn factorize_factorial(n: u64) -> Vec<(u64, u64)> {
+    let mut factors = Vec::new();
+    crate::visit_primes(n, |p| factors.push((p, factorial_prime_exponent(n, p))));
+    factors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_factorial_prime_exponent() {
+        assert_eq!(factorial_prime_exponent(0, 2), 0);
+        assert_eq!(factorial_prime_exponent(10, 2), 8);
+        assert_eq!(factorial_prime_exponent(100, 5), 24); // trailing zeros of 100!
+        assert_eq!(factorial_prime_exponent(u64::MAX, 2), u64::MAX - 64);
+        assert_eq!(factorial_prime_exponent(u64::MAX, u64::MAX), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "needs a prime")]
+    fn test_factorial_prime_exponent_rejects_one() {
+        factorial_prime_exponent(10, 1);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_factorize_factorial() {
+        assert!(factorize_factorial(1).is_empty());
+        assert_eq!(factorize_factorial(10), [(2, 8), (3, 4), (5, 2), (7, 1)]);
+
+        // Multiplying the factorizations of 2..=20 gives the same exponents
+        let mut expected = [0u64; 21];
+        for k in 2..=20 {
+            for (p, e) in crate::factorize(k) {
+                expected[p as usize] += u64::from(e);
+            }
+        }
+        for (p, e) in factorize_factorial(20) {
+            assert_eq!(e, expected[p as usize], "p = {p}");
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Fixed-capacity wheel sieve for allocator-less targets
+
+use crate::wheel::{self, SieveIter};
+use crate::CoreError;
+
+/// Wheel bytes a [`FixedSieve`] needs to cover `0..=limit`
+/// Meant for the const parameter: `FixedSieve<{ fixed_sieve_bytes(10_000) }>`
+pub const fn fixed_sieve_bytes(limit: u64) -> usize {
+    match wheel::bytes_for(limit) {
+        Some(bytes) => bytes,
+        None => panic!("sieve limit exceeds addressable memory"),
+    }
+}
+
+/// Sieve of Eratosthenes backed by an inline `[u8; BYTES]` instead of a Vec
+/// Same mod-30 wheel layout and queries as [`Sieve`](crate::Sieve), but it
+/// never allocates, so it works without the `alloc` feature. It covers at
+/// most `30 * BYTES - 1`; see [`fixed_sieve_bytes`] for sizing.
+pub struct FixedSieve<const BYTES: usize> {
+    bits: [u8; BYTES],
+    limit: u64,
+}
+
+impl<const BYTES: usize> FixedSieve<BYTES> {
+    /// Largest `limit` that fits in `BYTES` wheel bytes
+    pub const CAPACITY: u64 = (BYTES as u64 * 30).saturating_sub(1);
+
+    /// Sieve all numbers up to and including `limit`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `BYTES` is 0 or `limit` exceeds [`Self::CAPACITY`];
+    /// use [`FixedSieve::try_new`] to handle that case
+    pub fn new(limit: u64) -> Self {
+        match Self::try_new(limit) {
+            Ok(sieve) => sieve,
+            Err(_) => panic!("limit {limit} exceeds fixed sieve capacity"),
+        }
+    }
+
+    /// Sieve all numbers up to and including `limit`
+    /// Returns [`CoreError::LimitTooLarge`] if `BYTES` is 0 or `limit` exceeds
+    /// [`Self::CAPACITY`]
+    pub fn try_new(limit: u64) -> Result<Self, CoreError> {
+        if BYTES == 0 || limit > Self::CAPACITY {
+            return Err(CoreError::LimitTooLarge { limit });
+        }
+
+        let mut bits = [0; BYTES];
+        let used = (limit / 30) as usize + 1;
+        bits[..used].fill(0xFF); // All bits set (all potentially prime)
+        wheel::sieve(&mut bits[..used], limit);
+        Ok(Self { bits, limit })
+    }
+
+    /// Upper bound (inclusive) this sieve covers
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Check whether `n` is prime; numbers above `limit` report false
+    pub fn is_prime(&self, n: u64) -> bool {
+        wheel::is_set(self.used(), self.limit, n)
+    }
+
+    /// Count the primes up to and including `limit`
+    pub fn count(&self) -> u64 {
+        wheel::count(self.used(), self.limit)
+    }
+
+    /// Find the nth prime (1-indexed), or None if it lies above `limit`
+    pub fn nth(&self, n: u64) -> Option<u64> {
+        wheel::nth(self.iter(), n)
+    }
+
+    /// Iterate over the primes in ascending order
+    pub fn iter(&self) -> SieveIter<'_> {
+        SieveIter::new(self.used(), self.limit)
+    }
+
+    /// The wheel bytes covering `0..=limit`; the rest of the array is zero
+    fn used(&self) -> &[u8] {
+        &self.bits[..(self.limit / 30) as usize + 1]
+    }
+}
+
+impl<'a, const BYTES: usize> IntoIterator for &'a FixedSieve<BYTES> {
+    type Item = u64;
+    type IntoIter = SieveIter<'a>;
+
+    fn into_iter(self) -> SieveIter<'a> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::is_prime;
+
+    #[test]
+    fn test_fixed_sieve_matches_trial_division() {
+        let sieve = FixedSieve::<{ fixed_sieve_bytes(10_000) }>::new(10_000);
+        for n in 0..=10_000 {
+            assert_eq!(sieve.is_prime(n), is_prime(n), "n = {n}");
+        }
+        assert_eq!(sieve.count(), 1_229);
+        assert_eq!(sieve.nth(1_229), Some(9_973));
+    }
+
+    #[test]
+    fn test_fixed_sieve_below_capacity() {
+        let sieve = FixedSieve::<4>::new(50);
+        assert_eq!(FixedSieve::<4>::CAPACITY, 119);
+        assert_eq!(sieve.limit(), 50);
+        assert_eq!(sieve.iter().last(), Some(47));
+        assert!(!sieve.is_prime(53));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_fixed_sieve_matches_heap_sieve() {
+        use alloc::vec::Vec;
+
+        let fixed = FixedSieve::<{ fixed_sieve_bytes(100_000) }>::new(100_000);
+        let heap = crate::Sieve::new(100_000);
+        assert_eq!(fixed.iter().collect::<Vec<_>>(), heap.primes());
+    }
+
+    #[test]
+    fn test_fixed_sieve_try_new() {
+        assert!(FixedSieve::<4>::try_new(119).is_ok());
+        assert_eq!(
+            FixedSieve::<4>::try_new(120).err(),
+            Some(CoreError::LimitTooLarge { limit: 120 })
+        );
+        assert!(FixedSieve::<0>::try_new(0).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds fixed sieve capacity")]
+    fn test_fixed_sieve_over_capacity_panics() {
+        FixedSieve::<4>::new(120);
+    }
+}
@@ -0,0 +1,92 @@
+//! Integer-only logarithms in Q16 fixed point
+//!
+//! A Q16 value v stands for v / 2^16. Every result is rounded up, so bounds
+//! built from these (such as the nth-prime estimate) stay bounds without a
+//! blanket safety factor, and no floating point is needed under no_std.
+
+/// Fractional bits in the fixed-point logarithms
+pub const LOG_FRAC_BITS: u32 = 16;
+
+/// ln(2) in Q32, rounded up (0.6931471805... * 2^32 = 2977044471.8...)
+const LN2_Q32: u64 = 2_977_044_472;
+
+/// log2(x) in Q16 fixed point, rounded up
+///
+/// # Panics
+///
+/// Panics if `x` is 0
+pub fn log2_fixed(x: u64) -> u64 {
+    assert!(x > 0, "log2 of 0 is undefined");
+    let int_part = 63 - x.leading_zeros() as u64;
+
+    // Mantissa x / 2^int_part in [1, 2), as Q63 in a u128 so squaring fits
+    let mut mantissa = (x as u128) << (63 - int_part);
+    let mut frac_part = 0;
+    for _ in 0..LOG_FRAC_BITS {
+        mantissa = (mantissa * mantissa) >> 63;
+        frac_part <<= 1;
+        if mantissa >= 2 << 63 {
+            frac_part |= 1;
+            mantissa >>= 1;
+        }
+    }
+
+    // The digits above are truncated; one ULP more makes it a ceiling
+    (int_part << LOG_FRAC_BITS) + frac_part + 1
+}
+
+/// ln(x) in Q16 fixed point, rounded up
+///
+/// # Panics
+///
+/// Panics if `x` is 0
+pub fn ln_approx(x: u64) -> u64 {
+    log2_to_ln(log2_fixed(x))
+}
+
+/// ln(y / 2^16) in Q16 for a Q16 value `y`, rounded up
+/// Lets estimators chain logarithms, e.g. ln(ln(x)) = `ln_of_fixed(ln_approx(x))`
+///
+/// # Panics
+///
+/// Panics if `y` stands for less than 1.0 (`y < 1 << LOG_FRAC_BITS`)
+pub fn ln_of_fixed(y: u64) -> u64 {
+    assert!(y >= 1 << LOG_FRAC_BITS, "ln of a Q16 value below 1.0 is negative");
+    let log2 = log2_fixed(y) - ((LOG_FRAC_BITS as u64) << LOG_FRAC_BITS);
+    log2_to_ln(log2)
+}
+
+/// Convert a Q16 base-2 logarithm to a natural one, rounded up
+fn log2_to_ln(log2: u64) -> u64 {
+    (log2 as u128 * LN2_Q32 as u128).div_ceil(1 << 32) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log2_fixed() {
+        assert_eq!(log2_fixed(1), 1);
+        assert_eq!(log2_fixed(1024), (10 << LOG_FRAC_BITS) + 1);
+        // log2(3) = 1.58496..., Q16 = 103,872.9
+        assert_eq!(log2_fixed(3), 103_873);
+        assert_eq!(log2_fixed(u64::MAX), 64 << LOG_FRAC_BITS);
+    }
+
+    #[test]
+    fn test_ln_approx() {
+        // ln(1000) = 6.9077..., Q16 = 452,705.4
+        let ln_1000 = ln_approx(1000);
+        assert!((452_706..=452_708).contains(&ln_1000), "{ln_1000}");
+        // ln(ln(10^6)) = ln(13.8155...) = 2.62579..., Q16 = 172,083.9
+        let ln_ln = ln_of_fixed(ln_approx(1_000_000));
+        assert!((172_084..=172_087).contains(&ln_ln), "{ln_ln}");
+    }
+
+    #[test]
+    #[should_panic(expected = "log2 of 0")]
+    fn test_log2_of_zero_panics() {
+        log2_fixed(0);
+    }
+}
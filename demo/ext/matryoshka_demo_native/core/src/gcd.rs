@@ -0,0 +1,96 @@
+//! Greatest common divisors and least common multiples
+
+/// Greatest common divisor of `a` and `b` (binary GCD); gcd(0, 0) is 0
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if a == 0 || b == 0 {
+        return a | b;
+    }
+
+    // Common factors of two, then only odd values are compared
+    let shift = (a | b).trailing_zeros();
+    let mut a = a >> a.trailing_zeros();
+    let mut b = b;
+    while b != 0 {
+        b >>= b.trailing_zeros();
+        if a > b {
+            (a, b) = (b, a);
+        }
+        b -= a;
+    }
+    a << shift
+}
+
+/// Least common multiple of `a` and `b`, or None if it does not fit a u64
+/// lcm(0, b) is 0
+pub fn lcm(a: u64, b: u64) -> Option<u64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// Extended Euclid: `(g, x, y)` with g = gcd(a, b) and a * x + b * y = g
+/// The coefficients are the minimal ones, |x| <= b / 2g and |y| <= a / 2g,
+/// so they always fit an i64
+pub fn ext_gcd(a: u64, b: u64) -> (u64, i64, i64) {
+    let (mut r0, mut r1) = (a as i128, b as i128);
+    let (mut x0, mut x1) = (1i128, 0i128);
+    let (mut y0, mut y1) = (0i128, 1i128);
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (x0, x1) = (x1, x0 - q * x1);
+        (y0, y1) = (y1, y0 - q * y1);
+    }
+    (r0 as u64, x0 as i64, y0 as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcd() {
+        assert_eq!(gcd(0, 0), 0);
+        assert_eq!(gcd(0, 7), 7);
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(17, 5), 1);
+        assert_eq!(gcd(1 << 40, 3 << 20), 1 << 20);
+        assert_eq!(gcd(u64::MAX, u64::MAX - 1), 1);
+        for a in 0..100 {
+            for b in 0..100 {
+                let expected = (1..=a.max(b)).rev().find(|&d| a % d == 0 && b % d == 0);
+                assert_eq!(gcd(a, b), expected.unwrap_or(0), "gcd({a}, {b})");
+            }
+        }
+    }
+
+    #[test]
+    fn test_lcm() {
+        assert_eq!(lcm(0, 5), Some(0));
+        assert_eq!(lcm(4, 6), Some(12));
+        assert_eq!(lcm(1 << 32, 1 << 31), Some(1 << 32));
+        assert_eq!(lcm(u64::MAX, u64::MAX - 1), None);
+    }
+
+    #[test]
+    fn test_ext_gcd() {
+        assert_eq!(ext_gcd(0, 0), (0, 1, 0));
+        assert_eq!(ext_gcd(240, 46), (2, -9, 47));
+        for (a, b) in [
+            (0, 9),
+            (9, 0),
+            (3, 11),
+            (1 << 63, 3),
+            (u64::MAX, u64::MAX - 1),
+        ] {
+            let (g, x, y) = ext_gcd(a, b);
+            assert_eq!(g, gcd(a, b));
+            assert_eq!(
+                a as i128 * x as i128 + b as i128 * y as i128,
+                g as i128,
+                "({a}, {b})"
+            );
+        }
+    }
+}
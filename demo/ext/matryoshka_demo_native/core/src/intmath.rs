@@ -0,0 +1,123 @@
+//! Integer roots, logarithms and powers without floating point
+
+/// Integer square root: the largest r with r^2 <= n
+#[inline]
+pub fn isqrt(n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+
+    let mut x = n;
+    let mut y = x.div_ceil(2);
+
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+
+    x
+}
+
+/// Integer cube root: the largest r with r^3 <= n
+pub fn icbrt(n: u64) -> u64 {
+    if n < 8 {
+        return u64::from(n > 0);
+    }
+
+    // Newton's method from above: x = 2^ceil(bits / 3) already has x^3 > n,
+    // and each step stays at or above the root until it stops decreasing
+    let bits = 64 - n.leading_zeros();
+    let mut x = 1u64 << bits.div_ceil(3);
+    loop {
+        let y = (2 * x + n / (x * x)) / 3;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+/// Base-2 logarithm rounded down, i.e. the index of the highest set bit
+///
+/// # Panics
+///
+/// Panics if `n` is 0
+pub fn ilog2(n: u64) -> u32 {
+    assert!(n > 0, "ilog2 of 0 is undefined");
+    63 - n.leading_zeros()
+}
+
+/// `base ^ exp` by square-and-multiply, or None if it overflows a u64
+pub fn checked_pow(base: u64, exp: u32) -> Option<u64> {
+    let mut result = 1u64;
+    let mut base = base;
+    let mut exp = exp;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result.checked_mul(base)?;
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = base.checked_mul(base)?;
+        }
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isqrt() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(15), 3);
+        assert_eq!(isqrt(16), 4);
+        assert_eq!(isqrt(u64::MAX), u32::MAX as u64);
+        for n in 0..10_000 {
+            assert_eq!(isqrt(n), n.isqrt(), "n = {n}");
+        }
+    }
+
+    #[test]
+    fn test_icbrt() {
+        assert_eq!(icbrt(0), 0);
+        assert_eq!(icbrt(7), 1);
+        assert_eq!(icbrt(8), 2);
+        assert_eq!(icbrt(u64::MAX), 2_642_245);
+        for r in 1..=2_642_245u64 {
+            let cube = r * r * r;
+            assert_eq!(icbrt(cube), r);
+            assert_eq!(icbrt(cube - 1), r - 1);
+        }
+    }
+
+    #[test]
+    fn test_ilog2() {
+        assert_eq!(ilog2(1), 0);
+        assert_eq!(ilog2(1023), 9);
+        assert_eq!(ilog2(1024), 10);
+        assert_eq!(ilog2(u64::MAX), 63);
+    }
+
+    #[test]
+    #[should_panic(expected = "ilog2 of 0")]
+    fn test_ilog2_of_zero_panics() {
+        ilog2(0);
+    }
+
+    #[test]
+    fn test_checked_pow() {
+        assert_eq!(checked_pow(0, 0), Some(1));
+        assert_eq!(checked_pow(3, 4), Some(81));
+        assert_eq!(checked_pow(2, 63), Some(1 << 63));
+        assert_eq!(checked_pow(2, 64), None);
+        assert_eq!(checked_pow(u64::MAX, 1), Some(u64::MAX));
+        assert_eq!(checked_pow(1 << 32, 2), None);
+        for base in 0..20u64 {
+            for exp in 0..20 {
+                assert_eq!(checked_pow(base, exp), base.checked_pow(exp), "{base}^{exp}");
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Prime-counting estimate from the logarithmic integral
+//!
+//! li(x) = γ + ln ln x + Σ (ln x)^n / (n · n!) has only positive terms, so
+//! it sums exactly in fixed point without floats, allocation or std.
+
+use crate::fixed_log::{self, LOG_FRAC_BITS};
+
+/// Fractional bits of the series accumulator
+const SUM_FRAC_BITS: u32 = 32;
+
+/// Euler–Mascheroni constant γ in Q32 (0.5772156649... * 2^32)
+const EULER_GAMMA_Q32: u128 = 2_479_122_404;
+
+/// li(2) in Q32 (1.0451637801... * 2^32), subtracted to give the offset Li(x)
+const LI_2_Q32: u128 = 4_488_947_838;
+
+/// Estimate π(x), the number of primes up to `x`, as Li(x) = li(x) - li(2)
+/// Runs in O(log x) time, so it can size buffers or progress bars before an
+/// exact count finishes; above 10^5 the estimate is within 0.5% of π(x) and
+/// tightens as x grows (Li(x) overshoots π(x) by roughly √x / ln x)
+pub fn prime_pi_approx(x: u64) -> u64 {
+    if x < 3 {
+        return u64::from(x == 2);
+    }
+
+    let ln_x = fixed_log::ln_approx(x) as u128; // Q16, at least ln 3
+    let ln_ln_x = fixed_log::ln_of_fixed(ln_x as u64) as u128;
+
+    // term_n = (ln x)^n / n! in Q32; the series peaks near n = ln x and its
+    // terms fall below one ULP well before n = 3 ln x
+    let mut sum = EULER_GAMMA_Q32 + (ln_ln_x << (SUM_FRAC_BITS - LOG_FRAC_BITS));
+    let mut term = 1u128 << SUM_FRAC_BITS;
+    let mut n = 1;
+    loop {
+        term = ((term * ln_x) >> LOG_FRAC_BITS) / n;
+        if term == 0 {
+            break;
+        }
+        sum += term / n;
+        n += 1;
+    }
+
+    let li = (sum.saturating_sub(LI_2_Q32) + (1 << (SUM_FRAC_BITS - 1))) >> SUM_FRAC_BITS;
+    u64::try_from(li).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prime_pi_approx_small() {
+        assert_eq!(prime_pi_approx(0), 0);
+        assert_eq!(prime_pi_approx(1), 0);
+        assert_eq!(prime_pi_approx(2), 1);
+        assert_eq!(prime_pi_approx(10), 5); // Li(10) = 5.12, π(10) = 4
+    }
+
+    #[test]
+    fn test_prime_pi_approx_tracks_li() {
+        // (x, round(Li(x)), π(x))
+        for (x, li, pi) in [
+            (100_000u64, 9_630u64, 9_592u64),
+            (1_000_000, 78_627, 78_498),
+            (1_000_000_000, 50_849_234, 50_847_534),
+            (
+                1_000_000_000_000_000_000,
+                24_739_954_309_690_414,
+                24_739_954_287_740_860,
+            ),
+        ] {
+            let estimate = prime_pi_approx(x);
+            // ln x is rounded up to a Q16 ULP, worth about x / 2^16 / ln x in Li
+            let slack = li / 30_000 + 1;
+            assert!(
+                estimate.abs_diff(li) <= slack,
+                "x = {x}: {estimate} vs {li}"
+            );
+            assert!(estimate.abs_diff(pi) * 200 <= pi, "x = {x}");
+        }
+        let li_max = 425_656_284_115_718_614u64;
+        assert!(prime_pi_approx(u64::MAX).abs_diff(li_max) <= li_max / 30_000);
+    }
+}
@@ -3,195 +3,986 @@
 #[cfg(feature = "std")]
 extern crate std;
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use core::ops::{Bound, ControlFlow, RangeBounds};
 
-/// Bitset-based Sieve of Eratosthenes
-/// Uses 1 bit per number for memory efficiency
-struct BitSieve {
-    bits: Vec<u8>,
-    size: usize,
-}
+#[cfg(feature = "atkin")]
+mod atkin;
+#[cfg(feature = "bpsw")]
+mod bpsw;
+#[cfg(feature = "cache")]
+mod cache;
+mod cancel;
+mod counting;
+mod error;
+#[cfg(feature = "alloc")]
+mod factor;
+mod factorial;
+mod fixed;
+mod fixed_log;
+mod gcd;
+mod intmath;
+mod li;
+#[cfg(feature = "alloc")]
+mod lucy;
+mod mersenne;
+mod modmath;
+#[cfg(feature = "alloc")]
+mod mobius;
+#[cfg(feature = "alloc")]
+mod omega;
+#[cfg(feature = "alloc")]
+mod presieve;
+mod primality;
+#[cfg(feature = "alloc")]
+mod primes;
+#[cfg(feature = "rand")]
+mod random;
+#[cfg(feature = "alloc")]
+mod segmented;
+#[cfg(feature = "alloc")]
+mod sieve;
+#[cfg(feature = "alloc")]
+mod storage;
+mod table;
+#[cfg(feature = "alloc")]
+mod totient;
+mod wheel;
 
-impl BitSieve {
-    /// Create a new sieve for numbers up to `limit`
-    fn new(limit: usize) -> Self {
-        let num_bytes = (limit + 1 + 7) / 8; // Ceiling division by 8
-        let mut bits = Vec::with_capacity(num_bytes);
-        bits.resize(num_bytes, 0xFF); // All bits set (all potentially prime)
+#[cfg(feature = "bpsw")]
+pub use bpsw::{is_prime_u128, is_probable_prime};
+#[cfg(feature = "cache")]
+pub use cache::{
+    cached_count_primes, cached_is_prime, cached_limit, cached_memory_usage, clear_cache,
+    is_prime_hybrid,
+};
+pub use cancel::CancelToken;
+pub use counting::{CountingAlloc, MemoryCounter};
+pub use error::{CoreError, ErrorField};
+#[cfg(feature = "alloc")]
+pub use factor::factorize;
+pub use factorial::factorial_prime_exponent;
+#[cfg(feature = "alloc")]
+pub use factorial::factorize_factorial;
+pub use fixed::{fixed_sieve_bytes, FixedSieve};
+pub use fixed_log::{ln_approx, ln_of_fixed, log2_fixed, LOG_FRAC_BITS};
+pub use gcd::{ext_gcd, gcd, lcm};
+pub use intmath::{checked_pow, icbrt, ilog2, isqrt};
+pub use li::prime_pi_approx;
+#[cfg(feature = "alloc")]
+pub use lucy::{count_primes_fast, try_count_primes_fast};
+pub use mersenne::{is_mersenne_prime, MAX_MERSENNE_EXPONENT};
+pub use modmath::{crt, inv_mod, mul_mod, pow_mod};
+#[cfg(feature = "alloc")]
+pub use mobius::{is_squarefree, mobius_up_to};
+#[cfg(feature = "alloc")]
+pub use omega::{almost_primes_up_to, big_omega, is_semiprime, omega};
+pub use primality::{goldbach_pair, is_prime, next_prime, next_safe_prime, prev_prime};
+#[cfg(feature = "alloc")]
+pub use primes::Primes;
+#[cfg(feature = "rand")]
+pub use random::random_prime_in_range;
+#[cfg(feature = "alloc")]
+use segmented::SegmentedSieve;
+#[cfg(feature = "alloc")]
+pub use sieve::{DecodeError, Sieve, SieveAlgorithm};
+#[cfg(feature = "alloc")]
+pub use storage::BitStorage;
+#[cfg(feature = "mmap")]
+pub use storage::MmapStorage;
+pub use wheel::SieveIter;
+#[cfg(feature = "alloc")]
+pub use totient::{totient, totients_up_to};
 
-        let mut sieve = Self {
-            bits,
-            size: limit + 1,
-        };
+/// Version of this crate, as in Cargo.toml
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-        // 0 and 1 are not prime
-        sieve.clear(0);
-        sieve.clear(1);
+/// Names of the cargo features this build was compiled with
+pub fn features() -> impl Iterator<Item = &'static str> {
+    [
+        ("alloc", cfg!(feature = "alloc")),
+        ("std", cfg!(feature = "std")),
+        ("rayon", cfg!(feature = "rayon")),
+        ("atkin", cfg!(feature = "atkin")),
+        ("bpsw", cfg!(feature = "bpsw")),
+        ("cache", cfg!(feature = "cache")),
+        ("rand", cfg!(feature = "rand")),
+        ("mmap", cfg!(feature = "mmap")),
+        ("simd", cfg!(feature = "simd")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then_some(name))
+}
 
-        sieve
-    }
+/// Whether sieve segments are presieved in SIMD lanes: built with `simd`
+/// for x86_64 (SSE2) or aarch64 (NEON), baselines every CPU of those
+/// targets has; elsewhere the feature falls back to scalar code
+pub const SIMD_PRESIEVE: bool = cfg!(all(
+    feature = "simd",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+));
 
-    /// Check if a number is marked as prime
-    #[inline]
-    fn is_set(&self, n: usize) -> bool {
-        if n >= self.size {
-            return false;
-        }
-        let byte_idx = n / 8;
-        let bit_idx = n % 8;
-        (self.bits[byte_idx] & (1 << bit_idx)) != 0
-    }
+/// Limits above this are counted with `count_primes_fast` instead of a sieve
+/// Lucy_Hedgehog already wins by 10^4 and the gap grows as O(n^(1/4))
+#[cfg(feature = "alloc")]
+const FAST_COUNT_THRESHOLD: u64 = 1 << 16;
 
-    /// Mark a number as composite (not prime)
-    #[inline]
-    fn clear(&mut self, n: usize) {
-        if n >= self.size {
-            return;
-        }
-        let byte_idx = n / 8;
-        let bit_idx = n % 8;
-        self.bits[byte_idx] &= !(1 << bit_idx);
-    }
-
-    /// Run the sieve algorithm
-    fn run_sieve(&mut self) {
-        let limit = self.size - 1;
-        let sqrt_limit = isqrt(limit);
-
-        let mut i = 2;
-        while i <= sqrt_limit {
-            if self.is_set(i) {
-                // Mark all multiples of i as composite
-                let mut j = i * i;
-                while j <= limit {
-                    self.clear(j);
-                    j += i;
+/// How [`try_count_primes_using`] and [`CountOptions`] count a range
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CountAlgorithm {
+    /// Sieve small windows and count large ones sublinearly, whichever is
+    /// cheaper for the range
+    #[default]
+    Auto,
+    /// Sieve the range segment by segment, O(sqrt(high) + width)
+    Segmented,
+    /// Lucy_Hedgehog's count at both ends, O(high^(3/4)) whatever the width
+    Sublinear,
+}
+
+#[cfg(feature = "alloc")]
+impl CountAlgorithm {
+    /// The algorithm counting `low..=high` uses: `Auto` becomes whichever
+    /// of the others is cheaper for the range, the rest stay as they are
+    pub fn resolve(self, low: u64, high: u64) -> Self {
+        match self {
+            // Sieving a window costs O(sqrt(high) + width) and the fast
+            // count O(high^(3/4)) per end, so windows up to about
+            // high^(3/4) are sieved
+            CountAlgorithm::Auto => {
+                let quarter_root = isqrt(isqrt(high));
+                if high <= FAST_COUNT_THRESHOLD || high - low <= isqrt(high) * quarter_root {
+                    CountAlgorithm::Segmented
+                } else {
+                    CountAlgorithm::Sublinear
                 }
             }
-            i += 1;
+            algorithm => algorithm,
         }
     }
+}
 
-    /// Count how many primes are in the sieve
-    fn count_primes(&self) -> usize {
-        let mut count = 0;
-        // Only count bits within our actual size limit
-        for i in 0..self.size {
-            if self.is_set(i) {
-                count += 1;
-            }
+/// Tuning for [`try_count_primes_with_options`]
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountOptions {
+    /// Which method counts the range
+    pub algorithm: CountAlgorithm,
+    /// Bytes of bitset per segment when sieving, rounded down to whole u64
+    /// words (at least one); the 32 KiB default fits in L1 cache
+    pub segment_bytes: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl Default for CountOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: CountAlgorithm::Auto,
+            segment_bytes: segmented::SEGMENT_WORDS * 8,
         }
-        count
     }
+}
 
-    /// Find the nth prime (1-indexed)
-    fn nth_prime(&self, n: usize) -> Option<usize> {
-        if n == 0 {
-            return None;
-        }
+/// What [`try_count_primes_with_stats`] counted and what the count cost
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountStats {
+    /// Prime numbers in the range
+    pub count: u64,
+    /// The algorithm that counted, never `Auto`
+    pub algorithm: CountAlgorithm,
+    /// Segments sieved; 0 for the sublinear count
+    pub segments: u64,
+    /// Most bytes of working memory (base primes and bitset, or the
+    /// sublinear tables) held at once
+    pub peak_bytes: usize,
+}
 
-        let mut count = 0;
-        for i in 0..self.size {
-            if self.is_set(i) {
-                count += 1;
-                if count == n {
-                    return Some(i);
-                }
-            }
-        }
-        None
+/// Count the prime numbers in `range`, e.g. `count_primes(..=1000)` or
+/// `count_primes(10..100)`
+/// Small ranges are sieved segment by segment; larger ones use the sublinear
+/// `count_primes_fast`, which the `rayon` feature parallelizes
+///
+/// # Panics
+///
+/// Panics if `count_primes_fast` cannot allocate its tables;
+/// use [`try_count_primes`] to handle that case
+#[cfg(feature = "alloc")]
+pub fn count_primes(range: impl RangeBounds<u64>) -> u64 {
+    match try_count_primes(range) {
+        Ok(count) => count,
+        Err(error) => panic!("{error}"),
     }
 }
 
-/// Integer square root (no_std compatible)
-#[inline]
-fn isqrt(n: usize) -> usize {
-    if n < 2 {
-        return n;
-    }
+/// Count the prime numbers in `range`
+/// Returns [`CoreError::LimitTooLarge`] when the tables for its upper end
+/// cannot be allocated
+#[cfg(feature = "alloc")]
+pub fn try_count_primes(range: impl RangeBounds<u64>) -> Result<u64, CoreError> {
+    try_count_primes_with_cancel(range, &CancelToken::new())
+}
+
+/// Count the prime numbers in `range`, checking `cancel` once per segment
+/// (or per sieving prime of the fast count)
+/// Returns [`CoreError::Cancelled`] once it is set
+#[cfg(feature = "alloc")]
+pub fn try_count_primes_with_cancel(
+    range: impl RangeBounds<u64>,
+    cancel: &CancelToken,
+) -> Result<u64, CoreError> {
+    try_count_primes_using(range, CountAlgorithm::Auto, cancel)
+}
 
-    let mut x = n;
-    let mut y = (x + 1) / 2;
+/// Count the prime numbers in `range` with `algorithm`, checking `cancel`
+/// as [`try_count_primes_with_cancel`] does
+#[cfg(feature = "alloc")]
+pub fn try_count_primes_using(
+    range: impl RangeBounds<u64>,
+    algorithm: CountAlgorithm,
+    cancel: &CancelToken,
+) -> Result<u64, CoreError> {
+    try_count_primes_with_observer(range, algorithm, |_, _| cancel.observe())
+}
+
+/// Count the prime numbers in `range` with `algorithm`, calling
+/// `observer(done, total)` as the work advances so callers can report
+/// progress: per segment when sieving, per sieving prime up to sqrt(high)
+/// for the sublinear count
+/// Returns [`CoreError::Cancelled`] as soon as the observer breaks
+#[cfg(feature = "alloc")]
+pub fn try_count_primes_with_observer(
+    range: impl RangeBounds<u64>,
+    algorithm: CountAlgorithm,
+    observer: impl FnMut(u64, u64) -> ControlFlow<()>,
+) -> Result<u64, CoreError> {
+    let options = CountOptions {
+        algorithm,
+        ..CountOptions::default()
+    };
+    try_count_primes_with_options(range, options, observer)
+}
 
-    while y < x {
-        x = y;
-        y = (x + n / x) / 2;
+/// Count the prime numbers in `range` as `options` describe, reporting to
+/// `observer` as [`try_count_primes_with_observer`] does
+#[cfg(feature = "alloc")]
+pub fn try_count_primes_with_options(
+    range: impl RangeBounds<u64>,
+    options: CountOptions,
+    observer: impl FnMut(u64, u64) -> ControlFlow<()>,
+) -> Result<u64, CoreError> {
+    try_count_primes_with_stats(range, options, observer).map(|stats| stats.count)
+}
+
+/// [`try_count_primes_with_options`], also reporting which algorithm
+/// counted, how many segments it sieved and its peak working memory
+#[cfg(feature = "alloc")]
+pub fn try_count_primes_with_stats(
+    range: impl RangeBounds<u64>,
+    options: CountOptions,
+    mut observer: impl FnMut(u64, u64) -> ControlFlow<()>,
+) -> Result<CountStats, CoreError> {
+    let Some((low, high)) = inclusive_bounds(range) else {
+        return Ok(CountStats {
+            count: 0,
+            algorithm: options.algorithm.resolve(0, 0),
+            segments: 0,
+            peak_bytes: 0,
+        });
+    };
+
+    if options.algorithm.resolve(low, high) == CountAlgorithm::Segmented {
+        return segmented::count_with(low, high, options.segment_bytes / 8, observer);
     }
 
-    x
+    let stats = |count| CountStats {
+        count,
+        algorithm: CountAlgorithm::Sublinear,
+        segments: 0,
+        // The ends are counted one after the other, the upper one last
+        peak_bytes: lucy::table_bytes(high),
+    };
+    let Some(below_low) = low.checked_sub(1) else {
+        return lucy::count_with(high, observer).map(stats);
+    };
+    // Both ends report into one total
+    let (lower, upper) = (isqrt(below_low), isqrt(high));
+    let below = lucy::count_with(below_low, |done, _| observer(done, lower + upper))?;
+    let count = lucy::count_with(high, |done, _| observer(lower + done, lower + upper))?;
+    Ok(stats(count - below))
 }
 
-/// Count prime numbers up to and including `limit`
-pub fn count_primes(limit: usize) -> usize {
+/// Resolve `range` to inclusive `(low, high)`, or None if it holds no u64
+#[cfg(feature = "alloc")]
+fn inclusive_bounds(range: impl RangeBounds<u64>) -> Option<(u64, u64)> {
+    let low = match range.start_bound() {
+        Bound::Included(&low) => low,
+        Bound::Excluded(&low) => low.checked_add(1)?,
+        Bound::Unbounded => 0,
+    };
+    let high = match range.end_bound() {
+        Bound::Included(&high) => high,
+        Bound::Excluded(&high) => high.checked_sub(1)?,
+        Bound::Unbounded => u64::MAX,
+    };
+    (low <= high).then_some((low, high))
+}
+
+/// Sum the prime numbers up to and including `limit`
+/// Accumulates in u128, which cannot overflow: the sum of all primes below
+/// 2^64 is less than 2^128
+#[cfg(feature = "alloc")]
+pub fn sum_of_primes(limit: u64) -> u128 {
     if limit < 2 {
         return 0;
     }
 
-    let mut sieve = BitSieve::new(limit);
-    sieve.run_sieve();
-    sieve.count_primes()
+    let mut sieve = SegmentedSieve::new(0, limit);
+    let mut sum = 0;
+    while let Some(segment) = sieve.next_segment() {
+        sum += segment.sum();
+    }
+    sum
+}
+
+/// Collect all prime numbers up to and including `limit` in ascending order
+#[cfg(feature = "alloc")]
+pub fn primes_up_to(limit: u64) -> Vec<u64> {
+    let mut primes = Vec::new();
+    if limit < 2 {
+        return primes;
+    }
+
+    let mut sieve = SegmentedSieve::new(0, limit);
+    while let Some(segment) = sieve.next_segment() {
+        segment.collect_into(&mut primes);
+    }
+    primes
+}
+
+/// Collect the primes in `[low, high]` (both inclusive) in ascending order
+/// Only the requested window is sieved, using base primes up to sqrt(high)
+#[cfg(feature = "alloc")]
+pub fn primes_in_range(low: u64, high: u64) -> Vec<u64> {
+    let mut primes = Vec::new();
+    if high < 2 || low > high {
+        return primes;
+    }
+
+    let mut sieve = SegmentedSieve::new(low, high);
+    while let Some(segment) = sieve.next_segment() {
+        segment.collect_into(&mut primes);
+    }
+    primes
+}
+
+/// Collect the primes in `range` in ascending order, e.g. `primes_in(10..100)`
+/// Only the requested window is sieved, as in [`primes_in_range`]
+#[cfg(feature = "alloc")]
+pub fn primes_in(range: impl RangeBounds<u64>) -> Vec<u64> {
+    match inclusive_bounds(range) {
+        Some((low, high)) => primes_in_range(low, high),
+        None => Vec::new(),
+    }
+}
+
+/// Collect the primes in `range` as [`primes_in`] does, checking `cancel`
+/// once per segment
+/// Returns [`CoreError::Cancelled`] once it is set
+#[cfg(feature = "alloc")]
+pub fn try_primes_in_with_cancel(
+    range: impl RangeBounds<u64>,
+    cancel: &CancelToken,
+) -> Result<Vec<u64>, CoreError> {
+    let mut primes = Vec::new();
+    let Some((low, high)) = inclusive_bounds(range).filter(|&(_, high)| high >= 2) else {
+        return Ok(primes);
+    };
+
+    let mut sieve = SegmentedSieve::new(low, high);
+    while let Some(segment) = sieve.next_segment() {
+        cancel.check()?;
+        segment.collect_into(&mut primes);
+    }
+    Ok(primes)
+}
+
+/// Collect every twin prime pair `(p, p + 2)` with `p + 2 <= limit`
+#[cfg(feature = "alloc")]
+pub fn twin_primes_up_to(limit: u64) -> Vec<(u64, u64)> {
+    let mut twins = Vec::new();
+    let mut previous = None;
+    visit_primes(limit, |p| {
+        if previous == Some(p.wrapping_sub(2)) {
+            twins.push((p - 2, p));
+        }
+        previous = Some(p);
+    });
+    twins
+}
+
+/// Find the largest gap between consecutive primes up to `limit`
+/// Returns the first pair `(p, q)` achieving it, or None below 3
+#[cfg(feature = "alloc")]
+pub fn max_prime_gap(limit: u64) -> Option<(u64, u64)> {
+    let mut best: Option<(u64, u64)> = None;
+    let mut previous = None;
+    visit_primes(limit, |q| {
+        if let Some(p) = previous
+            && best.is_none_or(|(a, b)| q - p > b - a)
+        {
+            best = Some((p, q));
+        }
+        previous = Some(q);
+    });
+    best
+}
+
+/// Collect every Sophie Germain prime `p <= limit`: primes whose `2p + 1`
+/// is prime too
+/// A single sieve up to `2 * limit + 1` answers both checks
+#[cfg(feature = "alloc")]
+pub fn sophie_germain_primes_up_to(limit: u64) -> Vec<u64> {
+    // Past this, 2p + 1 no longer fits in a u64
+    let limit = limit.min((u64::MAX - 1) / 2);
+    let sieve = Sieve::new(2 * limit + 1);
+    sieve
+        .iter()
+        .take_while(|&p| p <= limit)
+        .filter(|&p| sieve.is_prime(2 * p + 1))
+        .collect()
+}
+
+/// Collect every prime `p <= limit` with `p ≡ residue (mod modulus)` in
+/// ascending order, matching each prime as its segment is scanned
+/// When `residue` shares a factor with `modulus` at most one prime
+/// qualifies, and it is checked without sieving
+/// Returns [`CoreError::InvalidArgument`] for a zero modulus
+#[cfg(feature = "alloc")]
+pub fn primes_mod(limit: u64, modulus: u64, residue: u64) -> Result<Vec<u64>, CoreError> {
+    if modulus == 0 {
+        return Err(CoreError::InvalidArgument {
+            reason: "modulus must be positive",
+        });
+    }
+
+    let residue = residue % modulus;
+    let mut primes = Vec::new();
+    if gcd(residue, modulus) > 1 {
+        // Such a prime divides both, so it is the residue itself, or the
+        // modulus when the residue is 0
+        let p = if residue == 0 { modulus } else { residue };
+        if p <= limit && is_prime(p) {
+            primes.push(p);
+        }
+        return Ok(primes);
+    }
+
+    visit_primes(limit, |p| {
+        if p % modulus == residue {
+            primes.push(p);
+        }
+    });
+    Ok(primes)
+}
+
+/// Call `f` with every prime up to and including `limit` in ascending order
+/// Primes are produced one segment at a time and never collected; returning
+/// `ControlFlow::Break(())` from `f` stops the sweep, which is then reported
+/// back as `Break`
+#[cfg(feature = "alloc")]
+pub fn for_each_prime(limit: u64, mut f: impl FnMut(u64) -> ControlFlow<()>) -> ControlFlow<()> {
+    if limit < 2 {
+        return ControlFlow::Continue(());
+    }
+
+    let mut sieve = SegmentedSieve::new(0, limit);
+    while let Some(segment) = sieve.next_segment() {
+        segment.try_for_each(&mut f)?;
+    }
+    ControlFlow::Continue(())
+}
+
+/// `for_each_prime` for callers that always visit every prime
+#[cfg(feature = "alloc")]
+fn visit_primes(limit: u64, mut f: impl FnMut(u64)) {
+    let _ = for_each_prime(limit, |p| {
+        f(p);
+        ControlFlow::Continue(())
+    });
+}
+
+/// Find the nth prime number (1-indexed)
+/// Sieves up to a proven upper bound for p_n, continuing past it with a
+/// doubled bound should it ever fall short; returns None only if n is 0 or
+/// p_n does not fit in a u64 (see [`try_nth_prime`] to tell them apart)
+#[cfg(feature = "alloc")]
+pub fn nth_prime(n: u64) -> Option<u64> {
+    try_nth_prime(n).ok()
 }
 
 /// Find the nth prime number (1-indexed)
-/// Returns None if n is 0 or if the estimate is too low
-pub fn nth_prime(n: usize) -> Option<usize> {
+/// Returns [`CoreError::InvalidArgument`] for n = 0 and
+/// [`CoreError::EstimateExceeded`] when p_n does not fit in a u64
+#[cfg(feature = "alloc")]
+pub fn try_nth_prime(n: u64) -> Result<u64, CoreError> {
+    try_nth_prime_with_cancel(n, &CancelToken::new())
+}
+
+/// Find the nth prime number (1-indexed), checking `cancel` once per segment
+/// Returns [`CoreError::Cancelled`] once it is set
+#[cfg(feature = "alloc")]
+pub fn try_nth_prime_with_cancel(n: u64, cancel: &CancelToken) -> Result<u64, CoreError> {
     if n == 0 {
-        return None;
+        return Err(CoreError::InvalidArgument {
+            reason: "primes are numbered from 1",
+        });
+    }
+    if n <= table::PRIME_TABLE_LEN as u64 {
+        return Ok(table::PRIME_TABLE[n as usize - 1]);
     }
 
     // Estimate upper bound for nth prime using integer approximation
-    // For small n, use lookup; for large n, use p_n < n * (ln(n) + ln(ln(n)))
-    // We approximate without floating point for no_std compatibility
-    let limit = estimate_nth_prime_upper_bound(n);
+    // p_n < n * (ln(n) + ln(ln(n))), computed without floating point
+    // for no_std compatibility
+    nth_prime_in(0, n, estimate_nth_prime_upper_bound(n), cancel)?
+        .ok_or(CoreError::EstimateExceeded { n })
+}
 
-    let mut sieve = BitSieve::new(limit);
-    sieve.run_sieve();
-    sieve.nth_prime(n)
+/// Find the nth prime (1-indexed) strictly greater than `start`
+/// Sieves segments upward from `start` only, so skipping a known prefix costs
+/// nothing; returns None if n is 0 or the answer does not fit in a u64
+#[cfg(feature = "alloc")]
+pub fn nth_prime_after(start: u64, n: u64) -> Option<u64> {
+    if n == 0 {
+        return None;
+    }
+    let low = start.checked_add(1)?;
+
+    // Primes near x are about ln(x) apart; a quarter more absorbs local gaps,
+    // and the sweep widens the window should it still fall short
+    let density = fixed_log::ln_approx(low.saturating_add(n).max(3));
+    let width = (n as u128 * density as u128 * 5 / 4).div_ceil(1 << LOG_FRAC_BITS);
+    let high = u64::try_from(low as u128 + width).unwrap_or(u64::MAX);
+    nth_prime_in(low, n, high, &CancelToken::new()).ok().flatten()
+}
+
+/// Times `nth_prime` doubles its window before giving up
+#[cfg(feature = "alloc")]
+const NTH_PRIME_RETRIES: u32 = 8;
+
+/// Sweep segments of `[low, high]` looking for the nth prime in it, doubling
+/// the window and carrying on from where the sweep stopped if fewer than n
+/// primes lie inside; `cancel` is checked before every segment
+#[cfg(feature = "alloc")]
+fn nth_prime_in(
+    low: u64,
+    n: u64,
+    high: u64,
+    cancel: &CancelToken,
+) -> Result<Option<u64>, CoreError> {
+    let mut sieve = SegmentedSieve::new(low, high);
+    let mut seen = 0;
+    for _ in 0..=NTH_PRIME_RETRIES {
+        cancel.check()?;
+        while let Some(segment) = sieve.next_segment() {
+            let count = segment.count() as u64;
+            if seen + count >= n {
+                return Ok(segment.nth((n - seen) as usize));
+            }
+            seen += count;
+            cancel.check()?;
+        }
+
+        if sieve.high() == u64::MAX {
+            break;
+        }
+        let width = sieve.high() - low + 1;
+        sieve.extend_to(sieve.high().saturating_add(width));
+    }
+    Ok(None)
 }
 
 /// Estimate an upper bound for the nth prime number
 /// Uses integer-only approximation to avoid floating point
-fn estimate_nth_prime_upper_bound(n: usize) -> usize {
+#[cfg(feature = "alloc")]
+fn estimate_nth_prime_upper_bound(n: u64) -> u64 {
     if n < 6 {
         return 15;
     }
 
-    // For n >= 6, use approximation: p_n < n * (ln(n) + ln(ln(n)))
-    // We use integer approximations: ln(x) ≈ log2(x) * 0.693
-    // Simplified: p_n < n * log2(n) for a safe upper bound
-    let log2_n = (usize::BITS - n.leading_zeros()) as usize;
+    // For n >= 6, p_n < n * (ln(n) + ln(ln(n))) (Rosser, refined by Dusart)
+    // Both logarithms are computed in Q16 fixed point and rounded up, so the
+    // result stays an upper bound without a blanket safety factor
+    let ln_n = fixed_log::ln_approx(n);
+    let ln_ln_n = fixed_log::ln_of_fixed(ln_n);
 
-    // Add extra margin for safety
-    n * log2_n * 2
+    let bound = (n as u128 * (ln_n + ln_ln_n) as u128).div_ceil(1 << LOG_FRAC_BITS);
+    u64::try_from(bound).unwrap_or(u64::MAX)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_features_match_cfg() {
+        assert!(features().any(|name| name == "alloc"));
+        assert!(!SIMD_PRESIEVE || features().any(|name| name == "simd"));
+        assert_eq!(features().any(|name| name == "rayon"), cfg!(feature = "rayon"));
+        assert_eq!(features().any(|name| name == "mmap"), cfg!(feature = "mmap"));
+        assert_eq!(VERSION.split('.').count(), 3);
+    }
+
     #[test]
     fn test_count_primes_small() {
-        assert_eq!(count_primes(0), 0);
-        assert_eq!(count_primes(1), 0);
-        assert_eq!(count_primes(2), 1);
-        assert_eq!(count_primes(3), 2);
-        assert_eq!(count_primes(10), 4);
+        assert_eq!(count_primes(..=0), 0);
+        assert_eq!(count_primes(..=1), 0);
+        assert_eq!(count_primes(..=2), 1);
+        assert_eq!(count_primes(..=3), 2);
+        assert_eq!(count_primes(..=10), 4);
     }
 
     #[test]
     fn test_count_primes_100() {
-        assert_eq!(count_primes(100), 25);
+        assert_eq!(count_primes(..=100), 25);
     }
 
     #[test]
     fn test_count_primes_1000() {
-        assert_eq!(count_primes(1000), 168);
+        assert_eq!(count_primes(..=1000), 168);
     }
 
     #[test]
     fn test_count_primes_10000() {
-        assert_eq!(count_primes(10_000), 1229);
+        assert_eq!(count_primes(..=10_000), 1229);
+    }
+
+    #[test]
+    fn test_count_primes_large() {
+        assert_eq!(count_primes(..=1_000_000), 78_498);
+        assert_eq!(count_primes(..=10_000_000), 664_579);
+        assert_eq!(count_primes(..=1_000_000_000), 50_847_534);
+    }
+
+    #[test]
+    fn test_count_primes_across_segments() {
+        // count_primes no longer sieves at these sizes; keep the sieve covered
+        assert_eq!(segmented::count(0, 1_000_000), 78_498);
+        assert_eq!(segmented::count(0, 10_000_000), 664_579);
+    }
+
+    #[test]
+    fn test_count_primes_agrees_across_threshold() {
+        for limit in FAST_COUNT_THRESHOLD - 50..FAST_COUNT_THRESHOLD + 50 {
+            assert_eq!(count_primes(..=limit), segmented::count(0, limit));
+        }
+    }
+
+    #[test]
+    fn test_count_primes_ranges() {
+        assert_eq!(count_primes(..1000), 168);
+        assert_eq!(count_primes(10..100), 21);
+        assert_eq!(count_primes(11..=97), 21);
+        assert_eq!(count_primes((Bound::Included(100), Bound::Excluded(10))), 0);
+        assert_eq!(count_primes(5..5), 0);
+        assert_eq!(count_primes((Bound::Excluded(u64::MAX), Bound::Unbounded)), 0);
+        // A window sieved directly and one counted as a difference of two fast counts
+        assert_eq!(count_primes(1_000_000_000..=1_000_000_100), 7);
+        assert_eq!(count_primes(500_000..=1_000_000), 78_498 - 41_538);
+    }
+
+    #[test]
+    fn test_count_algorithms_agree() {
+        let cancel = CancelToken::new();
+        for range in [
+            0..=0,
+            0..=1,
+            2..=2,
+            0..=100_000,
+            1_000..=2_000_000,
+            999_000..=1_000_000,
+        ] {
+            let expected = count_primes(range.clone());
+            for algorithm in [CountAlgorithm::Segmented, CountAlgorithm::Sublinear] {
+                let count = try_count_primes_using(range.clone(), algorithm, &cancel);
+                assert_eq!(count, Ok(expected), "{algorithm:?} over {range:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_observer_reports_progress() {
+        for (range, algorithm) in [
+            (0..=10_000_000, CountAlgorithm::Segmented),
+            (0..=10_000_000, CountAlgorithm::Sublinear),
+            (1_000_000..=10_000_000, CountAlgorithm::Sublinear),
+        ] {
+            let mut seen = alloc::vec::Vec::new();
+            let count = try_count_primes_with_observer(range.clone(), algorithm, |done, total| {
+                seen.push((done, total));
+                ControlFlow::Continue(())
+            });
+            assert_eq!(count, Ok(count_primes(range)), "{algorithm:?}");
+            let &(done, total) = seen.last().unwrap();
+            assert_eq!(done, total, "{algorithm:?}");
+            assert!(seen.windows(2).all(|w| w[0].0 <= w[1].0 && w[0].1 == w[1].1));
+        }
+
+        let mut calls = 0;
+        let third_call = |_, _| {
+            calls += 1;
+            if calls == 3 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        };
+        let stopped =
+            try_count_primes_with_observer(..=10_000_000, CountAlgorithm::Auto, third_call);
+        assert_eq!(stopped, Err(CoreError::Cancelled));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_count_algorithm_resolve() {
+        use CountAlgorithm::*;
+        assert_eq!(Auto.resolve(0, 1_000), Segmented);
+        assert_eq!(Auto.resolve(0, 10_000_000), Sublinear);
+        assert_eq!(Auto.resolve(10_000_000, 10_001_000), Segmented);
+        assert_eq!(Segmented.resolve(0, 10_000_000), Segmented);
+        assert_eq!(Sublinear.resolve(0, 1_000), Sublinear);
+    }
+
+    #[test]
+    fn test_count_primes_with_stats() {
+        let go = |_, _| ControlFlow::Continue(());
+        let sieved = CountOptions {
+            algorithm: CountAlgorithm::Segmented,
+            ..CountOptions::default()
+        };
+        let stats = try_count_primes_with_stats(..=1_000_000, sieved, go).unwrap();
+        assert_eq!(stats.count, 78_498);
+        assert_eq!(stats.algorithm, CountAlgorithm::Segmented);
+        assert_eq!(stats.segments, 1_000_000 / segmented::SEGMENT_SPAN + 1);
+        assert!(stats.peak_bytes >= segmented::SEGMENT_WORDS * 8);
+
+        let options = CountOptions::default();
+        let stats = try_count_primes_with_stats(10..=100_000_000, options, go).unwrap();
+        assert_eq!(stats.count, 5_761_455 - 4);
+        assert_eq!(stats.algorithm, CountAlgorithm::Sublinear);
+        assert_eq!(stats.segments, 0);
+        assert_eq!(stats.peak_bytes, 2 * 8 * 10_001);
+
+        let empty = try_count_primes_with_stats(10..10, options, go).unwrap();
+        assert_eq!((empty.count, empty.segments, empty.peak_bytes), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_count_segment_sizes_agree() {
+        for segment_bytes in [0, 8, 1_000, 4_096, 1 << 20] {
+            let options = CountOptions {
+                algorithm: CountAlgorithm::Segmented,
+                segment_bytes,
+            };
+            let mut segments = 0;
+            let count = try_count_primes_with_options(3..=2_000_000, options, |_, total| {
+                segments = total;
+                ControlFlow::Continue(())
+            });
+            assert_eq!(count, Ok(148_932), "{segment_bytes} bytes");
+            let span = (segment_bytes / 8).max(1) as u64 * 128;
+            assert_eq!(segments, (2_000_000 - 3) / span + 1);
+        }
+    }
+
+    #[test]
+    fn test_cancelled_token_stops_counting() {
+        let cancel = CancelToken::new();
+        assert_eq!(try_count_primes_with_cancel(..=1_000_000, &cancel), Ok(78_498));
+        assert_eq!(try_count_primes_with_cancel(10..100, &cancel), Ok(21));
+        assert_eq!(try_nth_prime_with_cancel(100_000, &cancel), Ok(1_299_709));
+
+        cancel.cancel();
+        for range in [..=100, ..=1_000_000_000] {
+            assert_eq!(
+                try_count_primes_with_cancel(range, &cancel),
+                Err(CoreError::Cancelled)
+            );
+        }
+        assert_eq!(try_nth_prime_with_cancel(100_000, &cancel), Err(CoreError::Cancelled));
+        // Answers from the prime table never reach a checkpoint
+        assert_eq!(try_nth_prime_with_cancel(10, &cancel), Ok(29));
+    }
+
+    #[test]
+    fn test_primes_in() {
+        assert_eq!(primes_in(10..30), [11, 13, 17, 19, 23, 29]);
+        assert_eq!(primes_in(..=10), [2, 3, 5, 7]);
+        assert_eq!(primes_in(7..=7), [7]);
+        assert!(primes_in((Bound::Included(8), Bound::Included(7))).is_empty());
+        assert!(primes_in(..0).is_empty());
+        let t = 1_000_000_000_000;
+        assert_eq!(primes_in(t..t + 100), [t + 39, t + 61, t + 63, t + 91]);
+    }
+
+    #[test]
+    fn test_primes_in_with_cancel() {
+        let cancel = CancelToken::new();
+        let t = 1_000_000_000_000;
+        assert_eq!(try_primes_in_with_cancel(t..t + 100, &cancel), Ok(primes_in(t..t + 100)));
+        assert_eq!(try_primes_in_with_cancel(..2, &cancel), Ok(Vec::new()));
+        cancel.cancel();
+        assert_eq!(try_primes_in_with_cancel(..=10, &cancel), Err(CoreError::Cancelled));
+    }
+
+    #[test]
+    fn test_primes_up_to_small() {
+        assert!(primes_up_to(0).is_empty());
+        assert!(primes_up_to(1).is_empty());
+        assert_eq!(primes_up_to(2), [2]);
+        assert_eq!(primes_up_to(30), [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+    }
+
+    #[test]
+    fn test_primes_up_to_matches_count() {
+        let primes = primes_up_to(1_000_000);
+        assert_eq!(primes.len() as u64, count_primes(..=1_000_000));
+        assert_eq!(primes.last(), Some(&999_983));
+    }
+
+    #[test]
+    fn test_sum_of_primes() {
+        assert_eq!(sum_of_primes(0), 0);
+        assert_eq!(sum_of_primes(1), 0);
+        assert_eq!(sum_of_primes(2), 2);
+        assert_eq!(sum_of_primes(10), 17);
+        // Project Euler #10, spanning several segments
+        assert_eq!(sum_of_primes(2_000_000), 142_913_828_922);
+    }
+
+    #[test]
+    fn test_twin_primes_up_to() {
+        assert_eq!(twin_primes_up_to(4), []);
+        assert_eq!(twin_primes_up_to(5), [(3, 5)]);
+        assert_eq!(
+            twin_primes_up_to(50),
+            [(3, 5), (5, 7), (11, 13), (17, 19), (29, 31), (41, 43)]
+        );
+        // 8169 twin pairs below 10^6, crossing segment boundaries
+        assert_eq!(twin_primes_up_to(1_000_000).len(), 8_169);
+    }
+
+    #[test]
+    fn test_max_prime_gap() {
+        assert_eq!(max_prime_gap(2), None);
+        assert_eq!(max_prime_gap(3), Some((2, 3)));
+        assert_eq!(max_prime_gap(30), Some((23, 29)));
+        // First occurrence of each maximal gap (OEIS A002386)
+        assert_eq!(max_prime_gap(1_000), Some((887, 907)));
+        assert_eq!(max_prime_gap(1_000_000), Some((492_113, 492_227)));
+    }
+
+    #[test]
+    fn test_sophie_germain_primes_up_to() {
+        assert_eq!(sophie_germain_primes_up_to(1), []);
+        assert_eq!(
+            sophie_germain_primes_up_to(100),
+            [2, 3, 5, 11, 23, 29, 41, 53, 83, 89]
+        );
+        let expected = (0..=100_000).filter(|&p| is_prime(p) && is_prime(2 * p + 1)).count();
+        assert_eq!(sophie_germain_primes_up_to(100_000).len(), expected);
+    }
+
+    #[test]
+    fn test_primes_mod() {
+        assert_eq!(
+            primes_mod(100, 4, 1).unwrap(),
+            [5, 13, 17, 29, 37, 41, 53, 61, 73, 89, 97]
+        );
+        assert_eq!(primes_mod(100, 4, 7), primes_mod(100, 4, 3));
+        assert_eq!(primes_mod(100, 1, 0).unwrap(), primes_up_to(100));
+        assert_eq!(primes_mod(100, 10, 5).unwrap(), [5]);
+        assert_eq!(primes_mod(100, 4, 2).unwrap(), [2]);
+        assert_eq!(primes_mod(100, 7, 0).unwrap(), [7]);
+        assert_eq!(primes_mod(5, 7, 0).unwrap(), []);
+        assert_eq!(primes_mod(100, 6, 4).unwrap(), []);
+        assert!(matches!(
+            primes_mod(100, 0, 1),
+            Err(CoreError::InvalidArgument { .. })
+        ));
+
+        // Every residue class partitions the primes, across segments
+        let all = primes_up_to(300_000);
+        for modulus in 1..=12 {
+            let classes: Vec<Vec<u64>> = (0..modulus)
+                .map(|residue| primes_mod(300_000, modulus, residue).unwrap())
+                .collect();
+            assert_eq!(classes.iter().map(Vec::len).sum::<usize>(), all.len());
+            for (residue, class) in (0..).zip(&classes) {
+                assert!(class.iter().all(|p| p % modulus == residue));
+            }
+        }
+    }
+
+    #[test]
+    fn test_for_each_prime_visits_in_order() {
+        let mut seen = Vec::new();
+        let flow = for_each_prime(1_000_000, |p| {
+            seen.push(p);
+            ControlFlow::Continue(())
+        });
+        assert_eq!(flow, ControlFlow::Continue(()));
+        assert_eq!(seen, primes_up_to(1_000_000));
+        assert_eq!(for_each_prime(1, |_| ControlFlow::Break(())), ControlFlow::Continue(()));
+    }
+
+    #[test]
+    fn test_for_each_prime_breaks_early() {
+        // Stop at the first prime past the first segment
+        let mut last = 0;
+        let flow = for_each_prime(4 * segmented::SEGMENT_SPAN, |p| {
+            last = p;
+            if p > segmented::SEGMENT_SPAN {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert_eq!(flow, ControlFlow::Break(()));
+        assert_eq!(Some(last), next_prime(segmented::SEGMENT_SPAN));
+    }
+
+    #[test]
+    fn test_primes_in_range_small() {
+        assert_eq!(primes_in_range(0, 10), [2, 3, 5, 7]);
+        assert_eq!(primes_in_range(10, 30), [11, 13, 17, 19, 23, 29]);
+        assert_eq!(primes_in_range(24, 28), []);
+        assert!(primes_in_range(30, 10).is_empty());
+    }
+
+    #[test]
+    fn test_primes_in_range_high_window() {
+        let low = 1_000_000_000;
+        let high = low + 100_000;
+        let primes = primes_in_range(low, high);
+        let expected = (low..=high).filter(|&n| is_prime(n)).count();
+        assert_eq!(primes.len(), expected);
+        assert_eq!(primes.first(), Some(&1_000_000_007));
+    }
+
+    #[test]
+    fn test_primes_in_range_beyond_32_bits() {
+        // Same answer on 32-bit targets, where usize would have capped the limit
+        let low = 1 << 40;
+        let primes = primes_in_range(low, low + 1_000);
+        let expected: Vec<u64> = (low..=low + 1_000).filter(|&n| is_prime(n)).collect();
+        assert_eq!(primes, expected);
     }
 
     #[test]
@@ -213,8 +1004,55 @@ mod tests {
         assert_eq!(nth_prime(1000), Some(7919));
     }
 
+    #[test]
+    fn test_nth_prime_across_segments() {
+        assert_eq!(nth_prime(100_000), Some(1_299_709));
+    }
+
+    #[test]
+    fn test_nth_prime_bound_is_tight() {
+        // p_1000000 = 15485863; the bound should hug it instead of doubling it
+        let bound = estimate_nth_prime_upper_bound(1_000_000);
+        assert!(bound >= 15_485_863);
+        assert!(bound < 15_485_863 + 15_485_863 / 10, "bound {bound}");
+    }
+
+    #[test]
+    fn test_nth_prime_bound_never_too_low() {
+        for (n, p) in (1..).zip(Primes::new().take(200_000)) {
+            assert!(estimate_nth_prime_upper_bound(n) >= p, "n = {n}");
+        }
+    }
+
+    #[test]
+    fn test_nth_prime_retries_when_bound_too_low() {
+        // 100_000 doubled four times covers p_100000 = 1_299_709
+        let cancel = CancelToken::new();
+        assert_eq!(nth_prime_in(0, 100_000, 100_000, &cancel), Ok(Some(1_299_709)));
+        assert_eq!(nth_prime_in(0, 100_000, 1_000, &cancel), Ok(None));
+    }
+
+    #[test]
+    fn test_nth_prime_after() {
+        assert_eq!(nth_prime_after(0, 1), Some(2));
+        assert_eq!(nth_prime_after(2, 1), Some(3));
+        assert_eq!(nth_prime_after(7, 3), Some(17));
+        assert_eq!(nth_prime_after(10, 0), None);
+        assert_eq!(nth_prime_after(1_000_000, 1), Some(1_000_003));
+        assert_eq!(nth_prime_after(0, 100_000), nth_prime(100_000));
+        // p_1000 = 7919, so the 1000th prime after it is p_2000
+        assert_eq!(nth_prime_after(7_919, 1_000), nth_prime(2_000));
+        assert_eq!(nth_prime_after(1_000_000_000_000, 4), Some(1_000_000_000_091));
+        assert_eq!(nth_prime_after(u64::MAX, 1), None);
+    }
+
     #[test]
     fn test_nth_prime_invalid() {
         assert_eq!(nth_prime(0), None);
+        assert!(matches!(
+            try_nth_prime(0),
+            Err(CoreError::InvalidArgument { .. })
+        ));
+        assert_eq!(try_nth_prime(4_097), Ok(38_891));
     }
 }
@@ -0,0 +1,209 @@
+//! Sublinear prime counting (Lucy_Hedgehog's method)
+//!
+//! pi(n) only depends on the values S(v) = #{primes <= v} at the O(sqrt n)
+//! distinct quotients v = n / i. Starting from S(v) = v - 1 (every number
+//! from 2 is a candidate), each prime p <= sqrt(n) removes the numbers whose
+//! smallest prime factor is p:
+//!
+//!   S(v) -= S(v / p) - S(p - 1)    for every v >= p^2
+//!
+//! This runs in O(n^(3/4)) time and O(sqrt n) memory, so limits around
+//! 10^12 take a couple of seconds instead of sieving a trillion numbers.
+
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+
+use crate::{isqrt, CancelToken, CoreError};
+
+/// Count prime numbers up to and including `limit` without sieving
+/// Faster than the sieve for large limits; `count_primes` dispatches here
+/// above a threshold
+///
+/// # Panics
+///
+/// Panics if the two sqrt(limit)-sized tables cannot be allocated;
+/// use [`try_count_primes_fast`] to handle that case
+pub fn count_primes_fast(limit: u64) -> u64 {
+    match try_count_primes_fast(limit) {
+        Ok(count) => count,
+        Err(error) => panic!("{error}"),
+    }
+}
+
+/// Count prime numbers up to and including `limit` without sieving
+/// Returns [`CoreError::LimitTooLarge`] instead of aborting when the tables
+/// (2 * 8 * sqrt(limit) bytes, about 64 GiB near u64::MAX) cannot be allocated
+///
+/// With the `rayon` feature, each round's updates are split into
+/// dependency-free blocks that run in parallel.
+pub fn try_count_primes_fast(limit: u64) -> Result<u64, CoreError> {
+    let cancel = CancelToken::new();
+    count_with(limit, |_, _| cancel.observe())
+}
+
+/// Bytes of the two tables counting up to `limit` allocates
+pub(crate) fn table_bytes(limit: u64) -> usize {
+    if limit < 2 {
+        return 0;
+    }
+    usize::try_from(isqrt(limit) + 1)
+        .map_or(usize::MAX, |len| len.saturating_mul(2 * size_of::<u64>()))
+}
+
+/// [`try_count_primes_fast`], calling `observer(p, isqrt(limit))` before
+/// each sieving prime p and `observer(isqrt(limit), isqrt(limit))` at the end
+/// Returns [`CoreError::Cancelled`] as soon as the observer breaks
+pub(crate) fn count_with(
+    limit: u64,
+    mut observer: impl FnMut(u64, u64) -> ControlFlow<()>,
+) -> Result<u64, CoreError> {
+    if limit < 2 {
+        return Ok(0);
+    }
+
+    let n = limit;
+    let r = isqrt(n);
+    let too_large = CoreError::LimitTooLarge { limit };
+    let r_len = usize::try_from(r)
+        .ok()
+        .and_then(|r| r.checked_add(1))
+        .ok_or(too_large)?;
+
+    // small[v] = S(v) for v <= r, large[i] = S(n / i) for i <= r
+    let mut small: Vec<u64> = Vec::new();
+    let mut large: Vec<u64> = Vec::new();
+    small.try_reserve_exact(r_len).map_err(|_| too_large)?;
+    large.try_reserve_exact(r_len).map_err(|_| too_large)?;
+    small.extend((0..r_len as u64).map(|v| v.saturating_sub(1)));
+    large.extend((0..r_len as u64).map(|i| n.checked_div(i).map_or(0, |q| q - 1)));
+
+    for p in 2..=r {
+        let p_idx = p as usize;
+        if small[p_idx] == small[p_idx - 1] {
+            continue; // p is composite
+        }
+        if observer(p, r).is_break() {
+            return Err(CoreError::Cancelled);
+        }
+
+        let below_p = small[p_idx - 1];
+        let square = p * p;
+
+        // Large quotients first: they read small quotients not yet updated
+        let large_end = r.min(n / square) as usize;
+        update_large(&mut large, &small, n, r, p, below_p, large_end);
+        update_small(&mut small, r, p, square, below_p);
+    }
+
+    if observer(r, r).is_break() {
+        return Err(CoreError::Cancelled);
+    }
+    Ok(large[1])
+}
+
+/// large[i] -= S(n / (i * p)) - S(p - 1) for i in 1..=end
+/// large[i] reads large[i * p], which must still hold the previous round's
+/// value, so blocks [lo, lo * p) are processed in ascending order: every read
+/// lands above the block, in entries no later block has touched yet
+fn update_large(
+    large: &mut [u64],
+    small: &[u64],
+    n: u64,
+    r: u64,
+    p: u64,
+    below_p: u64,
+    end: usize,
+) {
+    let mut lo = 1;
+    while lo <= end {
+        let hi = end.min(lo * p as usize - 1);
+        let (block, above) = large[..].split_at_mut(hi + 1);
+        let update = |(k, slot): (usize, &mut u64)| {
+            let d = (lo + k) as u64 * p;
+            let quotient = if d <= r {
+                above[d as usize - hi - 1]
+            } else {
+                small[(n / d) as usize]
+            };
+            *slot -= quotient - below_p;
+        };
+        for_each_slot(&mut block[lo..], update);
+        lo = hi + 1;
+    }
+}
+
+/// small[v] -= S(v / p) - S(p - 1) for v in square..=r
+/// Blocks (hi / p, hi] are processed from the top down, so every read of
+/// small[v / p] lands below the block, in entries not yet updated this round
+fn update_small(small: &mut [u64], r: u64, p: u64, square: u64, below_p: u64) {
+    let mut hi = r;
+    while hi >= square {
+        let lo = square.max(hi / p + 1);
+        let (below, rest) = small.split_at_mut(lo as usize);
+        let update = |(k, slot): (usize, &mut u64)| {
+            let v = lo + k as u64;
+            *slot -= below[(v / p) as usize] - below_p;
+        };
+        for_each_slot(&mut rest[..=(hi - lo) as usize], update);
+        hi = lo - 1;
+    }
+}
+
+/// Apply `update` to every (index, slot) pair of `block`
+#[cfg(not(feature = "rayon"))]
+#[inline]
+fn for_each_slot(block: &mut [u64], update: impl Fn((usize, &mut u64))) {
+    block.iter_mut().enumerate().for_each(update);
+}
+
+/// Apply `update` to every (index, slot) pair of `block`, in parallel once
+/// the block is large enough to be worth splitting
+#[cfg(feature = "rayon")]
+#[inline]
+fn for_each_slot(block: &mut [u64], update: impl Fn((usize, &mut u64)) + Sync + Send) {
+    use rayon::prelude::*;
+
+    const MIN_PARALLEL_LEN: usize = 1 << 14;
+    if block.len() < MIN_PARALLEL_LEN {
+        block.iter_mut().enumerate().for_each(update);
+    } else {
+        block
+            .par_iter_mut()
+            .with_min_len(MIN_PARALLEL_LEN / 4)
+            .enumerate()
+            .for_each(update);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_sieve_small() {
+        for limit in 0..2_000 {
+            assert_eq!(
+                count_primes_fast(limit),
+                crate::segmented::count(0, limit),
+                "pi({limit})"
+            );
+        }
+    }
+
+    #[test]
+    fn test_powers_of_ten() {
+        assert_eq!(count_primes_fast(1_000_000), 78_498);
+        assert_eq!(count_primes_fast(10_000_000), 664_579);
+        assert_eq!(count_primes_fast(1_000_000_000), 50_847_534);
+    }
+
+    #[test]
+    fn test_perfect_squares() {
+        // r = isqrt(n) exactly; p^2 == n must still be removed
+        assert_eq!(count_primes_fast(49), 15);
+        assert_eq!(
+            count_primes_fast(10_201),
+            crate::segmented::count(0, 10_201)
+        );
+    }
+}
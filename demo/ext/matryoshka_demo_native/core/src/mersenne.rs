@@ -0,0 +1,100 @@
+//! Lucas–Lehmer test for Mersenne primes
+//!
+//! M_p = 2^p - 1 is prime (for odd prime p) iff s_(p-2) = 0 mod M_p, where
+//! s_0 = 4 and s_(k+1) = s_k^2 - 2. With p <= 127 every residue fits a u128;
+//! squares are formed as 256-bit (hi, lo) pairs and reduced with the
+//! Mersenne identity 2^p = 1 mod M_p, so no division is needed.
+
+use crate::is_prime;
+
+/// Largest exponent [`is_mersenne_prime`] accepts, so that M_p fits a u128
+pub const MAX_MERSENNE_EXPONENT: u32 = 127;
+
+/// Check whether the Mersenne number 2^p - 1 is prime (Lucas–Lehmer)
+///
+/// # Panics
+///
+/// Panics if `p` exceeds [`MAX_MERSENNE_EXPONENT`]
+pub fn is_mersenne_prime(p: u32) -> bool {
+    assert!(
+        p <= MAX_MERSENNE_EXPONENT,
+        "Mersenne exponent {p} exceeds {MAX_MERSENNE_EXPONENT}"
+    );
+    if p == 2 {
+        return true; // M_2 = 3; Lucas–Lehmer needs an odd exponent
+    }
+    // A composite exponent always gives a composite Mersenne number
+    if !is_prime(u64::from(p)) {
+        return false;
+    }
+
+    let modulus = u128::MAX >> (128 - p);
+    let mut s: u128 = 4;
+    for _ in 0..p - 2 {
+        s = square_mod_mersenne(s, p, modulus);
+        // s^2 - 2, wrapping into [0, M_p)
+        s = if s >= 2 { s - 2 } else { s + modulus - 2 };
+    }
+    s == 0
+}
+
+/// s^2 mod (2^p - 1) for `s < 2^p - 1` and `p <= 127`
+fn square_mod_mersenne(s: u128, p: u32, modulus: u128) -> u128 {
+    let (hi, lo) = mul_wide(s, s);
+    // x = hi * 2^128 + lo < 2^(2p), so x >> p < 2^p fits a u128
+    let shifted = (hi << (128 - p)) | (lo >> p);
+    let mut r = (lo & modulus) + shifted;
+    while r >= modulus {
+        r -= modulus;
+    }
+    r
+}
+
+/// Full 256-bit product of two u128 values as (high, low) halves
+pub(crate) fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    const LOW: u128 = u64::MAX as u128;
+    let (a1, a0) = (a >> 64, a & LOW);
+    let (b1, b0) = (b >> 64, b & LOW);
+
+    let (mid, mid_carry) = (a1 * b0).overflowing_add(a0 * b1);
+    let (lo, lo_carry) = (a0 * b0).overflowing_add(mid << 64);
+    let hi = a1 * b1 + (mid >> 64) + ((mid_carry as u128) << 64) + lo_carry as u128;
+    (hi, lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mersenne_exponents_up_to_127() {
+        let exponents: [u32; 12] = [2, 3, 5, 7, 13, 17, 19, 31, 61, 89, 107, 127];
+        for p in 0..=MAX_MERSENNE_EXPONENT {
+            assert_eq!(is_mersenne_prime(p), exponents.contains(&p), "p = {p}");
+        }
+    }
+
+    #[test]
+    fn test_mersenne_matches_miller_rabin() {
+        for p in 2..64 {
+            assert_eq!(
+                is_mersenne_prime(p),
+                is_prime(u64::MAX >> (64 - p)),
+                "p = {p}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_mul_wide() {
+        assert_eq!(mul_wide(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+        assert_eq!(mul_wide(1 << 64, 1 << 64), (1, 0));
+        assert_eq!(mul_wide(3, 5), (0, 15));
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds 127")]
+    fn test_exponent_too_large_panics() {
+        is_mersenne_prime(128);
+    }
+}
@@ -0,0 +1,107 @@
+//! Möbius function and squarefree testing
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::factorize;
+
+/// Compute mu(k) for every k in `0..=limit` with a linear sieve
+/// mu(k) is 0 when a square divides k, otherwise (-1)^(number of prime factors).
+/// mu(0) is reported as 0.
+pub fn mobius_up_to(limit: u64) -> Vec<i8> {
+    let n = usize::try_from(limit)
+        .ok()
+        .filter(|&n| n < usize::MAX)
+        .expect("mobius limit exceeds addressable memory");
+
+    let mut mu = vec![0i8; n + 1];
+    if n >= 1 {
+        mu[1] = 1;
+    }
+
+    // Linear sieve: each composite is reached once, through its smallest prime factor
+    let mut composite = vec![false; n + 1];
+    let mut primes: Vec<usize> = Vec::new();
+    for i in 2..=n {
+        if !composite[i] {
+            mu[i] = -1;
+            primes.push(i);
+        }
+        for &p in &primes {
+            let Some(multiple) = i.checked_mul(p).filter(|&m| m <= n) else {
+                break;
+            };
+            composite[multiple] = true;
+            if i.is_multiple_of(p) {
+                // p^2 divides i * p
+                mu[multiple] = 0;
+                break;
+            }
+            mu[multiple] = -mu[i];
+        }
+    }
+    mu
+}
+
+/// Check whether no square greater than 1 divides `n`
+/// 0 is divisible by every square, so it is not squarefree
+pub fn is_squarefree(n: u64) -> bool {
+    if n == 0 {
+        return false;
+    }
+
+    factorize(n).iter().all(|&(_, exponent)| exponent == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mobius_up_to_small() {
+        assert_eq!(mobius_up_to(0), [0]);
+        assert_eq!(mobius_up_to(1), [0, 1]);
+        assert_eq!(
+            mobius_up_to(12),
+            [0, 1, -1, -1, 0, -1, 1, -1, 0, 0, 1, -1, 0]
+        );
+    }
+
+    #[test]
+    fn test_mobius_matches_factorization() {
+        let mu = mobius_up_to(20_000);
+        for n in 1..=20_000u64 {
+            let factors = factorize(n);
+            let expected = if factors.iter().any(|&(_, e)| e > 1) {
+                0
+            } else if factors.len().is_multiple_of(2) {
+                1
+            } else {
+                -1
+            };
+            assert_eq!(mu[n as usize], expected, "mu({n})");
+        }
+    }
+
+    #[test]
+    fn test_mertens_function() {
+        // M(10^4) = sum of mu(k) for k <= 10^4 = -23
+        let total: i64 = mobius_up_to(10_000).iter().map(|&m| i64::from(m)).sum();
+        assert_eq!(total, -23);
+    }
+
+    #[test]
+    fn test_is_squarefree() {
+        assert!(!is_squarefree(0));
+        assert!(is_squarefree(1));
+        assert!(is_squarefree(30));
+        assert!(!is_squarefree(12));
+        assert!(is_squarefree((1 << 61) - 1));
+        assert!(!is_squarefree(1_000_003 * 1_000_003));
+        // About 6 / pi^2 of integers are squarefree: 6083 up to 10^4
+        let mu = mobius_up_to(10_000);
+        let count = (1..=10_000u64).filter(|&n| is_squarefree(n)).count();
+        assert_eq!(count, mu.iter().filter(|&&m| m != 0).count());
+        assert_eq!(count, 6_083);
+    }
+}
@@ -0,0 +1,131 @@
+//! Modular arithmetic on u64
+//!
+//! Products are widened to u128, so every function is exact for any modulus
+//! up to u64::MAX.
+
+use crate::gcd::{ext_gcd, gcd};
+
+/// (a * b) mod m, widened to u128 so the product cannot overflow
+///
+/// # Panics
+///
+/// Panics if `m` is 0
+#[inline]
+pub fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+/// (base ^ exp) mod m by square-and-multiply
+///
+/// # Panics
+///
+/// Panics if `m` is 0
+pub fn pow_mod(base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    let mut base = base % m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Inverse of `a` modulo `m`: the x in `[0, m)` with a * x = 1 (mod m)
+/// Returns None when `m` is 0 or `a` and `m` share a factor
+pub fn inv_mod(a: u64, m: u64) -> Option<u64> {
+    if m == 0 {
+        return None;
+    }
+
+    let (g, x, _) = ext_gcd(a % m, m);
+    if g != 1 {
+        return None;
+    }
+    Some((x as i128).rem_euclid(m as i128) as u64)
+}
+
+/// Combine congruences x = r_i (mod m_i) with the Chinese remainder theorem
+/// Returns `(x, lcm)` with x the least solution modulo lcm of the moduli, or
+/// None if a modulus is 0, the congruences conflict, or the lcm exceeds u64.
+/// Moduli need not be coprime; an empty slice gives `(0, 1)`.
+pub fn crt(congruences: &[(u64, u64)]) -> Option<(u64, u64)> {
+    congruences
+        .iter()
+        .try_fold((0, 1), |(r1, m1), &(r2, m2)| crt_pair(r1, m1, r2, m2))
+}
+
+/// Solve x = r1 (mod m1), x = r2 (mod m2) for `m1 > 0`, `r1 < m1`
+fn crt_pair(r1: u64, m1: u64, r2: u64, m2: u64) -> Option<(u64, u64)> {
+    if m2 == 0 {
+        return None;
+    }
+
+    let g = gcd(m1, m2);
+    // r2 - r1 reduced into [0, m2), in u128 so m2 near u64::MAX cannot overflow
+    let diff = (((r2 % m2) as u128 + m2 as u128 - (r1 % m2) as u128) % m2 as u128) as u64;
+    if !diff.is_multiple_of(g) {
+        return None;
+    }
+
+    let m2_reduced = m2 / g;
+    let lcm = m1.checked_mul(m2_reduced)?;
+    // m1 * t = diff (mod m2)  <=>  (m1 / g) * t = diff / g (mod m2 / g)
+    let inverse = inv_mod((m1 / g) % m2_reduced, m2_reduced)?;
+    let t = mul_mod(diff / g, inverse, m2_reduced);
+    Some((r1 + m1 * t, lcm))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mul_mod_near_u64_max() {
+        assert_eq!(mul_mod(u64::MAX - 1, u64::MAX - 1, u64::MAX), 1);
+        assert_eq!(
+            mul_mod(1 << 63, 4, 1_000_000_007),
+            ((1u128 << 65) % 1_000_000_007) as u64
+        );
+    }
+
+    #[test]
+    fn test_pow_mod() {
+        assert_eq!(pow_mod(2, 10, 1_000), 24);
+        assert_eq!(pow_mod(3, 0, 7), 1);
+        assert_eq!(pow_mod(3, 0, 1), 0);
+        assert_eq!(pow_mod(0, 0, 5), 1);
+        // Fermat: a^(p-1) = 1 mod p
+        assert_eq!(pow_mod(12_345, 1_000_000_006, 1_000_000_007), 1);
+        assert_eq!(pow_mod(u64::MAX, u64::MAX, u64::MAX - 1), 1);
+    }
+
+    #[test]
+    fn test_inv_mod() {
+        assert_eq!(inv_mod(3, 11), Some(4));
+        assert_eq!(inv_mod(10, 17), Some(12));
+        assert_eq!(inv_mod(6, 9), None);
+        assert_eq!(inv_mod(5, 0), None);
+        assert_eq!(inv_mod(5, 1), Some(0));
+        let m = 18_446_744_073_709_551_557; // largest u64 prime
+        for a in [2, 3, 1 << 40, m - 1] {
+            assert_eq!(mul_mod(a, inv_mod(a, m).unwrap(), m), 1, "a = {a}");
+        }
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt(&[]), Some((0, 1)));
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        // Non-coprime moduli: consistent and conflicting
+        assert_eq!(crt(&[(3, 4), (1, 6)]), Some((7, 12)));
+        assert_eq!(crt(&[(0, 4), (1, 6)]), None);
+        assert_eq!(crt(&[(1, 0)]), None);
+        // lcm 2^40 * (2^40 - 1) does not fit a u64
+        assert_eq!(crt(&[(1, 1 << 40), (2, (1 << 40) - 1)]), None);
+        // Residues are reduced first
+        assert_eq!(crt(&[(10, 3), (12, 5)]), Some((7, 15)));
+    }
+}
@@ -0,0 +1,90 @@
+//! Prime factor counts, semiprimes and k-almost primes
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::factorize;
+
+/// Number of distinct prime factors of `n`; 0 for 0 and 1
+pub fn omega(n: u64) -> u32 {
+    factorize(n).len() as u32
+}
+
+/// Number of prime factors of `n` counted with multiplicity; 0 for 0 and 1
+pub fn big_omega(n: u64) -> u32 {
+    factorize(n).iter().map(|&(_, exponent)| exponent).sum()
+}
+
+/// Check whether `n` is the product of exactly two primes (not necessarily distinct)
+pub fn is_semiprime(n: u64) -> bool {
+    big_omega(n) == 2
+}
+
+/// Collect every k-almost prime up to and including `limit` in ascending order:
+/// the numbers with exactly `k` prime factors counted with multiplicity
+/// k = 1 gives the primes, k = 2 the semiprimes, and k = 0 just `[1]`
+pub fn almost_primes_up_to(limit: u64, k: u32) -> Vec<u64> {
+    let n = usize::try_from(limit)
+        .ok()
+        .filter(|&n| n < usize::MAX)
+        .expect("almost prime limit exceeds addressable memory");
+
+    // Linear sieve of big_omega: each composite is reached once, through
+    // its smallest prime factor, so counts fit a byte (at most 63)
+    let mut factors = vec![0u8; n + 1];
+    let mut primes: Vec<usize> = Vec::new();
+    for i in 2..=n {
+        if factors[i] == 0 {
+            factors[i] = 1;
+            primes.push(i);
+        }
+        for &p in &primes {
+            let Some(multiple) = i.checked_mul(p).filter(|&m| m <= n) else {
+                break;
+            };
+            factors[multiple] = factors[i] + 1;
+            if i.is_multiple_of(p) {
+                break;
+            }
+        }
+    }
+
+    (1..=n)
+        .filter(|&i| u32::from(factors[i]) == k)
+        .map(|i| i as u64)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_omega_and_big_omega() {
+        assert_eq!((omega(0), big_omega(0)), (0, 0));
+        assert_eq!((omega(1), big_omega(1)), (0, 0));
+        assert_eq!((omega(12), big_omega(12)), (2, 3));
+        assert_eq!((omega(1 << 63), big_omega(1 << 63)), (1, 63));
+        // 600851475143 = 71 * 839 * 1471 * 6857
+        assert_eq!(omega(600_851_475_143), 4);
+    }
+
+    #[test]
+    fn test_is_semiprime() {
+        let semiprimes: Vec<u64> = (0..40).filter(|&n| is_semiprime(n)).collect();
+        assert_eq!(semiprimes, [4, 6, 9, 10, 14, 15, 21, 22, 25, 26, 33, 34, 35, 38, 39]);
+        assert!(is_semiprime(1_000_003 * 1_000_033));
+        assert!(!is_semiprime((1 << 61) - 1));
+    }
+
+    #[test]
+    fn test_almost_primes_match_big_omega() {
+        for k in 0..5 {
+            let expected: Vec<u64> = (1..=20_000).filter(|&n| big_omega(n) == k).collect();
+            assert_eq!(almost_primes_up_to(20_000, k), expected, "k = {k}");
+        }
+        assert!(almost_primes_up_to(0, 0).is_empty());
+        assert_eq!(almost_primes_up_to(1, 0), [1]);
+        assert_eq!(almost_primes_up_to(1_000, 1).len(), 168);
+    }
+}
@@ -0,0 +1,144 @@
+//! Pattern pre-sieving for the smallest primes
+//!
+//! Segments store odd numbers only, and odd multiples of 3, 5, 7 and 11
+//! repeat every 3 * 5 * 7 * 11 = 1155 odd numbers, which is exactly 1155
+//! u64 words of bitset once lined up on a word boundary.
+//! Instead of walking those primes bit by bit (touching nearly every word),
+//! segments are ANDed with this precomputed mask, 2 words at a time with the
+//! `simd` feature or one word at a time otherwise.
+
+/// Primes whose multiples are removed by the pattern
+pub(crate) const PRESIEVE_PRIMES: [u64; 4] = [3, 5, 7, 11];
+
+/// Largest prime handled by the pattern; the sieve loop starts after it
+pub(crate) const LARGEST_PRESIEVE_PRIME: u64 = 11;
+
+/// Words before the pattern repeats: lcm(1155, 64) / 64
+const PATTERN_WORDS: usize = 1_155;
+
+/// Bit `i` of the pattern stands for the odd number 2i + 1 and is clear when
+/// that number is a multiple of a presieve prime
+static PATTERN: [u64; PATTERN_WORDS] = build_pattern();
+
+const fn build_pattern() -> [u64; PATTERN_WORDS] {
+    let mut pattern = [u64::MAX; PATTERN_WORDS];
+    let mut i = 0;
+    while i < PRESIEVE_PRIMES.len() {
+        // Odd multiples of p are 2p apart, which is p bits
+        let p = PRESIEVE_PRIMES[i] as usize;
+        let mut bit = (p - 1) / 2;
+        while bit < PATTERN_WORDS * 64 {
+            pattern[bit / 64] &= !(1 << (bit % 64));
+            bit += p;
+        }
+        i += 1;
+    }
+    pattern
+}
+
+/// Clear every multiple of the presieve primes (including the primes themselves)
+/// Bit i of `bits` is the odd number `start + 2i + 1`; `start` must be a multiple of 128
+pub(crate) fn apply(bits: &mut [u64], start: u64) {
+    debug_assert!(start.is_multiple_of(128));
+
+    let mut phase = ((start / 128) % PATTERN_WORDS as u64) as usize;
+    let mut rest = bits;
+    while !rest.is_empty() {
+        let take = rest.len().min(PATTERN_WORDS - phase);
+        let (chunk, tail) = rest.split_at_mut(take);
+        and_assign(chunk, &PATTERN[phase..phase + take]);
+        rest = tail;
+        phase = 0;
+    }
+}
+
+/// dst[i] &= src[i] with 128-bit SSE2 lanes (baseline on every x86_64 CPU)
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+fn and_assign(dst: &mut [u64], src: &[u64]) {
+    use core::arch::x86_64::{__m128i, _mm_and_si128, _mm_loadu_si128, _mm_storeu_si128};
+
+    let mut dst_chunks = dst.chunks_exact_mut(2);
+    let mut src_chunks = src.chunks_exact(2);
+    for (d, s) in (&mut dst_chunks).zip(&mut src_chunks) {
+        // SAFETY: both chunks are exactly 16 bytes and unaligned loads/stores are used
+        unsafe {
+            let a = _mm_loadu_si128(d.as_ptr() as *const __m128i);
+            let b = _mm_loadu_si128(s.as_ptr() as *const __m128i);
+            _mm_storeu_si128(d.as_mut_ptr() as *mut __m128i, _mm_and_si128(a, b));
+        }
+    }
+    and_assign_scalar(dst_chunks.into_remainder(), src_chunks.remainder());
+}
+
+/// dst[i] &= src[i] with 128-bit NEON lanes (baseline on every aarch64 CPU)
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+fn and_assign(dst: &mut [u64], src: &[u64]) {
+    use core::arch::aarch64::{vandq_u64, vld1q_u64, vst1q_u64};
+
+    let mut dst_chunks = dst.chunks_exact_mut(2);
+    let mut src_chunks = src.chunks_exact(2);
+    for (d, s) in (&mut dst_chunks).zip(&mut src_chunks) {
+        // SAFETY: both chunks are exactly 2 words; NEON loads have no alignment requirement
+        unsafe {
+            let a = vld1q_u64(d.as_ptr());
+            let b = vld1q_u64(s.as_ptr());
+            vst1q_u64(d.as_mut_ptr(), vandq_u64(a, b));
+        }
+    }
+    and_assign_scalar(dst_chunks.into_remainder(), src_chunks.remainder());
+}
+
+/// Portable fallback for targets (or builds) without a SIMD path
+#[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+fn and_assign(dst: &mut [u64], src: &[u64]) {
+    and_assign_scalar(dst, src);
+}
+
+/// dst[i] &= src[i], one u64 word at a time
+fn and_assign_scalar(dst: &mut [u64], src: &[u64]) {
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d &= *s;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn assert_matches_trial_division(bits: &[u64], start: u64) {
+        for offset in 0..bits.len() * 64 {
+            let n = start + 2 * offset as u64 + 1;
+            let coprime = PRESIEVE_PRIMES.iter().all(|&p| !n.is_multiple_of(p));
+            let set = bits[offset / 64] & (1 << (offset % 64)) != 0;
+            assert_eq!(set, coprime, "n = {n}");
+        }
+    }
+
+    #[test]
+    fn test_pattern_marks_small_multiples() {
+        let mut bits = vec![u64::MAX; 4];
+        apply(&mut bits, 0);
+        // Odd survivors below 16: 1 and 13
+        assert_eq!(bits[0] & 0xFF, (1 << 0) | (1 << 6));
+        assert_matches_trial_division(&bits, 0);
+    }
+
+    #[test]
+    fn test_pattern_wraps_around_period() {
+        // A window crossing the end of the pattern must stay aligned with n
+        let start = (PATTERN_WORDS as u64 - 3) * 128;
+        let mut bits = vec![u64::MAX; 40];
+        apply(&mut bits, start);
+        assert_matches_trial_division(&bits, start);
+    }
+
+    #[test]
+    fn test_and_assign_handles_tails() {
+        let mut dst = vec![u64::MAX; 37];
+        let src: Vec<u64> = (0..37).map(|i| i * 0x0101_0101_0101_0101).collect();
+        and_assign(&mut dst, &src);
+        assert_eq!(dst, src);
+    }
+}
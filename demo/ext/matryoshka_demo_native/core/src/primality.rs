@@ -0,0 +1,228 @@
+//! Primality testing without a sieve
+
+use crate::modmath::{mul_mod, pow_mod};
+use crate::table::{self, PRIME_TABLE_MAX};
+
+/// Witnesses that make Miller–Rabin deterministic for every u64
+/// (the first 12 primes cover all n < 3.3 * 10^24)
+const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// Check whether `n` is prime using deterministic Miller–Rabin
+/// Runs in O(log^3 n) without allocating, so it suits one-off checks
+pub fn is_prime(n: u64) -> bool {
+    if n <= PRIME_TABLE_MAX {
+        return table::contains(n);
+    }
+
+    // n is above every witness, so they double as quick trial division
+    for &p in &WITNESSES {
+        if n.is_multiple_of(p) {
+            return false;
+        }
+    }
+
+    // Write n - 1 as d * 2^s with d odd
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+
+    for &a in &WITNESSES {
+        if !passes_round(n, d, s, a) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Find the smallest prime strictly greater than `n`
+/// Returns None when no such prime fits in a u64
+pub fn next_prime(n: u64) -> Option<u64> {
+    if n < 2 {
+        return Some(2);
+    }
+    if n < 3 {
+        return Some(3);
+    }
+    if n < 5 {
+        return Some(5);
+    }
+
+    // Every prime above 3 is 6k ± 1, so only those candidates are tested
+    let mut candidate = n.checked_add(1)?;
+    while !on_wheel(candidate) {
+        candidate = candidate.checked_add(1)?;
+    }
+
+    loop {
+        if is_prime(candidate) {
+            return Some(candidate);
+        }
+        let gap = if candidate % 6 == 5 { 2 } else { 4 };
+        candidate = candidate.checked_add(gap)?;
+    }
+}
+
+/// Find the smallest safe prime strictly greater than `n`: a prime `q` with
+/// `(q - 1) / 2` prime as well
+/// Returns None when no such prime fits in a u64
+pub fn next_safe_prime(n: u64) -> Option<u64> {
+    let mut q = next_prime(n)?;
+    while !is_prime((q - 1) / 2) {
+        q = next_prime(q)?;
+    }
+    Some(q)
+}
+
+/// Split an even `n >= 4` into two primes `(p, n - p)` with `p` as small as
+/// possible; returns None for odd `n` or `n < 4`
+/// The smallest p stays in the low thousands across the u64 range, so this
+/// is a handful of Miller–Rabin tests rather than a sieve up to `n`
+pub fn goldbach_pair(n: u64) -> Option<(u64, u64)> {
+    if n < 4 || !n.is_multiple_of(2) {
+        return None;
+    }
+
+    let mut p = 2;
+    while p <= n / 2 {
+        if is_prime(n - p) {
+            return Some((p, n - p));
+        }
+        p = next_prime(p)?;
+    }
+    None
+}
+
+/// Find the largest prime strictly less than `n`
+/// Returns None when `n <= 2`
+pub fn prev_prime(n: u64) -> Option<u64> {
+    if n <= 2 {
+        return None;
+    }
+    if n == 3 {
+        return Some(2);
+    }
+    if n <= 5 {
+        return Some(3);
+    }
+
+    // n >= 6 here, and 5 is prime, so the walk never drops below 5
+    let mut candidate = n - 1;
+    while !on_wheel(candidate) {
+        candidate -= 1;
+    }
+
+    loop {
+        if is_prime(candidate) {
+            return Some(candidate);
+        }
+        let gap = if candidate % 6 == 1 { 2 } else { 4 };
+        candidate -= gap;
+    }
+}
+
+/// True for numbers of the form 6k ± 1
+#[inline]
+fn on_wheel(n: u64) -> bool {
+    n % 6 == 1 || n % 6 == 5
+}
+
+/// One Miller–Rabin round: false means `a` proves `n` composite
+fn passes_round(n: u64, d: u64, s: u32, a: u64) -> bool {
+    let mut x = pow_mod(a, d, n);
+    if x == 1 || x == n - 1 {
+        return true;
+    }
+
+    for _ in 1..s {
+        x = mul_mod(x, x, n);
+        if x == n - 1 {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixed_sieve_bytes, FixedSieve};
+
+    #[test]
+    fn test_is_prime_small() {
+        assert!(!is_prime(0));
+        assert!(!is_prime(1));
+        assert!(is_prime(2));
+        assert!(is_prime(3));
+        assert!(!is_prime(4));
+        assert!(is_prime(37));
+        assert!(!is_prime(49));
+    }
+
+    #[test]
+    fn test_is_prime_matches_sieve() {
+        let sieve = FixedSieve::<{ fixed_sieve_bytes(100_000) }>::new(100_000);
+        let count = (0..=100_000u64).filter(|&n| is_prime(n)).count();
+        assert_eq!(count as u64, sieve.count());
+    }
+
+    #[test]
+    fn test_next_prime() {
+        assert_eq!(next_prime(0), Some(2));
+        assert_eq!(next_prime(2), Some(3));
+        assert_eq!(next_prime(3), Some(5));
+        assert_eq!(next_prime(5), Some(7));
+        assert_eq!(next_prime(24), Some(29));
+        assert_eq!(next_prime(1_000_000_000), Some(1_000_000_007));
+        assert_eq!(next_prime(18_446_744_073_709_551_557), None);
+    }
+
+    #[test]
+    fn test_next_safe_prime() {
+        assert_eq!(next_safe_prime(0), Some(5));
+        assert_eq!(next_safe_prime(5), Some(7));
+        assert_eq!(next_safe_prime(7), Some(11));
+        assert_eq!(next_safe_prime(100), Some(107));
+        assert_eq!(next_safe_prime(1_000_000_000), Some(1_000_000_007));
+        assert_eq!(next_safe_prime(u64::MAX - 100), None);
+    }
+
+    #[test]
+    fn test_goldbach_pair() {
+        assert_eq!(goldbach_pair(2), None);
+        assert_eq!(goldbach_pair(9), None);
+        assert_eq!(goldbach_pair(4), Some((2, 2)));
+        assert_eq!(goldbach_pair(28), Some((5, 23)));
+        assert_eq!(goldbach_pair(u64::MAX - 1), Some((277, u64::MAX - 278)));
+        for n in (4..2_000).step_by(2) {
+            let (p, q) = goldbach_pair(n).unwrap();
+            assert!(p <= q && is_prime(p) && is_prime(q) && p + q == n, "n = {n}");
+        }
+    }
+
+    #[test]
+    fn test_prev_prime() {
+        assert_eq!(prev_prime(0), None);
+        assert_eq!(prev_prime(2), None);
+        assert_eq!(prev_prime(3), Some(2));
+        assert_eq!(prev_prime(7), Some(5));
+        assert_eq!(prev_prime(30), Some(29));
+        assert_eq!(prev_prime(u64::MAX), Some(18_446_744_073_709_551_557));
+    }
+
+    #[test]
+    fn test_next_prev_agree_with_sieve() {
+        let sieve = FixedSieve::<{ fixed_sieve_bytes(10_000) }>::new(10_000);
+        for (p, q) in sieve.iter().zip(sieve.iter().skip(1)) {
+            assert_eq!(next_prime(p), Some(q));
+            assert_eq!(prev_prime(q), Some(p));
+        }
+    }
+
+    #[test]
+    fn test_is_prime_large() {
+        assert!(is_prime(1_000_000_007));
+        assert!(is_prime(18_446_744_073_709_551_557)); // largest u64 prime
+        assert!(!is_prime(u64::MAX));
+        // Strong pseudoprime to bases 2..=23
+        assert!(!is_prime(3_825_123_056_546_413_051));
+    }
+}
@@ -0,0 +1,100 @@
+//! Unbounded prime iterator
+
+use alloc::vec::Vec;
+
+use crate::segmented::{SegmentedSieve, SEGMENT_SPAN};
+
+/// Lazily yields every prime in ascending order, with no upper bound
+///
+/// Primes are produced one segment at a time; whenever the sieved range is
+/// exhausted its upper bound is doubled, so memory stays proportional to a
+/// single segment plus the base primes.
+/// A clone carries on from the same position independently.
+#[derive(Clone)]
+pub struct Primes {
+    sieve: SegmentedSieve,
+    buffer: Vec<u64>,
+    position: usize,
+}
+
+impl Primes {
+    /// Create an iterator starting at 2
+    pub fn new() -> Self {
+        Self {
+            sieve: SegmentedSieve::new(0, SEGMENT_SPAN - 1),
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Fill the buffer with the next batch of primes
+    /// Returns None once the u64 range is exhausted
+    fn refill(&mut self) -> Option<()> {
+        self.buffer.clear();
+        self.position = 0;
+
+        loop {
+            if let Some(segment) = self.sieve.next_segment() {
+                segment.collect_into(&mut self.buffer);
+                if !self.buffer.is_empty() {
+                    return Some(());
+                }
+                continue;
+            }
+
+            let high = self.sieve.high();
+            if high == u64::MAX {
+                return None;
+            }
+            self.sieve.extend_to(high.saturating_mul(2));
+        }
+    }
+}
+
+impl Default for Primes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for Primes {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.position == self.buffer.len() {
+            self.refill()?;
+        }
+
+        let prime = self.buffer[self.position];
+        self.position += 1;
+        Some(prime)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_primes() {
+        let first: Vec<u64> = Primes::new().take(10).collect();
+        assert_eq!(first, [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+
+        let mut primes = Primes::new().skip(99);
+        let mut copy = primes.clone();
+        assert_eq!(primes.next(), Some(541));
+        assert_eq!(copy.next(), Some(541));
+    }
+
+    #[test]
+    fn test_crosses_window_growth() {
+        // The 100,000th prime lies well beyond the initial window
+        assert_eq!(Primes::new().nth(99_999), Some(1_299_709));
+    }
+
+    #[test]
+    fn test_strictly_increasing() {
+        let primes: Vec<u64> = Primes::new().take(50_000).collect();
+        assert!(primes.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
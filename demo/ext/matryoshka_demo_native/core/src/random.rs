@@ -0,0 +1,113 @@
+//! Random prime generation over any `rand_core` generator
+
+use rand_core::RngCore;
+
+use crate::{is_prime, next_prime};
+
+/// Uniform draws `random_prime_in_range` makes before scanning instead
+/// Even near 2^64 about 1 in 44 numbers is prime, so only a range that holds
+/// few or no primes gets this far
+const MAX_DRAWS: u32 = 4_096;
+
+/// Pick a prime in `[low, high]` (both inclusive) using `rng`
+/// Candidates are drawn uniformly and kept when prime, so every prime in the
+/// range is equally likely. Returns None if the range holds no prime.
+///
+/// A range too sparse to hit within `MAX_DRAWS` draws falls back to the
+/// first prime after a random point, wrapping around to `low`; that choice
+/// favours primes that follow long gaps.
+pub fn random_prime_in_range<R: RngCore + ?Sized>(low: u64, high: u64, rng: &mut R) -> Option<u64> {
+    let low = low.max(2);
+    if low > high {
+        return None;
+    }
+
+    for _ in 0..MAX_DRAWS {
+        let candidate = uniform(rng, low, high);
+        if is_prime(candidate) {
+            return Some(candidate);
+        }
+    }
+
+    let start = uniform(rng, low, high);
+    let prime_at_or_after = |n: u64| next_prime(n - 1).filter(|&p| p <= high);
+    prime_at_or_after(start).or_else(|| prime_at_or_after(low))
+}
+
+/// Uniform value in `[low, high]` without modulo bias
+fn uniform<R: RngCore + ?Sized>(rng: &mut R, low: u64, high: u64) -> u64 {
+    let Some(range) = (high - low).checked_add(1) else {
+        return rng.next_u64(); // the whole u64 range
+    };
+
+    // Reject the 2^64 mod range lowest draws so the rest split evenly
+    let threshold = range.wrapping_neg() % range;
+    loop {
+        let x = rng.next_u64();
+        if x >= threshold {
+            return low + x % range;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic xorshift64* good enough to exercise the sampling
+    struct XorShift(u64);
+
+    impl RngCore for XorShift {
+        fn next_u32(&mut self) -> u32 {
+            (self.next_u64() >> 32) as u32
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            rand_core::impls::fill_bytes_via_next(self, dest);
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_random_prime_in_range() {
+        let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..1_000 {
+            let p = random_prime_in_range(1_000, 2_000, &mut rng).unwrap();
+            assert!((1_000..=2_000).contains(&p) && is_prime(p), "{p}");
+        }
+        let p = random_prime_in_range(0, u64::MAX, &mut rng).unwrap();
+        assert!(is_prime(p));
+    }
+
+    #[test]
+    fn test_random_prime_covers_range() {
+        let mut rng = XorShift(1);
+        let mut seen = [false; 4];
+        for _ in 0..200 {
+            let p = random_prime_in_range(0, 7, &mut rng).unwrap();
+            seen[[2, 3, 5, 7].iter().position(|&q| q == p).unwrap()] = true;
+        }
+        assert_eq!(seen, [true; 4]);
+    }
+
+    #[test]
+    fn test_random_prime_empty_range() {
+        let mut rng = XorShift(7);
+        assert_eq!(random_prime_in_range(24, 28, &mut rng), None);
+        assert_eq!(random_prime_in_range(0, 1, &mut rng), None);
+        assert_eq!(random_prime_in_range(10, 5, &mut rng), None);
+        // 1_327 and 1_361 bound a gap of 34; only the endpoint is in range
+        assert_eq!(random_prime_in_range(1_328, 1_361, &mut rng), Some(1_361));
+    }
+}
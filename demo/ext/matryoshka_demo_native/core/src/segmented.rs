@@ -0,0 +1,431 @@
+//! Segmented Sieve of Eratosthenes
+//!
+//! Sweeps `[low, high]` in fixed-size windows using the primes up to
+//! `sqrt(high)` as a base, so memory stays at one segment plus the base
+//! primes no matter how large `high` gets. Base primes are sieved lazily,
+//! only as far as the current segment needs, so stopping early is cheap.
+
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+
+use crate::presieve::{self, LARGEST_PRESIEVE_PRIME, PRESIEVE_PRIMES};
+use crate::{isqrt, CoreError, CountAlgorithm, CountStats, Sieve};
+
+/// u64 words per segment (32 KiB fits comfortably in L1 cache)
+pub(crate) const SEGMENT_WORDS: usize = 4 * 1024;
+
+/// Numbers covered by one segment (1 bit per odd number)
+pub(crate) const SEGMENT_SPAN: u64 = SEGMENT_WORDS as u64 * 128;
+
+/// A sieve that produces one segment at a time
+#[derive(Clone)]
+pub(crate) struct SegmentedSieve {
+    base_primes: Vec<u64>,
+    /// `base_primes` holds every prime up to this bound
+    base_limit: u64,
+    bits: Vec<u64>,
+    /// Start of the next segment, None once `high` has been reached
+    next_low: Option<u64>,
+    high: u64,
+    /// Numbers covered by each segment, a multiple of 128
+    span: u64,
+}
+
+/// One sieved window, storing odd numbers only
+/// Bit i (bit i % 64 of word i / 64) is the number `start + 2i + 1`, where
+/// `start` is the requested low rounded down to a multiple of 128; bits below
+/// the requested low are clear and bits from `len` on are ignored.
+/// 2 is the only even prime and is tracked with a flag instead of a bit.
+pub(crate) struct Segment<'a> {
+    start: u64,
+    len: usize,
+    includes_two: bool,
+    bits: &'a [u64],
+}
+
+impl SegmentedSieve {
+    /// Create a sieve over `[low, high]` (both inclusive)
+    pub(crate) fn new(low: u64, high: u64) -> Self {
+        Self::with_segment_words(low, high, SEGMENT_WORDS)
+    }
+
+    /// Create a sieve over `[low, high]` whose segments hold `words` u64
+    /// words (at least one) instead of [`SEGMENT_WORDS`]
+    pub(crate) fn with_segment_words(low: u64, high: u64, words: usize) -> Self {
+        Self {
+            base_primes: Vec::new(),
+            base_limit: 0,
+            bits: Vec::new(),
+            next_low: if low <= high { Some(low) } else { None },
+            high,
+            span: words.max(1) as u64 * 128,
+        }
+    }
+
+    /// Bytes held by the base primes and the segment bitset
+    pub(crate) fn memory_usage(&self) -> usize {
+        (self.base_primes.capacity() + self.bits.capacity()) * size_of::<u64>()
+    }
+
+    /// Inclusive upper bound of the range being sieved
+    pub(crate) fn high(&self) -> u64 {
+        self.high
+    }
+
+    /// Raise the upper bound to `high`, continuing where the last segment stopped
+    pub(crate) fn extend_to(&mut self, high: u64) {
+        if high <= self.high {
+            return;
+        }
+
+        if self.next_low.is_none() {
+            self.next_low = self.high.checked_add(1);
+        }
+        self.high = high;
+    }
+
+    /// Sieve and return the next segment, or None when the range is exhausted
+    pub(crate) fn next_segment(&mut self) -> Option<Segment<'_>> {
+        let low = self.next_low?;
+        let high = self.high.min(low.saturating_add(self.span - 1));
+        self.next_low = high.checked_add(1).filter(|&next| next <= self.high);
+
+        // Grow the base geometrically so re-sieving it stays amortized
+        let needed = isqrt(high);
+        if needed > self.base_limit {
+            let target = needed.max(self.base_limit.saturating_mul(2)).min(isqrt(self.high));
+            self.base_primes = Sieve::new(target).primes();
+            self.base_limit = target;
+        }
+
+        Some(sieve_segment(&self.base_primes, low, high, &mut self.bits))
+    }
+}
+
+/// Count primes in `[low, high]` one segment at a time
+/// The uncancellable reference the other counters are tested against
+#[cfg(test)]
+pub(crate) fn count(low: u64, high: u64) -> u64 {
+    let mut sieve = SegmentedSieve::new(low, high);
+    let mut count = 0;
+    while let Some(segment) = sieve.next_segment() {
+        count += segment.count() as u64;
+    }
+    count
+}
+
+/// Count primes in `[low, high]` in segments of `words` u64 words, calling
+/// `observer(segments_done, total_segments)` before the first segment and
+/// after each one
+/// Returns [`CoreError::Cancelled`] as soon as the observer breaks
+pub(crate) fn count_with(
+    low: u64,
+    high: u64,
+    words: usize,
+    mut observer: impl FnMut(u64, u64) -> ControlFlow<()>,
+) -> Result<CountStats, CoreError> {
+    let mut sieve = SegmentedSieve::with_segment_words(low, high, words);
+    let total = high.saturating_sub(low) / sieve.span + 1;
+    let mut stats = CountStats {
+        count: 0,
+        algorithm: CountAlgorithm::Segmented,
+        segments: 0,
+        peak_bytes: 0,
+    };
+    while observer(stats.segments, total).is_continue() {
+        let Some(segment) = sieve.next_segment() else {
+            return Ok(stats);
+        };
+        stats.count += segment.count() as u64;
+        stats.segments += 1;
+        stats.peak_bytes = stats.peak_bytes.max(sieve.memory_usage());
+    }
+    Err(CoreError::Cancelled)
+}
+
+/// Sieve the window `[low, high]` into `bits` using `base_primes`
+/// `bits` is reused scratch space, so callers can keep one buffer per thread
+fn sieve_segment<'a>(
+    base_primes: &[u64],
+    low: u64,
+    high: u64,
+    bits: &'a mut Vec<u64>,
+) -> Segment<'a> {
+    // Start 128-aligned so odd number start + 2i + 1 sits at pattern bit i
+    let start = low - low % 128;
+    let len = (high - start).div_ceil(2) as usize; // odd numbers in [start, high]
+
+    bits.clear();
+    bits.resize(len.div_ceil(64), u64::MAX);
+
+    // Multiples of 3..=11 come from the pattern; the primes themselves survive
+    presieve::apply(bits, start);
+    for p in PRESIEVE_PRIMES {
+        if start <= p && p <= high {
+            set_bit(bits, odd_offset(start, p));
+        }
+    }
+
+    // Odd numbers below `low` (alignment padding) and 1 are not part of the result
+    for offset in 0..((low - start) / 2) as usize {
+        clear_bit(bits, offset);
+    }
+    if start == 0 && len > 0 {
+        clear_bit(bits, 0);
+    }
+
+    for &p in base_primes {
+        if p <= LARGEST_PRESIEVE_PRIME {
+            continue;
+        }
+
+        let square = p * p;
+        if square > high {
+            break;
+        }
+
+        // First odd multiple of p inside the window, never below p*p
+        let mut first = if square >= low {
+            square
+        } else {
+            match low.div_ceil(p).checked_mul(p) {
+                Some(multiple) => multiple,
+                None => continue,
+            }
+        };
+        if first.is_multiple_of(2) {
+            first = match first.checked_add(p) {
+                Some(odd) => odd,
+                None => continue,
+            };
+        }
+        if first > high {
+            continue;
+        }
+
+        // Consecutive odd multiples are 2p apart, which is p bits
+        let mut offset = odd_offset(start, first) as u64;
+        while offset < len as u64 {
+            clear_bit(bits, offset as usize);
+            offset += p;
+        }
+    }
+
+    Segment {
+        start,
+        len,
+        includes_two: low <= 2 && 2 <= high,
+        bits,
+    }
+}
+
+/// Bit index of the odd number `n` in a segment beginning at `start`
+#[inline]
+fn odd_offset(start: u64, n: u64) -> usize {
+    ((n - start - 1) / 2) as usize
+}
+
+impl Segment<'_> {
+    /// The odd number stored at `offset`
+    #[inline]
+    fn number_at(&self, offset: usize) -> u64 {
+        self.start + 2 * offset as u64 + 1
+    }
+
+    /// The words of the bitset with bits from `len` on masked out
+    fn words(&self) -> impl Iterator<Item = u64> + '_ {
+        let tail = self.len % 64;
+        let last = self.bits.len().wrapping_sub(1);
+        self.bits.iter().enumerate().map(move |(idx, &word)| {
+            if idx == last && tail != 0 {
+                word & ((1 << tail) - 1)
+            } else {
+                word
+            }
+        })
+    }
+
+    /// Count primes in this segment
+    pub(crate) fn count(&self) -> usize {
+        let odd: u32 = self.words().map(u64::count_ones).sum();
+        usize::from(self.includes_two) + odd as usize
+    }
+
+    /// Sum the primes in this segment
+    pub(crate) fn sum(&self) -> u128 {
+        let mut sum = 0;
+        self.for_each(|p| sum += u128::from(p));
+        sum
+    }
+
+    /// Call `f` with every prime in this segment in ascending order
+    pub(crate) fn for_each(&self, mut f: impl FnMut(u64)) {
+        let _ = self.try_for_each(|p| {
+            f(p);
+            ControlFlow::Continue(())
+        });
+    }
+
+    /// Like `for_each`, but stops as soon as `f` breaks
+    pub(crate) fn try_for_each(
+        &self,
+        mut f: impl FnMut(u64) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        if self.includes_two {
+            f(2)?;
+        }
+        for (idx, mut word) in self.words().enumerate() {
+            while word != 0 {
+                f(self.number_at(idx * 64 + word.trailing_zeros() as usize))?;
+                word &= word - 1;
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Append every prime in this segment to `primes`
+    pub(crate) fn collect_into(&self, primes: &mut Vec<u64>) {
+        self.for_each(|p| primes.push(p));
+    }
+
+    /// Find the nth prime (1-indexed) within this segment
+    pub(crate) fn nth(&self, n: usize) -> Option<u64> {
+        if n == 0 {
+            return None;
+        }
+
+        let mut remaining = n;
+        if self.includes_two {
+            if remaining == 1 {
+                return Some(2);
+            }
+            remaining -= 1;
+        }
+
+        // Skip whole words by popcount, then walk the bits of the one holding it
+        for (idx, mut word) in self.words().enumerate() {
+            let ones = word.count_ones() as usize;
+            if remaining > ones {
+                remaining -= ones;
+                continue;
+            }
+            for _ in 1..remaining {
+                word &= word - 1;
+            }
+            return Some(self.number_at(idx * 64 + word.trailing_zeros() as usize));
+        }
+        None
+    }
+}
+
+#[inline]
+fn set_bit(bits: &mut [u64], offset: usize) {
+    bits[offset / 64] |= 1 << (offset % 64);
+}
+
+#[inline]
+fn clear_bit(bits: &mut [u64], offset: usize) {
+    bits[offset / 64] &= !(1 << (offset % 64));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_segment_matches_plain_sieve() {
+        assert_eq!(count(0, 10_000), 1229);
+    }
+
+    #[test]
+    fn test_many_segments() {
+        // Spans several segments, including a partial final one
+        assert_eq!(count(0, 1_000_000), 78_498);
+    }
+
+    #[test]
+    fn test_window_not_starting_at_zero() {
+        // Primes in [100, 200]: 101, 103, ..., 199
+        assert_eq!(count(100, 200), 21);
+    }
+
+    #[test]
+    fn test_extend_to_continues_after_old_high() {
+        let mut sieve = SegmentedSieve::new(0, 100);
+        let mut total = 0;
+        while let Some(segment) = sieve.next_segment() {
+            total += segment.count();
+        }
+        sieve.extend_to(1_000_000);
+        while let Some(segment) = sieve.next_segment() {
+            total += segment.count();
+        }
+        assert_eq!(total, 78_498);
+    }
+
+    #[test]
+    fn test_unaligned_windows_keep_small_primes() {
+        assert_eq!(count(0, 13), 6);
+        assert_eq!(count(2, 2), 1);
+        assert_eq!(count(3, 13), 5);
+        assert_eq!(count(13, 17), 2);
+        assert_eq!(count(14, 16), 0);
+        assert_eq!(count(0, 1), 0);
+    }
+
+    #[test]
+    fn test_segment_stores_odd_numbers_only() {
+        let mut sieve = SegmentedSieve::new(0, 1_000);
+        let segment = sieve.next_segment().unwrap();
+        assert_eq!(segment.len, 500);
+        assert_eq!(segment.bits.len(), 8);
+    }
+
+    #[test]
+    fn test_segment_sum_matches_collected_primes() {
+        let low = 1u64 << 40;
+        let mut sieve = SegmentedSieve::new(low, low + 10_000);
+        let segment = sieve.next_segment().unwrap();
+        let mut primes = Vec::new();
+        segment.collect_into(&mut primes);
+        assert_eq!(segment.sum(), primes.iter().map(|&p| u128::from(p)).sum());
+    }
+
+    #[test]
+    fn test_nth_skips_whole_words() {
+        let mut sieve = SegmentedSieve::new(0, 100_000);
+        let segment = sieve.next_segment().unwrap();
+        let mut primes = Vec::new();
+        segment.collect_into(&mut primes);
+        for (i, &p) in primes.iter().enumerate() {
+            assert_eq!(segment.nth(i + 1), Some(p));
+        }
+        assert_eq!(segment.nth(primes.len() + 1), None);
+    }
+
+    #[test]
+    fn test_count_masks_tail_bits() {
+        // Odd numbers past a 128-aligned start leave a partial last word
+        for high in [1_000, 1_001, 1_127, 1_128, 1_129] {
+            let mut sieve = SegmentedSieve::new(900, high);
+            let segment = sieve.next_segment().unwrap();
+            let mut primes = Vec::new();
+            segment.collect_into(&mut primes);
+            assert_eq!(segment.count(), primes.len(), "high = {high}");
+            assert!(primes.iter().all(|&p| (900..=high).contains(&p)));
+        }
+    }
+
+    #[test]
+    fn test_base_primes_grow_with_segments() {
+        // Only the first segment's sqrt is sieved, not sqrt(u64::MAX)
+        let mut sieve = SegmentedSieve::new(0, u64::MAX);
+        assert_eq!(sieve.next_segment().unwrap().count(), 43_390);
+        assert_eq!(sieve.base_limit, isqrt(SEGMENT_SPAN - 1));
+    }
+
+    #[test]
+    fn test_empty_range() {
+        assert_eq!(count(10, 5), 0);
+    }
+}
@@ -0,0 +1,621 @@
+//! In-memory wheel sieve
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::ControlFlow;
+
+#[cfg(feature = "atkin")]
+use crate::atkin;
+use crate::storage::BitStorage;
+use crate::wheel::{self, SieveIter};
+use crate::{CancelToken, CoreError};
+
+/// How [`Sieve::with_algorithm`] marks the primes
+/// Both fill the same wheel bitset, so every query behaves identically
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SieveAlgorithm {
+    /// Sieve of Eratosthenes, crossing off multiples of each prime
+    #[default]
+    Eratosthenes,
+    /// Sieve of Atkin, toggling solutions of three quadratic forms
+    /// (requires the `atkin` feature)
+    #[cfg(feature = "atkin")]
+    Atkin,
+}
+
+/// Reusable Sieve of Eratosthenes on a mod-30 wheel
+/// Byte k holds the 8 numbers 30k + r with r coprime to 30, so multiples
+/// of 2, 3 and 5 take no space (1 byte per 30 numbers instead of ~4)
+/// Build it once and answer many queries; the whole range lives in memory,
+/// so prefer the free functions for one-off questions about huge limits
+/// The bitset lives in a Vec by default; any [`BitStorage`] works for queries
+#[derive(Clone)]
+pub struct Sieve<S = Vec<u8>> {
+    bits: S,
+    limit: u64,
+}
+
+impl Sieve {
+    /// Sieve all numbers up to and including `limit`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bitset for `limit` cannot be addressed on this target;
+    /// use [`Sieve::try_new`] to handle that case
+    pub fn new(limit: u64) -> Self {
+        match Self::try_new(limit) {
+            Ok(sieve) => sieve,
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    /// Sieve all numbers up to and including `limit`
+    /// Returns [`CoreError::LimitTooLarge`] instead of panicking or aborting when
+    /// the bitset cannot be addressed or allocated
+    pub fn try_new(limit: u64) -> Result<Self, CoreError> {
+        Self::try_with_algorithm(limit, SieveAlgorithm::Eratosthenes)
+    }
+
+    /// Sieve all numbers up to and including `limit` with `algorithm`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bitset for `limit` cannot be addressed on this target;
+    /// use [`Sieve::try_with_algorithm`] to handle that case
+    pub fn with_algorithm(limit: u64, algorithm: SieveAlgorithm) -> Self {
+        match Self::try_with_algorithm(limit, algorithm) {
+            Ok(sieve) => sieve,
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    /// Sieve all numbers up to and including `limit` with `algorithm`
+    /// Returns [`CoreError::LimitTooLarge`] when the bitset cannot be addressed
+    /// or allocated
+    pub fn try_with_algorithm(limit: u64, algorithm: SieveAlgorithm) -> Result<Self, CoreError> {
+        let bits = match algorithm {
+            SieveAlgorithm::Eratosthenes => {
+                let mut bits = allocate(limit, 0xFF)?; // All bits set (all potentially prime)
+                wheel::sieve(&mut bits, limit);
+                bits
+            }
+            #[cfg(feature = "atkin")]
+            SieveAlgorithm::Atkin => {
+                let mut bits = allocate(limit, 0)?; // Atkin toggles candidates on
+                atkin::sieve(&mut bits, limit);
+                bits
+            }
+        };
+        Ok(Self { bits, limit })
+    }
+
+    /// Sieve all numbers up to and including `limit` with Eratosthenes,
+    /// calling `observer(segments_done, total_segments)` after each segment
+    /// so callers can report progress on huge limits
+    /// Returns [`CoreError::Cancelled`] once the observer breaks, or
+    /// [`CoreError::LimitTooLarge`] when the bitset cannot be allocated
+    pub fn try_with_observer(
+        limit: u64,
+        observer: impl FnMut(u64, u64) -> ControlFlow<()>,
+    ) -> Result<Self, CoreError> {
+        let mut bits = allocate(limit, 0xFF)?;
+        match wheel::sieve_with(&mut bits, limit, observer) {
+            ControlFlow::Continue(()) => Ok(Self { bits, limit }),
+            ControlFlow::Break(()) => Err(CoreError::Cancelled),
+        }
+    }
+
+    /// Sieve all numbers up to and including `limit`, checking `cancel`
+    /// after each segment
+    /// Returns [`CoreError::Cancelled`] once it is set
+    pub fn try_new_with_cancel(limit: u64, cancel: &CancelToken) -> Result<Self, CoreError> {
+        Self::try_with_observer(limit, |_, _| cancel.observe())
+    }
+
+    /// Grow the sieve to cover numbers up to and including `new_limit`
+    /// Only the new range is sieved, always with Eratosthenes; does nothing
+    /// if `new_limit <= limit`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bitset for `new_limit` cannot be addressed on this target;
+    /// use [`Sieve::try_extend_to`] to handle that case
+    pub fn extend_to(&mut self, new_limit: u64) {
+        if let Err(error) = self.try_extend_to(new_limit) {
+            panic!("{error}");
+        }
+    }
+
+    /// Grow the sieve to cover numbers up to and including `new_limit`
+    /// Returns [`CoreError::LimitTooLarge`] and leaves the sieve unchanged when
+    /// the larger bitset cannot be addressed or allocated
+    pub fn try_extend_to(&mut self, new_limit: u64) -> Result<(), CoreError> {
+        if new_limit <= self.limit {
+            return Ok(());
+        }
+
+        let too_large = CoreError::LimitTooLarge { limit: new_limit };
+        let num_bytes = wheel::bytes_for(new_limit).ok_or(too_large)?;
+        self.bits
+            .try_reserve_exact(num_bytes - self.bits.len())
+            .map_err(|_| too_large)?;
+
+        let old_limit = self.limit;
+
+        // Bits past the old limit in its last byte were cleared; they are candidates again
+        let last = self.bits.len() - 1;
+        for (bit, &residue) in wheel::WHEEL.iter().enumerate() {
+            if last as u64 * 30 + residue > old_limit {
+                self.bits[last] |= 1 << bit;
+            }
+        }
+        self.bits.resize(num_bytes, 0xFF);
+        self.limit = new_limit;
+        wheel::clear_past_limit(&mut self.bits, new_limit);
+        wheel::clear(&mut self.bits, new_limit, 1);
+
+        wheel::run_sieve(&mut self.bits, new_limit, old_limit + 1);
+        Ok(())
+    }
+
+    /// Heap bytes held by the sieve, including spare bitset capacity
+    /// The wheel bitset is the only allocation, about `limit / 30` bytes
+    pub fn memory_usage(&self) -> usize {
+        self.bits.capacity()
+    }
+
+    /// Rebuild a sieve produced by [`Sieve::to_bytes`] without re-sieving
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(DecodeError::TooShort);
+        }
+        if bytes[0..4] != MAGIC {
+            return Err(DecodeError::BadMagic);
+        }
+        if bytes[4] != FORMAT_VERSION {
+            return Err(DecodeError::UnsupportedVersion(bytes[4]));
+        }
+
+        let mut limit = [0; 8];
+        limit.copy_from_slice(&bytes[8..16]);
+        let limit = u64::from_le_bytes(limit);
+
+        let payload = &bytes[HEADER_LEN..];
+        let expected = limit / 30 + 1;
+        if payload.len() as u64 != expected {
+            return Err(DecodeError::LengthMismatch {
+                expected,
+                actual: payload.len() as u64,
+            });
+        }
+
+        // Bits past `limit` (and the bit for 1) are never valid primes;
+        // clear whatever the buffer holds there so counts stay exact
+        let mut bits = payload.to_vec();
+        wheel::clear_past_limit(&mut bits, limit);
+        wheel::clear(&mut bits, limit, 1);
+
+        Ok(Self { bits, limit })
+    }
+}
+
+impl<S: BitStorage> Sieve<S> {
+    /// Sieve all numbers up to and including `limit` into caller-provided
+    /// `storage`, such as the `mmap` feature's `MmapStorage` for bitsets
+    /// larger than RAM; its previous contents are overwritten
+    /// Returns [`CoreError::InvalidArgument`] unless `storage` holds exactly
+    /// `limit / 30 + 1` bytes
+    pub fn try_with_storage(limit: u64, mut storage: S) -> Result<Self, CoreError> {
+        if wheel::bytes_for(limit) != Some(storage.bytes().len()) {
+            return Err(CoreError::InvalidArgument {
+                reason: "storage length does not match the sieve limit",
+            });
+        }
+
+        let bits = storage.bytes_mut();
+        bits.fill(0xFF); // All bits set (all potentially prime)
+        wheel::sieve(bits, limit);
+        Ok(Self {
+            bits: storage,
+            limit,
+        })
+    }
+
+    /// Upper bound (inclusive) this sieve covers
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Check whether `n` is prime; numbers above `limit` report false
+    pub fn is_prime(&self, n: u64) -> bool {
+        wheel::is_set(self.bits.bytes(), self.limit, n)
+    }
+
+    /// Count the primes up to and including `limit`
+    pub fn count(&self) -> u64 {
+        wheel::count(self.bits.bytes(), self.limit)
+    }
+
+    /// Count the primes up to and including `n`, capped at `limit`
+    pub fn count_up_to(&self, n: u64) -> u64 {
+        let n = n.min(self.limit);
+        wheel::count(&self.bits.bytes()[..(n / 30) as usize + 1], n)
+    }
+
+    /// Find the nth prime (1-indexed), or None if it lies above `limit`
+    pub fn nth(&self, n: u64) -> Option<u64> {
+        wheel::nth(self.iter(), n)
+    }
+
+    /// Split an even `n` with `4 <= n <= limit` into two primes `(p, n - p)`
+    /// with `p` as small as possible, answering from the bitset
+    /// Returns None for odd `n`, `n < 4` or `n > limit`
+    pub fn goldbach_pair(&self, n: u64) -> Option<(u64, u64)> {
+        if n < 4 || !n.is_multiple_of(2) || n > self.limit {
+            return None;
+        }
+
+        self.iter()
+            .take_while(|&p| p <= n / 2)
+            .find(|&p| self.is_prime(n - p))
+            .map(|p| (p, n - p))
+    }
+
+    /// Iterate over the primes in ascending order
+    pub fn iter(&self) -> SieveIter<'_> {
+        SieveIter::new(self.bits.bytes(), self.limit)
+    }
+
+    /// The raw wheel bitset, borrowed without copying
+    ///
+    /// Layout: byte k covers 30k..30k + 30; bit i (least significant first)
+    /// is set iff 30k + r_i is prime, with r = [1, 7, 11, 13, 17, 19, 23, 29].
+    /// There are `limit / 30 + 1` bytes and bits above `limit` are clear.
+    /// 2, 3 and 5 are not stored, so consumers must add them back
+    pub fn as_bits(&self) -> &[u8] {
+        self.bits.bytes()
+    }
+
+    /// Collect all primes in the sieve in ascending order
+    pub(crate) fn primes(&self) -> Vec<u64> {
+        self.iter().collect()
+    }
+
+    /// Serialize the sieve as a 16-byte header followed by the wheel bitset
+    ///
+    /// Header layout (all integers little-endian):
+    /// - bytes 0..4: magic `b"MDSV"`
+    /// - byte 4: format version (currently 1)
+    /// - bytes 5..8: reserved, zero
+    /// - bytes 8..16: `limit` as u64
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.bits.bytes().len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&[0, 0, 0]);
+        bytes.extend_from_slice(&self.limit.to_le_bytes());
+        bytes.extend_from_slice(self.bits.bytes());
+        bytes
+    }
+
+    /// The storage holding the wheel bitset
+    pub fn storage(&self) -> &S {
+        &self.bits
+    }
+
+    /// Give up the sieve and return its storage
+    pub fn into_storage(self) -> S {
+        self.bits
+    }
+}
+
+impl<'a, S: BitStorage> IntoIterator for &'a Sieve<S> {
+    type Item = u64;
+    type IntoIter = SieveIter<'a>;
+
+    fn into_iter(self) -> SieveIter<'a> {
+        self.iter()
+    }
+}
+
+/// Allocate the bitset for `0..=limit` with every byte set to `fill`
+fn allocate(limit: u64, fill: u8) -> Result<Vec<u8>, CoreError> {
+    let num_bytes = wheel::bytes_for(limit).ok_or(CoreError::LimitTooLarge { limit })?;
+    let mut bits = Vec::new();
+    bits.try_reserve_exact(num_bytes)
+        .map_err(|_| CoreError::LimitTooLarge { limit })?;
+    bits.resize(num_bytes, fill);
+    Ok(bits)
+}
+
+/// Magic bytes at the start of every serialized sieve
+const MAGIC: [u8; 4] = *b"MDSV";
+
+/// Bumped whenever the payload layout changes
+const FORMAT_VERSION: u8 = 1;
+
+/// Header size in bytes: magic, version, 3 reserved bytes, u64 limit
+const HEADER_LEN: usize = 16;
+
+/// Why a byte buffer could not be turned back into a sieve
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Shorter than the fixed-size header
+    TooShort,
+    /// The buffer does not start with the sieve magic
+    BadMagic,
+    /// Written by an incompatible version of this crate
+    UnsupportedVersion(u8),
+    /// Payload length does not match the limit in the header
+    LengthMismatch { expected: u64, actual: u64 },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooShort => write!(f, "buffer is shorter than the sieve header"),
+            Self::BadMagic => write!(f, "buffer is not a serialized sieve"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported sieve format version {version}")
+            }
+            Self::LengthMismatch { expected, actual } => {
+                write!(f, "expected {expected} payload bytes, found {actual}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::is_prime;
+
+    #[test]
+    fn test_bit_sieve_wheel_edges() {
+        for (limit, expected) in [
+            (0, &[][..]),
+            (1, &[]),
+            (2, &[2]),
+            (6, &[2, 3, 5]),
+            (7, &[2, 3, 5, 7]),
+            (30, &[2, 3, 5, 7, 11, 13, 17, 19, 23, 29]),
+            (31, &[2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31]),
+        ] {
+            let sieve = Sieve::new(limit);
+            assert_eq!(sieve.primes(), expected, "limit {limit}");
+        }
+    }
+
+    #[test]
+    fn test_bit_sieve_wheel_matches_trial_division() {
+        let sieve = Sieve::new(100_000);
+        for n in 0..=100_000 {
+            assert_eq!(sieve.is_prime(n), is_prime(n), "n = {n}");
+        }
+        assert_eq!(sieve.bits.len(), 100_000 / 30 + 1);
+    }
+
+    #[test]
+    fn test_sieve_queries() {
+        let sieve = Sieve::new(1_000);
+        assert_eq!(sieve.limit(), 1_000);
+        assert!(sieve.is_prime(997));
+        assert!(!sieve.is_prime(999));
+        assert!(!sieve.is_prime(1_009)); // prime, but above the limit
+        assert_eq!(sieve.count(), 168);
+        assert_eq!(sieve.nth(1), Some(2));
+        assert_eq!(sieve.nth(168), Some(997));
+        assert_eq!(sieve.nth(169), None);
+        assert_eq!(sieve.nth(0), None);
+
+        let copy = sieve.clone();
+        assert_eq!((copy.limit(), copy.count()), (1_000, 168));
+    }
+
+    #[test]
+    fn test_sieve_iter_matches_segmented() {
+        let sieve = Sieve::new(100_000);
+        let primes: Vec<u64> = sieve.iter().collect();
+        assert_eq!(primes, crate::primes_up_to(100_000));
+        assert_eq!((&sieve).into_iter().count() as u64, sieve.count());
+    }
+
+    #[test]
+    fn test_extend_to_matches_fresh_sieve() {
+        // Growth steps land inside, on and past byte boundaries, including from 0
+        let mut sieve = Sieve::new(0);
+        for new_limit in [1, 7, 29, 30, 31, 100, 961, 10_000, 10_007, 250_000] {
+            sieve.extend_to(new_limit);
+            let fresh = Sieve::new(new_limit);
+            assert_eq!(sieve.limit(), new_limit);
+            assert_eq!(sieve.bits, fresh.bits, "limit = {new_limit}");
+        }
+    }
+
+    #[test]
+    fn test_extend_to_lower_limit_is_noop() {
+        let mut sieve = Sieve::new(1_000);
+        sieve.extend_to(10);
+        assert_eq!(sieve.limit(), 1_000);
+        assert_eq!(sieve.count(), 168);
+    }
+
+    #[test]
+    fn test_try_new_rejects_unallocatable_limit() {
+        assert_eq!(
+            Sieve::try_new(u64::MAX).err(),
+            Some(CoreError::LimitTooLarge { limit: u64::MAX })
+        );
+        assert_eq!(Sieve::try_new(1_000).unwrap().count(), 168);
+    }
+
+    #[test]
+    fn test_try_extend_to_rejects_unallocatable_limit() {
+        let mut sieve = Sieve::new(1_000);
+        assert_eq!(
+            sieve.try_extend_to(u64::MAX),
+            Err(CoreError::LimitTooLarge { limit: u64::MAX })
+        );
+        assert_eq!(sieve.limit(), 1_000);
+        assert_eq!(sieve.try_extend_to(10_000), Ok(()));
+        assert_eq!(sieve.count(), 1_229);
+    }
+
+    #[cfg(feature = "atkin")]
+    #[test]
+    fn test_atkin_matches_eratosthenes() {
+        for limit in (0..2_000).chain([65_536, 1_000_003]) {
+            let atkin = Sieve::with_algorithm(limit, SieveAlgorithm::Atkin);
+            let eratosthenes = Sieve::new(limit);
+            assert_eq!(atkin.bits, eratosthenes.bits, "limit = {limit}");
+        }
+    }
+
+    #[test]
+    fn test_count_up_to() {
+        let sieve = Sieve::new(10_000);
+        for n in [0, 1, 2, 29, 30, 31, 100, 9_973, 9_999] {
+            assert_eq!(sieve.count_up_to(n), Sieve::new(n).count(), "n = {n}");
+        }
+        assert_eq!(sieve.count_up_to(u64::MAX), 1_229);
+    }
+
+    #[test]
+    fn test_goldbach_pair() {
+        let sieve = Sieve::new(10_000);
+        assert_eq!(sieve.goldbach_pair(4), Some((2, 2)));
+        assert_eq!(sieve.goldbach_pair(7), None);
+        assert_eq!(sieve.goldbach_pair(10_002), None);
+        for n in (4..=10_000).step_by(2) {
+            assert_eq!(sieve.goldbach_pair(n), crate::goldbach_pair(n), "n = {n}");
+        }
+    }
+
+    #[test]
+    fn test_observer_sees_every_segment() {
+        let limit = 3 * 30 * wheel::SEGMENT_BYTES + 5;
+        let mut calls = Vec::new();
+        let sieve = Sieve::try_with_observer(limit, |done, total| {
+            calls.push((done, total));
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(calls, [(1, 4), (2, 4), (3, 4), (4, 4)]);
+        assert_eq!(sieve.count(), Sieve::new(limit).count());
+        assert_eq!(sieve.as_bits(), Sieve::new(limit).as_bits());
+    }
+
+    #[test]
+    fn test_observer_can_cancel() {
+        let limit = 10 * 30 * wheel::SEGMENT_BYTES;
+        let mut calls = 0;
+        let result = Sieve::try_with_observer(limit, |done, _| {
+            calls += 1;
+            if done == 2 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert_eq!(result.err(), Some(CoreError::Cancelled));
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn test_storage_length_must_match_limit() {
+        let sieve = Sieve::try_with_storage(1_000, alloc::vec![0; 34]).unwrap();
+        assert_eq!(sieve.count(), 168);
+        let result = Sieve::try_with_storage(1_000, alloc::vec![0; 10]);
+        assert!(matches!(result, Err(CoreError::InvalidArgument { .. })));
+    }
+
+    #[test]
+    fn test_try_new_with_cancel() {
+        let cancel = CancelToken::new();
+        assert_eq!(Sieve::try_new_with_cancel(1_000, &cancel).unwrap().count(), 168);
+        cancel.cancel();
+        assert_eq!(
+            Sieve::try_new_with_cancel(1_000, &cancel).err(),
+            Some(CoreError::Cancelled)
+        );
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let mut sieve = Sieve::new(29);
+        assert_eq!(sieve.memory_usage(), 1);
+        sieve.extend_to(300_000);
+        assert!(sieve.memory_usage() >= 10_001);
+    }
+
+    #[test]
+    fn test_as_bits_layout() {
+        const RESIDUES: [u64; 8] = [1, 7, 11, 13, 17, 19, 23, 29];
+        let sieve = Sieve::new(1_000);
+        let bits = sieve.as_bits();
+        assert_eq!(bits.len(), 34);
+        // 1 is not prime; 7 ..= 29 all are
+        assert_eq!(bits[0], 0b1111_1110);
+
+        let decoded: Vec<u64> = (0..bits.len() as u64)
+            .flat_map(|k| RESIDUES.iter().map(move |&r| 30 * k + r))
+            .enumerate()
+            .filter(|&(i, _)| bits[i / 8] & (1 << (i % 8)) != 0)
+            .map(|(_, n)| n)
+            .collect();
+        assert_eq!(&decoded[..], &sieve.primes()[3..]);
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let sieve = Sieve::new(10_000);
+        let bytes = sieve.to_bytes();
+        assert_eq!(&bytes[0..4], b"MDSV");
+
+        let restored = Sieve::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.limit(), 10_000);
+        assert_eq!(restored.primes(), sieve.primes());
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_bad_input() {
+        let bytes = Sieve::new(100).to_bytes();
+
+        assert_eq!(Sieve::from_bytes(&bytes[..8]).err(), Some(DecodeError::TooShort));
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert_eq!(Sieve::from_bytes(&bad_magic).err(), Some(DecodeError::BadMagic));
+
+        let mut bad_version = bytes.clone();
+        bad_version[4] = 99;
+        assert_eq!(
+            Sieve::from_bytes(&bad_version).err(),
+            Some(DecodeError::UnsupportedVersion(99))
+        );
+
+        assert_eq!(
+            Sieve::from_bytes(&bytes[..bytes.len() - 1]).err(),
+            Some(DecodeError::LengthMismatch {
+                expected: 4,
+                actual: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_ignores_bits_past_limit() {
+        let mut bytes = Sieve::new(100).to_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] = 0xFF; // 91..=119, all past 100 except 91 and 97
+        bytes[HEADER_LEN] |= 1; // the number 1
+
+        let sieve = Sieve::from_bytes(&bytes).unwrap();
+        assert!(sieve.iter().all(|p| p <= 100));
+        assert!(!sieve.is_prime(1));
+        assert!(!sieve.is_prime(119));
+        assert_eq!(sieve.iter().max(), Some(97));
+    }
+}
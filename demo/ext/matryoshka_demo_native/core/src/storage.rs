@@ -0,0 +1,116 @@
+//! Backing stores for the [`Sieve`](crate::Sieve) bitset
+//!
+//! The sieve only needs a byte slice it can read and write, so the storage
+//! is a small trait: a Vec by default, or with the `mmap` feature a file
+//! mapped into memory, letting the OS page a bitset larger than RAM to disk.
+
+use alloc::vec::Vec;
+
+#[cfg(feature = "mmap")]
+use std::fs::OpenOptions;
+#[cfg(feature = "mmap")]
+use std::io;
+#[cfg(feature = "mmap")]
+use std::path::Path;
+
+#[cfg(feature = "mmap")]
+use memmap2::MmapMut;
+
+#[cfg(feature = "mmap")]
+use crate::{wheel, CoreError};
+
+/// Byte buffer holding a wheel bitset
+pub trait BitStorage {
+    /// The bitset bytes
+    fn bytes(&self) -> &[u8];
+
+    /// The bitset bytes, writable
+    fn bytes_mut(&mut self) -> &mut [u8];
+}
+
+impl BitStorage for Vec<u8> {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+/// Wheel bitset in a memory-mapped file (requires the `mmap` feature)
+/// Pages are loaded and written back by the OS on demand, so a sieve can
+/// exceed physical memory at the cost of disk I/O
+#[cfg(feature = "mmap")]
+pub struct MmapStorage {
+    map: MmapMut,
+}
+
+#[cfg(feature = "mmap")]
+impl MmapStorage {
+    /// Create (or truncate) the file at `path` and map it, sized for a sieve
+    /// up to and including `limit`; pass the result to
+    /// [`Sieve::try_with_storage`](crate::Sieve::try_with_storage)
+    /// Fails with `InvalidInput` if the bitset cannot be addressed on this target
+    pub fn create(path: impl AsRef<Path>, limit: u64) -> io::Result<Self> {
+        let len = wheel::bytes_for(limit).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                CoreError::LimitTooLarge { limit },
+            )
+        })?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(len as u64)?;
+
+        // SAFETY: the mapping is only sound while no one else resizes or
+        // writes the file; it was just truncated for this sieve's exclusive use
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        Ok(Self { map })
+    }
+
+    /// Write dirty pages back to the file
+    pub fn flush(&self) -> io::Result<()> {
+        self.map.flush()
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl BitStorage for MmapStorage {
+    fn bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.map
+    }
+}
+
+#[cfg(all(test, feature = "mmap"))]
+mod tests {
+    use super::*;
+    use crate::Sieve;
+
+    #[test]
+    fn test_mmap_sieve_matches_vec_sieve() {
+        let path =
+            std::env::temp_dir().join(std::format!("matryoshka-{}.bits", std::process::id()));
+        let limit = 1_000_000;
+
+        let sieve =
+            Sieve::try_with_storage(limit, MmapStorage::create(&path, limit).unwrap()).unwrap();
+        let heap = Sieve::new(limit);
+        assert_eq!(sieve.count(), 78_498);
+        assert_eq!(sieve.as_bits(), heap.as_bits());
+        assert!(sieve.is_prime(999_983));
+        sieve.storage().flush().unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), heap.as_bits());
+        drop(sieve);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
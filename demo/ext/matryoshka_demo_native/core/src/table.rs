@@ -0,0 +1,65 @@
+//! Compile-time table of the first primes
+//!
+//! Built by a const fn sieve, so answering small queries from it neither
+//! allocates nor sieves at runtime.
+
+/// Number of primes in the table
+pub(crate) const PRIME_TABLE_LEN: usize = 4_096;
+
+/// The last prime in the table, p_4096
+pub(crate) const PRIME_TABLE_MAX: u64 = 38_873;
+
+/// The first `PRIME_TABLE_LEN` primes in ascending order
+pub(crate) static PRIME_TABLE: [u64; PRIME_TABLE_LEN] = build_table();
+
+const fn build_table() -> [u64; PRIME_TABLE_LEN] {
+    const LIMIT: usize = PRIME_TABLE_MAX as usize;
+
+    let mut composite = [false; LIMIT + 1];
+    let mut table = [0u64; PRIME_TABLE_LEN];
+    let mut count = 0;
+    let mut n = 2;
+    while n <= LIMIT {
+        if !composite[n] {
+            table[count] = n as u64;
+            count += 1;
+            let mut multiple = n * n;
+            while multiple <= LIMIT {
+                composite[multiple] = true;
+                multiple += n;
+            }
+        }
+        n += 1;
+    }
+    assert!(count == PRIME_TABLE_LEN, "PRIME_TABLE_MAX is not p_4096");
+    table
+}
+
+/// Check `n <= PRIME_TABLE_MAX` for primality by binary search
+#[inline]
+pub(crate) fn contains(n: u64) -> bool {
+    debug_assert!(n <= PRIME_TABLE_MAX);
+    PRIME_TABLE.binary_search(&n).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixed_sieve_bytes, FixedSieve};
+
+    #[test]
+    fn test_table_matches_sieve() {
+        let sieve = FixedSieve::<{ fixed_sieve_bytes(PRIME_TABLE_MAX) }>::new(PRIME_TABLE_MAX);
+        assert!(sieve.iter().eq(PRIME_TABLE.iter().copied()));
+        assert_eq!(PRIME_TABLE[PRIME_TABLE_LEN - 1], PRIME_TABLE_MAX);
+    }
+
+    #[test]
+    fn test_contains() {
+        assert!(!contains(0));
+        assert!(!contains(1));
+        assert!(contains(2));
+        assert!(!contains(38_871));
+        assert!(contains(PRIME_TABLE_MAX));
+    }
+}
@@ -0,0 +1,93 @@
+//! Euler's totient function
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::factorize;
+
+/// Compute phi(k) for every k in `0..=limit` with a linear sieve
+/// Each composite is visited exactly once, through its smallest prime factor.
+/// phi(0) is reported as 0.
+pub fn totients_up_to(limit: u64) -> Vec<u64> {
+    let n = usize::try_from(limit)
+        .ok()
+        .filter(|&n| n < usize::MAX)
+        .expect("totient limit exceeds addressable memory");
+
+    // 0 marks "not yet reached", which after the sweep only primes hit
+    let mut phi = vec![0u64; n + 1];
+    if n >= 1 {
+        phi[1] = 1;
+    }
+
+    let mut primes: Vec<usize> = Vec::new();
+    for i in 2..=n {
+        if phi[i] == 0 {
+            phi[i] = i as u64 - 1;
+            primes.push(i);
+        }
+        for &p in &primes {
+            let Some(multiple) = i.checked_mul(p).filter(|&m| m <= n) else {
+                break;
+            };
+            if i.is_multiple_of(p) {
+                // p already divides i, so phi(i * p) = phi(i) * p
+                phi[multiple] = phi[i] * p as u64;
+                break;
+            }
+            phi[multiple] = phi[i] * (p as u64 - 1);
+        }
+    }
+    phi
+}
+
+/// Euler's totient of a single `n`: the count of `1..=n` coprime to n
+/// Uses the factorization of n, so no sieve is allocated; phi(0) is 0
+pub fn totient(n: u64) -> u64 {
+    if n == 0 {
+        return 0;
+    }
+
+    factorize(n)
+        .into_iter()
+        .fold(n, |phi, (p, _)| phi / p * (p - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gcd;
+
+    #[test]
+    fn test_totients_up_to_small() {
+        assert_eq!(totients_up_to(0), [0]);
+        assert_eq!(totients_up_to(1), [0, 1]);
+        assert_eq!(totients_up_to(12), [0, 1, 1, 2, 2, 4, 2, 6, 4, 6, 4, 10, 4]);
+    }
+
+    #[test]
+    fn test_totients_match_gcd_count() {
+        let phi = totients_up_to(300);
+        for n in 1..=300u64 {
+            let coprime = (1..=n).filter(|&k| gcd(n, k) == 1).count() as u64;
+            assert_eq!(phi[n as usize], coprime, "phi({n})");
+        }
+    }
+
+    #[test]
+    fn test_totient_matches_sieve() {
+        let phi = totients_up_to(100_000);
+        for n in 0..=100_000u64 {
+            assert_eq!(totient(n), phi[n as usize], "phi({n})");
+        }
+    }
+
+    #[test]
+    fn test_totient_large() {
+        // 2^61 - 1 is prime
+        assert_eq!(totient((1 << 61) - 1), (1 << 61) - 2);
+        assert_eq!(totient(1 << 63), 1 << 62);
+        // 600851475143 = 71 * 839 * 1471 * 6857
+        assert_eq!(totient(600_851_475_143), 70 * 838 * 1470 * 6856);
+    }
+}
@@ -0,0 +1,278 @@
+//! Mod-30 wheel bitset shared by the heap and fixed-capacity sieves
+//!
+//! Byte k holds the 8 numbers 30k + r with r coprime to 30, so multiples
+//! of 2, 3 and 5 take no space (1 byte per 30 numbers instead of ~4).
+//! Everything here works on plain byte slices and never allocates.
+
+use core::ops::ControlFlow;
+
+use crate::isqrt;
+
+/// Residues modulo 30 that are coprime to 30, one per bit of a wheel byte
+pub(crate) const WHEEL: [u64; 8] = [1, 7, 11, 13, 17, 19, 23, 29];
+
+/// Distance from each wheel residue to the next one
+const WHEEL_GAPS: [u64; 8] = [6, 4, 2, 4, 2, 4, 6, 2];
+
+/// Bit index of each residue modulo 30, or NOT_ON_WHEEL for multiples of 2, 3 or 5
+const NOT_ON_WHEEL: u8 = 0xFF;
+const WHEEL_INDEX: [u8; 30] = {
+    let mut index = [NOT_ON_WHEEL; 30];
+    let mut i = 0;
+    while i < WHEEL.len() {
+        index[WHEEL[i] as usize] = i as u8;
+        i += 1;
+    }
+    index
+};
+
+/// The primes below 7, which the wheel does not store
+const SMALL_PRIMES: [u64; 3] = [2, 3, 5];
+
+/// Wheel bytes needed to cover `0..=limit`, or None if that overflows usize
+pub(crate) const fn bytes_for(limit: u64) -> Option<usize> {
+    let bytes = limit / 30 + 1;
+    if bytes > usize::MAX as u64 {
+        None
+    } else {
+        Some(bytes as usize)
+    }
+}
+
+/// Sieve `bits` (exactly `bytes_for(limit)` bytes) covering `0..=limit`
+/// Every byte must start out as 0xFF
+pub(crate) fn sieve(bits: &mut [u8], limit: u64) {
+    let _ = sieve_with(bits, limit, |_, _| ControlFlow::Continue(()));
+}
+
+/// Like [`sieve`], reporting each segment to `observer` as in [`run_sieve_with`]
+pub(crate) fn sieve_with(
+    bits: &mut [u8],
+    limit: u64,
+    observer: impl FnMut(u64, u64) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    clear_past_limit(bits, limit);
+    clear(bits, limit, 1); // 1 is not prime
+    run_sieve_with(bits, limit, 0, observer)
+}
+
+/// Check if a number is marked as prime
+#[inline]
+pub(crate) fn is_set(bits: &[u8], limit: u64, n: u64) -> bool {
+    if n > limit {
+        return false;
+    }
+    if n == 2 || n == 3 || n == 5 {
+        return true;
+    }
+    let bit_idx = WHEEL_INDEX[(n % 30) as usize];
+    if bit_idx == NOT_ON_WHEEL {
+        return false;
+    }
+    (bits[(n / 30) as usize] & (1 << bit_idx)) != 0
+}
+
+/// Mark a number as composite (not prime)
+/// Numbers off the wheel are already composite and are ignored
+#[inline]
+pub(crate) fn clear(bits: &mut [u8], limit: u64, n: u64) {
+    if n > limit {
+        return;
+    }
+    let bit_idx = WHEEL_INDEX[(n % 30) as usize];
+    if bit_idx == NOT_ON_WHEEL {
+        return;
+    }
+    bits[(n / 30) as usize] &= !(1 << bit_idx);
+}
+
+/// Flip the bit for `n`; numbers off the wheel are ignored
+#[cfg(feature = "atkin")]
+#[inline]
+pub(crate) fn toggle(bits: &mut [u8], limit: u64, n: u64) {
+    if n > limit {
+        return;
+    }
+    let bit_idx = WHEEL_INDEX[(n % 30) as usize];
+    if bit_idx == NOT_ON_WHEEL {
+        return;
+    }
+    bits[(n / 30) as usize] ^= 1 << bit_idx;
+}
+
+/// Clear the bits in the last byte that lie past `limit` so they never count
+pub(crate) fn clear_past_limit(bits: &mut [u8], limit: u64) {
+    let last = bits.len() - 1;
+    bits[last] &= last_byte_mask(bits.len(), limit);
+}
+
+/// Wheel bytes sieved per segment (32 KiB, about 983k numbers)
+pub(crate) const SEGMENT_BYTES: u64 = 32 * 1024;
+
+/// Run the sieve algorithm over `[low, limit]`
+/// Numbers below `low` must already be sieved
+#[cfg(feature = "alloc")]
+pub(crate) fn run_sieve(bits: &mut [u8], limit: u64, low: u64) {
+    let _ = run_sieve_with(bits, limit, low, |_, _| ControlFlow::Continue(()));
+}
+
+/// Run the sieve algorithm over `[low, limit]` one segment at a time,
+/// calling `observer(segments_done, total_segments)` after each one
+/// Returns Break as soon as the observer does, leaving the rest unsieved;
+/// numbers below `low` must already be sieved
+pub(crate) fn run_sieve_with(
+    bits: &mut [u8],
+    limit: u64,
+    low: u64,
+    mut observer: impl FnMut(u64, u64) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    let first_byte = low / 30;
+    let total = (limit / 30 - first_byte) / SEGMENT_BYTES + 1;
+    for done in 1..=total {
+        let segment_low = low.max((first_byte + (done - 1) * SEGMENT_BYTES) * 30);
+        // The last segment can end past u64::MAX, where `limit` caps it anyway
+        let segment_high = (first_byte + done * SEGMENT_BYTES)
+            .checked_mul(30)
+            .map_or(limit, |end| (end - 1).min(limit));
+        sieve_range(bits, limit, segment_low, segment_high);
+        observer(done, total)?;
+    }
+    ControlFlow::Continue(())
+}
+
+/// Cross off composites in `[low, high]` (`high <= limit`)
+/// Primes up to sqrt(high) are read from `bits` itself: each one is final by
+/// the time the walk reaches it, because its smaller factors came first
+fn sieve_range(bits: &mut [u8], limit: u64, low: u64, high: u64) {
+    let sqrt_high = isqrt(high);
+
+    // Candidates start at 7, the first wheel number past 1
+    let mut i = 7;
+    let mut i_wheel = 1;
+    while i <= sqrt_high {
+        if is_set(bits, limit, i) {
+            // Mark i * q for every wheel number q >= i with i * q >= low
+            // Near u64::MAX the next multiple can overflow; it would be past
+            // `high` anyway, so overflow simply ends the walk
+            let mut q = i.max(low.div_ceil(i));
+            while WHEEL_INDEX[(q % 30) as usize] == NOT_ON_WHEEL {
+                q += 1;
+            }
+            let mut next = i.checked_mul(q);
+            let mut q_wheel = WHEEL_INDEX[(q % 30) as usize] as usize;
+            while let Some(j) = next.filter(|&j| j <= high) {
+                clear(bits, limit, j);
+                next = j.checked_add(i * WHEEL_GAPS[q_wheel]);
+                q_wheel = (q_wheel + 1) % WHEEL.len();
+            }
+        }
+        i += WHEEL_GAPS[i_wheel];
+        i_wheel = (i_wheel + 1) % WHEEL.len();
+    }
+}
+
+/// Count the primes in a sieved bitset covering `0..=limit` by popcount
+/// The last byte is masked against `limit`, so stray bits past it never count
+pub(crate) fn count(bits: &[u8], limit: u64) -> u64 {
+    let small = SMALL_PRIMES.iter().filter(|&&p| p <= limit).count() as u64;
+
+    let Some((&last, full)) = bits.split_last() else {
+        return small;
+    };
+    let ones: u64 = full.iter().map(|b| u64::from(b.count_ones())).sum();
+    small + ones + u64::from((last & last_byte_mask(bits.len(), limit)).count_ones())
+}
+
+/// Bits of the last wheel byte whose numbers are at most `limit`
+fn last_byte_mask(len: usize, limit: u64) -> u8 {
+    let base = (len as u64 - 1) * 30;
+    let mut mask = 0;
+    for (bit, &residue) in WHEEL.iter().enumerate() {
+        if base + residue <= limit {
+            mask |= 1 << bit;
+        }
+    }
+    mask
+}
+
+/// Iterator over the primes of a [`Sieve`](crate::Sieve) or
+/// [`FixedSieve`](crate::FixedSieve), created by their `iter` methods
+pub struct SieveIter<'a> {
+    bits: &'a [u8],
+    limit: u64,
+    /// Next entry of SMALL_PRIMES to yield
+    small: usize,
+    byte_idx: usize,
+    bit_idx: usize,
+}
+
+impl<'a> SieveIter<'a> {
+    pub(crate) fn new(bits: &'a [u8], limit: u64) -> Self {
+        Self {
+            bits,
+            limit,
+            small: 0,
+            byte_idx: 0,
+            bit_idx: 0,
+        }
+    }
+}
+
+impl Iterator for SieveIter<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        while self.small < SMALL_PRIMES.len() {
+            let p = SMALL_PRIMES[self.small];
+            self.small += 1;
+            if p <= self.limit {
+                return Some(p);
+            }
+        }
+
+        while self.byte_idx < self.bits.len() {
+            let byte = self.bits[self.byte_idx];
+            while self.bit_idx < WHEEL.len() {
+                let bit_idx = self.bit_idx;
+                self.bit_idx += 1;
+                if byte & (1 << bit_idx) != 0 {
+                    return Some(self.byte_idx as u64 * 30 + WHEEL[bit_idx]);
+                }
+            }
+            self.byte_idx += 1;
+            self.bit_idx = 0;
+        }
+        None
+    }
+}
+
+/// Find the nth prime (1-indexed) yielded by `iter`
+pub(crate) fn nth(iter: SieveIter<'_>, n: u64) -> Option<u64> {
+    if n == 0 {
+        return None;
+    }
+
+    let mut count = 0;
+    for p in iter {
+        count += 1;
+        if count == n {
+            return Some(p);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_masks_bits_past_limit() {
+        // 90..=119 is the last byte for limit 100; only 91 and 97 are in range
+        let mut bits = [0u8; 4];
+        bits[3] = 0xFF;
+        assert_eq!(count(&bits, 100), 3 + 2);
+        assert_eq!(count(&bits, 119), 3 + 8);
+        assert_eq!(count(&[0xFF], 0), 0);
+    }
+}
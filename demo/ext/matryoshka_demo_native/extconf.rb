@@ -39,7 +39,10 @@ end
 require 'mkmf'
 require 'rb_sys/mkmf'
 
+# rayon is on by default so count_primes_parallel and threads: run in parallel;
+# MATRYOSHKADEMO_NATIVE_FEATURES picks another comma-separated list ('' for none)
 create_rust_makefile('matryoshka_demo_native/matryoshka_demo_native') do |r|
   r.ext_dir = 'ffi'
   r.profile = ENV.fetch('RB_SYS_CARGO_PROFILE', :release).to_sym
+  r.features = ENV.fetch('MATRYOSHKADEMO_NATIVE_FEATURES', 'rayon').split(',')
 end
@@ -0,0 +1,44 @@
+//! Records the toolchain, target and profile for `MatryoshkaDemoNative.build_info`,
+//! and the namespace the extension defines itself under
+
+use std::env;
+use std::process::Command;
+
+fn main() {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_owned())
+        .filter(|version| !version.is_empty())
+        .unwrap_or_else(|| "unknown".to_owned());
+    let target = env::var("TARGET").expect("cargo sets TARGET");
+    let profile = env::var("PROFILE").expect("cargo sets PROFILE");
+    let opt_level = env::var("OPT_LEVEL").expect("cargo sets OPT_LEVEL");
+
+    println!("cargo:rustc-env=MATRYOSHKA_RUSTC_VERSION={version}");
+    println!("cargo:rustc-env=MATRYOSHKA_TARGET={target}");
+    println!("cargo:rustc-env=MATRYOSHKA_PROFILE={profile}");
+    println!("cargo:rustc-env=MATRYOSHKA_OPT_LEVEL={opt_level}");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=RUSTC");
+
+    let namespace =
+        env::var("MATRYOSHKA_NAMESPACE").unwrap_or_else(|_| "MatryoshkaDemoNative".to_owned());
+    assert!(
+        namespace.split("::").all(is_constant_name),
+        "MATRYOSHKA_NAMESPACE must be a constant path such as MyGem::Native, got {namespace:?}"
+    );
+    println!("cargo:rustc-env=MATRYOSHKA_NAMESPACE={namespace}");
+    println!("cargo:rerun-if-env-changed=MATRYOSHKA_NAMESPACE");
+}
+
+/// Whether `name` can name a Ruby constant: an ASCII capital, then letters,
+/// digits and underscores
+fn is_constant_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_uppercase())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
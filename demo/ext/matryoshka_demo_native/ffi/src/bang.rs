@@ -0,0 +1,75 @@
+//! Bang variants: `nth_prime!` and friends, raising where the plain method
+//! answers nil
+//!
+//! The plain methods follow `config.strict` for out-of-domain arguments and
+//! answer nil when no result exists (no prime below 2, an overflowing
+//! power). Each bang variant runs the plain one with strict checks forced on
+//! and turns nil into a `MatryoshkaDemoNative::Error`: InvalidArgument when
+//! the input has no answer, OverflowError when the answer passes u64. They
+//! are all generated by `bang!`, so none can drift from that policy.
+
+use magnus::prelude::*;
+use magnus::{function, Error, Integer, RModule, Ruby, Value};
+
+use crate::intmath::{checked_pow_native, next_prime_native, prev_prime_native};
+use crate::{
+    error, goldbach_pair_native, inv_mod_native, max_prime_gap_native, next_safe_prime_native,
+    nth_prime_after_native, nth_prime_native, Output, CONFIG,
+};
+
+/// Define a bang variant of each listed lenient `_native` function and a
+/// `define` registering them as `<ruby name>!`; a nil answer raises
+/// `$raise(ruby, format!($message))`, whose message may name the arguments
+macro_rules! bang {
+    ($(
+        $ruby_name:literal / $arity:literal => $bang:ident =
+            $lenient:ident($($arg:ident: $ty:ty),*) -> $answer:ty,
+            $raise:path, $message:literal;
+    )*) => {
+        $(
+            #[doc = concat!("`", $ruby_name, "!`: `", $ruby_name, "` raising where it answers nil")]
+            /// Rust FFI wrapper for Ruby
+            fn $bang(ruby: &Ruby, $($arg: $ty),*) -> Result<$answer, Error> {
+                match CONFIG.strictly(|| $lenient(ruby, $($arg),*))? {
+                    Some(answer) => Ok(answer),
+                    None => Err($raise(ruby, format!($message))),
+                }
+            }
+        )*
+
+        /// Define every bang variant under `module`
+        pub(crate) fn define(module: RModule) -> Result<(), Error> {
+            $(
+                module.define_module_function(
+                    concat!($ruby_name, "!"),
+                    function!($bang, $arity),
+                )?;
+            )*
+            Ok(())
+        }
+    };
+}
+
+bang! {
+    "nth_prime" / 1 => nth_prime_bang = nth_prime_native(n: Value) -> u64,
+        error::invalid_argument, "n must be positive, got {n}";
+    "nth_prime_after" / 2 => nth_prime_after_bang =
+        nth_prime_after_native(start: Integer, n: Value) -> u64,
+        error::overflow, "prime {n} after {start} does not fit in 64 bits";
+    "next_prime" / 1 => next_prime_bang = next_prime_native(n: Integer) -> u64,
+        error::overflow, "the prime after {n} does not fit in 64 bits";
+    "next_safe_prime" / 1 => next_safe_prime_bang = next_safe_prime_native(n: Integer) -> u64,
+        error::overflow, "the safe prime after {n} does not fit in 64 bits";
+    "prev_prime" / 1 => prev_prime_bang = prev_prime_native(n: Integer) -> u64,
+        error::invalid_argument, "no prime is less than {n}";
+    "goldbach_pair" / 1 => goldbach_pair_bang =
+        goldbach_pair_native(n: Integer) -> Output<(u64, u64)>,
+        error::invalid_argument, "n must be even and at least 4, got {n}";
+    "max_prime_gap" / 1 => max_prime_gap_bang =
+        max_prime_gap_native(limit: Value) -> Output<(u64, u64)>,
+        error::invalid_argument, "fewer than two primes are at most {limit}";
+    "inv_mod" / 2 => inv_mod_bang = inv_mod_native(a: Integer, m: Integer) -> u64,
+        error::invalid_argument, "{a} has no inverse modulo {m}";
+    "checked_pow" / 2 => checked_pow_bang = checked_pow_native(base: Integer, exp: Integer) -> u64,
+        error::overflow, "{base} ** {exp} does not fit in 64 bits";
+}
@@ -0,0 +1,129 @@
+//! Batch entry points: a whole Array of inputs per native call
+//!
+//! For small inputs the per-call overhead (argument conversion, releasing
+//! and reacquiring the GVL) dwarfs the work itself. These convert the
+//! Array once, answer every element with the GVL released, spread across
+//! the rayon pool with the rayon feature, and convert the results once.
+//! `count_primes_parallel` is `count_primes_many` under the name a caller
+//! looking to parallelize independent counts reaches for; extconf.rb builds
+//! with rayon unless told otherwise, and `parallel?` tells whether it is.
+
+use magnus::prelude::*;
+use magnus::scan_args::{get_kwargs, scan_args};
+use magnus::{function, Error, Integer, RArray, RHash, RModule, Ruby, Value};
+use matryoshka_demo_core::{CoreError, CountOptions};
+
+use crate::cancel::without_gvl_cancellable_opt;
+use crate::threads::Threads;
+use crate::{
+    domain_arg, error, memory, u128_from_integer, without_gvl, Output, CONFIG, MAX_COUNT_LIMIT,
+};
+
+/// Count the primes up to each limit in `limits`, in input order; also
+/// defined as `count_primes_parallel`
+/// Keywords: `threads:` for the worker pool, defaulting to `config.threads`,
+/// `cancel:` a CancelToken and `timeout:` seconds for the whole batch
+/// Limits within `config.cache_limit` share the process-wide sieve, grown
+/// once to the largest of them; the rest are counted independently
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits,
+/// ResourceLimit above MAX_COUNT_LIMIT and Timeout past `timeout:`
+fn count_primes_many_native(ruby: &Ruby, args: &[Value]) -> Result<Output<RArray>, Error> {
+    let args = scan_args::<(RArray,), (), (), (), RHash, ()>(args)?;
+    let (limits,) = args.required;
+    let kwargs = get_kwargs::<_, (), (Option<Value>, Option<Value>, Option<Value>), ()>(
+        args.keywords,
+        &[],
+        &["threads", "cancel", "timeout"],
+    )?;
+    let (threads, cancel, timeout) = kwargs.optional;
+    let threads = Threads::from_option(ruby, threads)?;
+
+    let mut checked = Vec::with_capacity(limits.len());
+    for limit in limits.to_vec::<Value>()? {
+        let limit = domain_arg(ruby, limit, "limit", 0)?;
+        if let Some(limit) = limit.filter(|&limit| limit > MAX_COUNT_LIMIT) {
+            return Err(error::resource_limit(
+                ruby,
+                format!("limit must be at most {MAX_COUNT_LIMIT}, got {limit}"),
+            ));
+        }
+        checked.push(limit);
+    }
+
+    let cache_limit = CONFIG.cache_limit();
+    let options = CountOptions {
+        segment_bytes: CONFIG.segment_bytes(),
+        ..CountOptions::default()
+    };
+    let largest_cached = checked
+        .iter()
+        .flatten()
+        .copied()
+        .filter(|&limit| limit <= cache_limit)
+        .max();
+    let counts = without_gvl_cancellable_opt(ruby, cancel, timeout, |cancel| {
+        if let Some(limit) = largest_cached {
+            matryoshka_demo_core::cached_count_primes(limit)?;
+        }
+        let counts = threads.map(&checked, |&limit| match limit {
+            None => Ok(0),
+            Some(limit) if limit <= cache_limit => matryoshka_demo_core::cached_count_primes(limit),
+            Some(limit) => {
+                matryoshka_demo_core::try_count_primes_with_options(..=limit, options, |_, _| {
+                    cancel.observe()
+                })
+            }
+        });
+        counts.into_iter().collect::<Result<Vec<u64>, CoreError>>()
+    });
+    memory::sync_cache();
+    Ok(Output(ruby.ary_from_vec(counts?)))
+}
+
+/// Check every Integer in `numbers` for primality, as `prime?` does
+/// Returns a Hash of `{n => true or false}` in the order of `numbers`
+/// Keyword: `threads:` for the worker pool, defaulting to `config.threads`
+/// Rust FFI wrapper for Ruby; raises RangeError past 128 bits
+fn prime_map_native(ruby: &Ruby, args: &[Value]) -> Result<Output<RHash>, Error> {
+    let args = scan_args::<(RArray,), (), (), (), RHash, ()>(args)?;
+    let (numbers,) = args.required;
+    let kwargs = get_kwargs::<_, (), (Option<Value>,), ()>(args.keywords, &[], &["threads"])?;
+    let (threads,) = kwargs.optional;
+    let threads = Threads::from_option(ruby, threads)?;
+
+    // The converted Integers live in a Ruby Array so the GC sees them
+    let keys = ruby.ary_new_capa(numbers.len());
+    let mut values = Vec::with_capacity(numbers.len());
+    for i in 0..numbers.len() {
+        let n = numbers.entry::<Integer>(i as isize)?;
+        keys.push(n)?;
+        values.push(if n < ruby.integer_from_u64(2) {
+            0
+        } else {
+            u128_from_integer(ruby, n)?
+        });
+    }
+
+    let primes = without_gvl(|| {
+        threads.map(&values, |&n| match u64::try_from(n) {
+            Ok(n) => matryoshka_demo_core::is_prime_hybrid(n),
+            Err(_) => matryoshka_demo_core::is_prime_u128(n),
+        })
+    });
+    let map = ruby.hash_new_capa(primes.len());
+    for (i, prime) in primes.into_iter().enumerate() {
+        map.aset(keys.entry::<Integer>(i as isize)?, prime)?;
+    }
+    Ok(Output(map))
+}
+
+/// Define the batch functions under `module`
+pub(crate) fn define(module: RModule) -> Result<(), Error> {
+    module.define_module_function("count_primes_many", function!(count_primes_many_native, -1))?;
+    module.define_module_function(
+        "count_primes_parallel",
+        function!(count_primes_many_native, -1),
+    )?;
+    module.define_module_function("prime_map", function!(prime_map_native, -1))?;
+    Ok(())
+}
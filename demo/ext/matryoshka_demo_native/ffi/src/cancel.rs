@@ -0,0 +1,120 @@
+//! `MatryoshkaDemoNative::CancelToken` (also `Cancel`): stop a running
+//! computation from Ruby
+//!
+//! Passed as `cancel:`, the token is followed by the native call while the
+//! GVL is released; another Ruby thread calling `cancel!` makes the call
+//! stop at its next segment and raise MatryoshkaDemoNative::Cancelled.
+//! Each call stops on a token of its own, a child of the `cancel:` one, so
+//! an interrupt stopping one call (Thread#raise, a signal) never cancels
+//! the caller's token or the other calls sharing it.
+//!
+//! The same calls take `timeout:` in seconds: the call's token gets that
+//! deadline and raises MatryoshkaDemoNative::Timeout once a segment ends
+//! past it.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use magnus::prelude::*;
+use magnus::{function, method, DataTypeFunctions, Error, RModule, Ruby, Value};
+use matryoshka_demo_core::{CancelToken, CoreError};
+
+use crate::gvl::without_gvl_cancellable_by;
+use crate::namespace::wrap;
+
+/// A core CancelToken wrapped as Ruby TypedData
+#[derive(DataTypeFunctions)]
+pub(crate) struct RbCancelToken(Arc<CancelToken>);
+
+wrap!(RbCancelToken, "CancelToken");
+
+impl RbCancelToken {
+    /// A token that has not been cancelled
+    fn new() -> Self {
+        Self(Arc::new(CancelToken::new()))
+    }
+
+    /// Ask every call using this token to stop
+    fn cancel(&self) {
+        self.0.cancel();
+    }
+
+    /// Whether `cancel` has been called
+    fn is_cancelled(&self) -> bool {
+        self.0.is_cancelled()
+    }
+}
+
+/// The token a call stops on, from its `cancel:` and `timeout:` keywords:
+/// a token of the call's own, following the caller's token if there is one
+/// and with the deadline if there is a timeout
+/// Raises TypeError unless `cancel` is a CancelToken, ArgumentError unless
+/// `timeout` is a positive number of seconds
+pub(crate) fn call_token(
+    ruby: &Ruby,
+    cancel: Option<Value>,
+    timeout: Option<Value>,
+) -> Result<Arc<CancelToken>, Error> {
+    let cancel = cancel
+        .map(|cancel| <&RbCancelToken>::try_convert(cancel).map(|cancel| Arc::clone(&cancel.0)))
+        .transpose()?;
+    let token = match cancel {
+        Some(cancel) => CancelToken::child(cancel),
+        None => CancelToken::new(),
+    };
+    Ok(Arc::new(match deadline(ruby, timeout)? {
+        Some(deadline) => token.with_deadline(deadline),
+        None => token,
+    }))
+}
+
+/// When a call given `timeout:` seconds must stop; None without one (or
+/// with nil), or for a timeout too long to represent
+fn deadline(ruby: &Ruby, timeout: Option<Value>) -> Result<Option<Instant>, Error> {
+    let Some(timeout) = timeout.filter(|timeout| !timeout.is_nil()) else {
+        return Ok(None);
+    };
+
+    let seconds = f64::try_convert(timeout)?;
+    if seconds.is_nan() || seconds <= 0.0 {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            format!(
+                "timeout must be a positive number of seconds, got {}",
+                timeout.inspect()
+            ),
+        ));
+    }
+    Ok(Duration::try_from_secs_f64(seconds)
+        .ok()
+        .and_then(|timeout| Instant::now().checked_add(timeout)))
+}
+
+/// Run `f` with the GVL released, stopping it at a Ruby interrupt, once
+/// `cancel` (the caller's `cancel:` keyword) is set or once `timeout:`
+/// seconds have passed
+/// Raises as `call_token` does for bad keywords
+pub(crate) fn without_gvl_cancellable_opt<F, R>(
+    ruby: &Ruby,
+    cancel: Option<Value>,
+    timeout: Option<Value>,
+    f: F,
+) -> Result<R, Error>
+where
+    F: FnOnce(&CancelToken) -> Result<R, CoreError>,
+{
+    let token = call_token(ruby, cancel, timeout)?;
+    without_gvl_cancellable_by(ruby, &token, f)
+}
+
+/// Define `MatryoshkaDemoNative::CancelToken` and its `Cancel` alias under
+/// `module`
+pub(crate) fn define(ruby: &Ruby, module: RModule) -> Result<(), Error> {
+    let class = module.define_class("CancelToken", ruby.class_object())?;
+    class.define_singleton_method("new", function!(RbCancelToken::new, 0))?;
+    class.define_method("cancel", method!(RbCancelToken::cancel, 0))?;
+    class.define_method("cancel!", method!(RbCancelToken::cancel, 0))?;
+    class.define_method("cancelled?", method!(RbCancelToken::is_cancelled, 0))?;
+    module.const_set("Cancel", class)?;
+    Ok(())
+}
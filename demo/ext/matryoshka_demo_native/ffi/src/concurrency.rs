@@ -0,0 +1,153 @@
+//! `MatryoshkaDemoNative._concurrency_check`: the shared state under load
+//!
+//! Native calls run on any Ruby thread, in any Ractor and, with the GVL
+//! released, truly in parallel. What they share, and when one call sees
+//! what another changed:
+//!
+//! - `config` settings are relaxed atomics, each read once per call. A
+//!   call never sees a torn value, and one starting after `config.x =`
+//!   returned on its own thread, or on a thread ordered after it (by
+//!   Thread#join, a Queue, a Ractor message), sees the new value.
+//! - The process-wide sieve sits behind the core's RwLock, so a call sees
+//!   it either before or after another call grew or cleared it, never
+//!   midway.
+//! - The `threads:` pool, the log queue and the objects a Sieve retains
+//!   sit behind Mutexes held only to read or swap them, never across a
+//!   computation or a call into Ruby.
+//! - Sieve and EratosthenesGenerator objects are not shared between
+//!   Ractors and rely on the GVL between threads; a CancelToken's flag is
+//!   an atomic any thread may set.
+//!
+//! The locks are parking_lot's, which do not poison, so a call that panics
+//! never wedges the calls after it. `_concurrency_check` races the shared
+//! paths against each other from Rust threads and checks every answer; it
+//! exists for the gem's tests.
+
+use std::panic;
+use std::thread;
+
+use magnus::scan_args::scan_args;
+use magnus::{function, Error, RModule, Ruby, Value};
+use matryoshka_demo_core::Sieve;
+
+use crate::threads::MAX_THREADS;
+use crate::{memory, without_gvl};
+
+/// Largest number the check asks about, so its reference sieve stays small
+const CHECK_LIMIT: u64 = 2_000_000;
+
+/// Threads the check starts unless asked for another number
+const DEFAULT_THREADS: u64 = 8;
+
+/// Rounds each thread runs unless asked for another number
+const DEFAULT_ITERATIONS: u64 = 1000;
+
+/// Most rounds each thread may run
+const MAX_ITERATIONS: u64 = 1_000_000;
+
+/// Run `threads` Rust threads (DEFAULT_THREADS unless given) for
+/// `iterations` rounds each (DEFAULT_ITERATIONS unless given), racing
+/// cached counts, primality checks and cache clears against each other,
+/// and return how many answers were checked
+/// Clears the process-wide sieve along the way
+/// Rust FFI wrapper for Ruby; raises ArgumentError for threads outside
+/// 1..=MAX_THREADS or iterations outside 1..=MAX_ITERATIONS, RuntimeError
+/// naming the first wrong answer or when no thread can be started
+fn concurrency_check_native(ruby: &Ruby, args: &[Value]) -> Result<u64, Error> {
+    let args = scan_args::<(), (Option<u64>, Option<u64>), (), (), (), ()>(args)?;
+    let (threads, iterations) = args.optional;
+    let threads = threads.unwrap_or(DEFAULT_THREADS);
+    let iterations = iterations.unwrap_or(DEFAULT_ITERATIONS);
+    check_bound(ruby, threads, "threads", MAX_THREADS)?;
+    check_bound(ruby, iterations, "iterations", MAX_ITERATIONS)?;
+
+    let checked = without_gvl(|| check(threads, iterations));
+    memory::sync_cache();
+    checked.map_err(|message| Error::new(ruby.exception_runtime_error(), message))
+}
+
+/// Raise ArgumentError naming `name` unless `value` lies in 1..=`max`
+fn check_bound(ruby: &Ruby, value: u64, name: &str, max: u64) -> Result<(), Error> {
+    if !(1..=max).contains(&value) {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            format!("{name} must be in 1..{max}, got {value}"),
+        ));
+    }
+    Ok(())
+}
+
+/// Race `threads` workers for `iterations` rounds each against a reference
+/// sieve no other thread touches
+fn check(threads: u64, iterations: u64) -> Result<u64, String> {
+    let reference = Sieve::new(CHECK_LIMIT);
+    thread::scope(|scope| {
+        let workers = (0..threads)
+            .map(|seed| {
+                let reference = &reference;
+                thread::Builder::new()
+                    .name("matryoshka-check".to_owned())
+                    .spawn_scoped(scope, move || hammer(reference, seed, iterations))
+                    .map_err(|e| format!("cannot start a thread: {e}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        workers
+            .into_iter()
+            .map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|payload| panic::resume_unwind(payload))
+            })
+            .sum()
+    })
+}
+
+/// One worker's rounds, over numbers drawn from a sequence seeded by
+/// `seed`; returns the answers checked, or the first wrong one
+fn hammer(reference: &Sieve, seed: u64, iterations: u64) -> Result<u64, String> {
+    let mut state = (seed + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let mut checked = 0;
+    for round in 0..iterations {
+        // xorshift64, so every worker asks about different numbers
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let n = state % (CHECK_LIMIT + 1);
+
+        let (name, answer, expected) = match round % 16 {
+            15 => {
+                matryoshka_demo_core::clear_cache();
+                continue;
+            }
+            r if r % 3 == 0 => (
+                "cached_count_primes",
+                matryoshka_demo_core::cached_count_primes(n).map_err(|e| e.to_string())?,
+                reference.count_up_to(n),
+            ),
+            r if r % 3 == 1 => (
+                "cached_is_prime",
+                matryoshka_demo_core::cached_is_prime(n).map_err(|e| e.to_string())? as u64,
+                reference.is_prime(n) as u64,
+            ),
+            _ => (
+                "is_prime_hybrid",
+                matryoshka_demo_core::is_prime_hybrid(n) as u64,
+                reference.is_prime(n) as u64,
+            ),
+        };
+        if answer != expected {
+            return Err(format!("{name}({n}) gave {answer}, expected {expected}"));
+        }
+        checked += 1;
+    }
+    Ok(checked)
+}
+
+/// Define `MatryoshkaDemoNative._concurrency_check` under `module`
+pub(crate) fn define(module: RModule) -> Result<(), Error> {
+    module.define_module_function(
+        "_concurrency_check",
+        function!(concurrency_check_native, -1),
+    )?;
+    Ok(())
+}
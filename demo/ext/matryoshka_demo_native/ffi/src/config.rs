@@ -0,0 +1,303 @@
+//! `MatryoshkaDemoNative.config`: process-wide defaults for the native calls
+//!
+//! The settings live in one static of atomics, read by every call that
+//! takes the matching option and does not pass it; a `Config` object is a
+//! handle onto that static, so any number of them (from any Ractor) see
+//! and change the same values.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use magnus::prelude::*;
+#[cfg(feature = "log")]
+use magnus::Symbol;
+use magnus::{
+    function, method, DataTypeFunctions, Error, Integer, IntoValue, RHash, RModule, Ruby, Value,
+};
+use matryoshka_demo_core::CountOptions;
+
+#[cfg(feature = "log")]
+use crate::logger;
+use crate::namespace::wrap;
+use crate::threads::{self, MAX_THREADS};
+use crate::{u64_from_integer, MAX_BITMAP_LIMIT};
+
+/// Default largest limit `count_primes` answers from the process-wide sieve
+/// The cached bitset costs limit / 30 bytes (about 3.3 MB here); larger
+/// limits use the sublinear counter, which needs no cache to be fast
+const DEFAULT_CACHE_LIMIT: u64 = 100_000_000;
+
+/// Default number of progress calls a count makes, at most
+const DEFAULT_PROGRESS_STEPS: u64 = 1000;
+
+/// Segment sizes `segment_bytes=` accepts: from 1 KiB to 64 MiB
+const SEGMENT_BYTES: (u64, u64) = (1 << 10, 1 << 26);
+
+/// Most progress calls `progress_steps=` accepts
+const MAX_PROGRESS_STEPS: u64 = 1_000_000;
+
+/// The process-wide settings; relaxed ordering is enough as no other data
+/// is published through them
+pub(crate) struct Config {
+    threads: AtomicU64,
+    segment_bytes: AtomicUsize,
+    cache_limit: AtomicU64,
+    strict: AtomicBool,
+    progress_steps: AtomicU64,
+    freeze_results: AtomicBool,
+}
+
+thread_local! {
+    /// Set while a bang variant runs, so its checks are strict whatever
+    /// `strict` is set to
+    static FORCE_STRICT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The settings every native call reads
+pub(crate) static CONFIG: Config = Config {
+    threads: AtomicU64::new(0),
+    segment_bytes: AtomicUsize::new(0),
+    cache_limit: AtomicU64::new(DEFAULT_CACHE_LIMIT),
+    strict: AtomicBool::new(true),
+    progress_steps: AtomicU64::new(DEFAULT_PROGRESS_STEPS),
+    freeze_results: AtomicBool::new(false),
+};
+
+impl Config {
+    /// Workers for calls without `threads:`; None for rayon's global pool
+    pub(crate) fn threads(&self) -> Option<u64> {
+        Some(self.threads.load(Ordering::Relaxed)).filter(|&threads| threads > 0)
+    }
+
+    /// Bytes of bitset per segment when a count sieves
+    pub(crate) fn segment_bytes(&self) -> usize {
+        match self.segment_bytes.load(Ordering::Relaxed) {
+            0 => CountOptions::default().segment_bytes,
+            bytes => bytes,
+        }
+    }
+
+    /// Largest limit `count_primes` answers from the process-wide sieve
+    pub(crate) fn cache_limit(&self) -> u64 {
+        self.cache_limit.load(Ordering::Relaxed)
+    }
+
+    /// Whether out-of-domain arguments raise ArgumentError
+    pub(crate) fn strict(&self) -> bool {
+        self.strict.load(Ordering::Relaxed) || FORCE_STRICT.get()
+    }
+
+    /// Run `f` with strict argument handling on the calling thread only
+    pub(crate) fn strictly<R>(&self, f: impl FnOnce() -> R) -> R {
+        /// Restores the previous state, even if `f` panics
+        struct Restore(bool);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                FORCE_STRICT.set(self.0);
+            }
+        }
+
+        let _restore = Restore(FORCE_STRICT.replace(true));
+        f()
+    }
+
+    /// Switch strict argument handling on or off
+    pub(crate) fn set_strict(&self, strict: bool) {
+        self.strict.store(strict, Ordering::Relaxed);
+    }
+
+    /// Most progress calls one count makes
+    pub(crate) fn progress_steps(&self) -> u64 {
+        self.progress_steps.load(Ordering::Relaxed)
+    }
+
+    /// Whether results are frozen and interned before they are returned
+    pub(crate) fn freeze_results(&self) -> bool {
+        self.freeze_results.load(Ordering::Relaxed)
+    }
+}
+
+/// Read `value` as an Integer from `min` to `max`
+/// Raises ArgumentError outside that range, TypeError for non-Integers
+fn bounded(ruby: &Ruby, value: Integer, name: &str, (min, max): (u64, u64)) -> Result<u64, Error> {
+    let n = u64_from_integer(ruby, value, name)
+        .ok()
+        .filter(|n| (min..=max).contains(n));
+    n.ok_or_else(|| {
+        Error::new(
+            ruby.exception_arg_error(),
+            format!("{name} must be between {min} and {max}, got {value}"),
+        )
+    })
+}
+
+/// A handle onto [`CONFIG`] for Ruby
+#[derive(DataTypeFunctions)]
+struct RbConfig;
+
+wrap!(RbConfig, "Config");
+
+impl RbConfig {
+    /// Workers for calls without `threads:`, or nil for the default pool
+    fn threads(&self) -> Option<u64> {
+        CONFIG.threads()
+    }
+
+    /// Set the default worker count; nil restores rayon's global pool
+    /// Takes effect on the next call, which starts a pool of that size
+    /// Raises ArgumentError outside 1..=MAX_THREADS and for more than one
+    /// thread without the rayon feature
+    fn set_threads(ruby: &Ruby, _rb_self: &Self, threads: Option<Integer>) -> Result<(), Error> {
+        let threads = match threads {
+            Some(threads) => bounded(ruby, threads, "threads", (1, MAX_THREADS))?,
+            None => 0,
+        };
+        threads::check_supported(ruby, threads)?;
+        CONFIG.threads.store(threads, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Bytes of bitset per segment when a count sieves
+    fn segment_bytes(&self) -> usize {
+        CONFIG.segment_bytes()
+    }
+
+    /// Set the segment size; nil restores the 32 KiB default
+    /// Raises ArgumentError outside 1 KiB to 64 MiB
+    fn set_segment_bytes(
+        ruby: &Ruby,
+        _rb_self: &Self,
+        bytes: Option<Integer>,
+    ) -> Result<(), Error> {
+        let bytes = match bytes {
+            Some(bytes) => bounded(ruby, bytes, "segment_bytes", SEGMENT_BYTES)?,
+            None => 0,
+        };
+        CONFIG
+            .segment_bytes
+            .store(bytes as usize, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Largest limit `count_primes` answers from the process-wide sieve
+    fn cache_limit(&self) -> u64 {
+        CONFIG.cache_limit()
+    }
+
+    /// Set the largest cached limit; 0 turns the cache off for new counts
+    /// Raises ArgumentError above MAX_BITMAP_LIMIT
+    fn set_cache_limit(ruby: &Ruby, _rb_self: &Self, limit: Integer) -> Result<(), Error> {
+        let limit = bounded(ruby, limit, "cache_limit", (0, MAX_BITMAP_LIMIT))?;
+        CONFIG.cache_limit.store(limit, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Whether out-of-domain arguments raise ArgumentError
+    fn is_strict(&self) -> bool {
+        CONFIG.strict()
+    }
+
+    /// Switch strict argument handling on or off
+    fn set_strict(&self, strict: bool) {
+        CONFIG.set_strict(strict);
+    }
+
+    /// Most progress calls one count makes
+    fn progress_steps(&self) -> u64 {
+        CONFIG.progress_steps()
+    }
+
+    /// Set how many progress calls a count makes, at most
+    /// Raises ArgumentError outside 1..=MAX_PROGRESS_STEPS
+    fn set_progress_steps(ruby: &Ruby, _rb_self: &Self, steps: Integer) -> Result<(), Error> {
+        let steps = bounded(ruby, steps, "progress_steps", (1, MAX_PROGRESS_STEPS))?;
+        CONFIG.progress_steps.store(steps, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Most verbose level of Rust log records kept, as a Symbol
+    #[cfg(feature = "log")]
+    fn log_level(ruby: &Ruby, _rb_self: &Self) -> Symbol {
+        logger::level(ruby)
+    }
+
+    /// Set the most verbose level kept: :off, :error, :warn (the default),
+    /// :info, :debug or :trace
+    /// Raises ArgumentError for other Symbols
+    #[cfg(feature = "log")]
+    fn set_log_level(ruby: &Ruby, _rb_self: &Self, level: Symbol) -> Result<(), Error> {
+        logger::set_level(ruby, level)
+    }
+
+    /// Whether returned Arrays, Hashes and Strings are frozen, and Strings
+    /// interned
+    fn is_freeze_results(&self) -> bool {
+        CONFIG.freeze_results()
+    }
+
+    /// Switch freezing results on or off
+    fn set_freeze_results(&self, freeze: bool) {
+        CONFIG.freeze_results.store(freeze, Ordering::Relaxed);
+    }
+
+    /// Every setting as a Hash keyed by Symbol
+    fn to_h(ruby: &Ruby, rb_self: &Self) -> Result<RHash, Error> {
+        let hash = ruby.hash_new();
+        let settings: [(&str, Value); 6] = [
+            ("threads", rb_self.threads().into_value_with(ruby)),
+            (
+                "segment_bytes",
+                rb_self.segment_bytes().into_value_with(ruby),
+            ),
+            ("cache_limit", rb_self.cache_limit().into_value_with(ruby)),
+            ("strict", rb_self.is_strict().into_value_with(ruby)),
+            (
+                "progress_steps",
+                rb_self.progress_steps().into_value_with(ruby),
+            ),
+            (
+                "freeze_results",
+                rb_self.is_freeze_results().into_value_with(ruby),
+            ),
+        ];
+        for (key, value) in settings {
+            hash.aset(ruby.to_symbol(key), value)?;
+        }
+        #[cfg(feature = "log")]
+        hash.aset(ruby.to_symbol("log_level"), logger::level(ruby))?;
+        Ok(hash)
+    }
+}
+
+/// A handle onto the process-wide settings
+/// Rust FFI wrapper for Ruby
+fn config_native() -> RbConfig {
+    RbConfig
+}
+
+/// Define `MatryoshkaDemoNative::Config` and `MatryoshkaDemoNative.config`
+/// under `module`
+pub(crate) fn define(ruby: &Ruby, module: RModule) -> Result<(), Error> {
+    let class = module.define_class("Config", ruby.class_object())?;
+    class.undef_default_alloc_func();
+    class.define_method("threads", method!(RbConfig::threads, 0))?;
+    class.define_method("threads=", method!(RbConfig::set_threads, 1))?;
+    class.define_method("segment_bytes", method!(RbConfig::segment_bytes, 0))?;
+    class.define_method("segment_bytes=", method!(RbConfig::set_segment_bytes, 1))?;
+    class.define_method("cache_limit", method!(RbConfig::cache_limit, 0))?;
+    class.define_method("cache_limit=", method!(RbConfig::set_cache_limit, 1))?;
+    class.define_method("strict?", method!(RbConfig::is_strict, 0))?;
+    class.define_method("strict=", method!(RbConfig::set_strict, 1))?;
+    class.define_method("progress_steps", method!(RbConfig::progress_steps, 0))?;
+    class.define_method("progress_steps=", method!(RbConfig::set_progress_steps, 1))?;
+    class.define_method("freeze_results?", method!(RbConfig::is_freeze_results, 0))?;
+    class.define_method("freeze_results=", method!(RbConfig::set_freeze_results, 1))?;
+    #[cfg(feature = "log")]
+    class.define_method("log_level", method!(RbConfig::log_level, 0))?;
+    #[cfg(feature = "log")]
+    class.define_method("log_level=", method!(RbConfig::set_log_level, 1))?;
+    class.define_method("to_h", method!(RbConfig::to_h, 0))?;
+    module.define_module_function("config", function!(config_native, 0))?;
+    Ok(())
+}
@@ -0,0 +1,227 @@
+//! `MatryoshkaDemoNative::Error` and the failures raised as its subclasses
+//!
+//! Argument checks keep Ruby's own ArgumentError, TypeError and RangeError;
+//! failures of the computation itself raise one of these instead, so
+//! callers can `rescue MatryoshkaDemoNative::Error` or a single kind:
+//!
+//! - `InvalidArgument`: the core rejected an argument combination
+//! - `OverflowError`: the answer does not fit in 64 bits
+//! - `Cancelled`: a CancelToken stopped the computation
+//! - `Timeout`: a computation ran past its deadline
+//! - `ResourceLimit`: the input is above a limit this extension enforces,
+//!   or the memory it needs cannot be allocated
+//!
+//! Each core error variant gets a class of its own, defined at init from
+//! `CoreError::VARIANTS` and named after the variant: `LimitTooLarge`
+//! under ResourceLimit, `EstimateExceeded` under OverflowError, any other
+//! directly under Error, while `InvalidArgument` and `Cancelled` are the
+//! classes above. A variant added to the core is raised as its own class
+//! without a change here.
+//!
+//! Each also answers `#code`, a Symbol, and `#details`, a frozen Hash, so
+//! callers can branch without parsing messages. Core errors give the
+//! variant's own code (`:limit_too_large` for an unaddressable sieve, say)
+//! and its fields as details; the rest give their class's code in
+//! snake_case and no details. These codes are stable across releases.
+
+use magnus::prelude::*;
+use magnus::value::Lazy;
+use magnus::{
+    method, Error, Exception, ExceptionClass, RArray, RHash, RObject, Ruby, Symbol, Value,
+};
+use matryoshka_demo_core::{CoreError, ErrorField};
+
+use crate::namespace;
+
+/// Define `MatryoshkaDemoNative::<name>` under `superclass`
+fn define_error(ruby: &Ruby, name: &str, superclass: ExceptionClass) -> ExceptionClass {
+    namespace::module(ruby)
+        .and_then(|module| module.define_error(name, superclass))
+        .expect("MatryoshkaDemoNative error classes can be defined")
+}
+
+/// Base class of every error raised by the computations themselves
+static ERROR: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "Error", ruby.exception_standard_error()));
+
+static INVALID_ARGUMENT: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "InvalidArgument", ruby.get_inner(&ERROR)));
+
+static OVERFLOW_ERROR: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "OverflowError", ruby.get_inner(&ERROR)));
+
+static CANCELLED: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "Cancelled", ruby.get_inner(&ERROR)));
+
+static TIMEOUT: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "Timeout", ruby.get_inner(&ERROR)));
+
+static RESOURCE_LIMIT: Lazy<ExceptionClass> =
+    Lazy::new(|ruby| define_error(ruby, "ResourceLimit", ruby.get_inner(&ERROR)));
+
+/// Each error class with the code its own raises answer, subclasses before
+/// `Error`, so the first class an error is a kind of gives its default code
+static CODES: [(&Lazy<ExceptionClass>, &str); 6] = [
+    (&INVALID_ARGUMENT, "invalid_argument"),
+    (&OVERFLOW_ERROR, "overflow"),
+    (&CANCELLED, "cancelled"),
+    (&TIMEOUT, "timeout"),
+    (&RESOURCE_LIMIT, "resource_limit"),
+    (&ERROR, "error"),
+];
+
+/// The class of each core error variant, in `CoreError::VARIANTS` order
+static VARIANT_CLASSES: Lazy<RArray> = Lazy::new(|ruby| {
+    let classes = ruby.ary_from_iter(CoreError::VARIANTS.iter().map(|&(name, code)| {
+        let parent = match code {
+            "limit_too_large" => &RESOURCE_LIMIT,
+            "estimate_exceeded" => &OVERFLOW_ERROR,
+            _ => &ERROR,
+        };
+        // Reopens InvalidArgument and Cancelled, which already extend Error
+        define_error(ruby, name, ruby.get_inner(parent))
+    }));
+    classes.freeze();
+    classes
+});
+
+/// The class a core error is raised as
+fn variant_class(ruby: &Ruby, error: &CoreError) -> ExceptionClass {
+    let index = CoreError::VARIANTS
+        .iter()
+        .position(|&(_, code)| code == error.code())
+        .expect("every variant is listed");
+    ruby.get_inner(&VARIANT_CLASSES)
+        .entry(index as isize)
+        .expect("a class is defined for every variant")
+}
+
+/// Raise `class` with `message`, answering `code` and `details` from
+/// `#code` and `#details`
+fn raise(
+    ruby: &Ruby,
+    class: ExceptionClass,
+    message: String,
+    code: &str,
+    details: &[(&str, Value)],
+) -> Error {
+    match tagged(ruby, class, message, code, details) {
+        Ok(exception) => exception.into(),
+        Err(error) => error,
+    }
+}
+
+/// The exception `raise` raises
+fn tagged(
+    ruby: &Ruby,
+    class: ExceptionClass,
+    message: String,
+    code: &str,
+    details: &[(&str, Value)],
+) -> Result<Exception, Error> {
+    let hash = ruby.hash_new();
+    for &(key, value) in details {
+        hash.aset(ruby.to_symbol(key), value)?;
+    }
+    hash.freeze();
+
+    let exception = class.new_instance((message,))?;
+    let object = RObject::from_value(exception.as_value()).expect("exceptions are plain objects");
+    object.ivar_set("@code", ruby.to_symbol(code))?;
+    object.ivar_set("@details", hash)?;
+    Ok(exception)
+}
+
+/// Raise the class of a core error's variant, with the variant's code and
+/// its fields as details
+pub(crate) fn core(ruby: &Ruby, error: CoreError) -> Error {
+    let mut details = Vec::new();
+    error.for_each_field(|name, field| {
+        let value = match field {
+            ErrorField::Text(text) => ruby.str_new(text).as_value(),
+            ErrorField::Number(n) => ruby.integer_from_u64(n).as_value(),
+        };
+        details.push((name, value));
+    });
+    let class = variant_class(ruby, &error);
+    raise(ruby, class, error.to_string(), error.code(), &details)
+}
+
+/// Raise `MatryoshkaDemoNative::InvalidArgument`
+pub(crate) fn invalid_argument(ruby: &Ruby, message: String) -> Error {
+    let class = ruby.get_inner(&INVALID_ARGUMENT);
+    raise(ruby, class, message, "invalid_argument", &[])
+}
+
+/// Raise `MatryoshkaDemoNative::OverflowError`
+pub(crate) fn overflow(ruby: &Ruby, message: String) -> Error {
+    let class = ruby.get_inner(&OVERFLOW_ERROR);
+    raise(ruby, class, message, "overflow", &[])
+}
+
+/// Raise `MatryoshkaDemoNative::Cancelled`
+pub(crate) fn cancelled(ruby: &Ruby, message: String) -> Error {
+    let class = ruby.get_inner(&CANCELLED);
+    raise(ruby, class, message, "cancelled", &[])
+}
+
+/// Raise `MatryoshkaDemoNative::Timeout`
+pub(crate) fn timeout(ruby: &Ruby, message: String) -> Error {
+    let class = ruby.get_inner(&TIMEOUT);
+    raise(ruby, class, message, "timeout", &[])
+}
+
+/// Raise `MatryoshkaDemoNative::ResourceLimit`
+pub(crate) fn resource_limit(ruby: &Ruby, message: String) -> Error {
+    let class = ruby.get_inner(&RESOURCE_LIMIT);
+    raise(ruby, class, message, "resource_limit", &[])
+}
+
+/// `#code`: the Symbol naming the failure; errors raised from Ruby give
+/// their class's code, a variant's before its category's
+fn code(ruby: &Ruby, rb_self: RObject) -> Result<Symbol, Error> {
+    if let Some(code) = rb_self.ivar_get::<_, Option<Symbol>>("@code")? {
+        return Ok(code);
+    }
+    let classes = ruby.get_inner(&VARIANT_CLASSES);
+    let variant = CoreError::VARIANTS
+        .iter()
+        .enumerate()
+        .find(|&(i, _)| {
+            classes
+                .entry::<ExceptionClass>(i as isize)
+                .is_ok_and(|class| rb_self.is_kind_of(class))
+        })
+        .map(|(_, &(_, code))| code);
+    let code = variant.unwrap_or_else(|| {
+        CODES
+            .iter()
+            .find(|(class, _)| rb_self.is_kind_of(ruby.get_inner(*class)))
+            .map_or("error", |&(_, code)| code)
+    });
+    Ok(ruby.to_symbol(code))
+}
+
+/// `#details`: a frozen Hash of what the failure concerned, empty when
+/// nothing was recorded
+fn details(ruby: &Ruby, rb_self: RObject) -> Result<RHash, Error> {
+    if let Some(details) = rb_self.ivar_get::<_, Option<RHash>>("@details")? {
+        return Ok(details);
+    }
+    let details = ruby.hash_new();
+    details.freeze();
+    Ok(details)
+}
+
+/// Define every error class up front, so they exist before the first
+/// raise, and the `#code` and `#details` readers they share
+pub(crate) fn define(ruby: &Ruby) -> Result<(), Error> {
+    for (class, _) in &CODES {
+        Lazy::force(class, ruby);
+    }
+    Lazy::force(&VARIANT_CLASSES, ruby);
+    let error = ruby.get_inner(&ERROR);
+    error.define_method("code", method!(code, 0))?;
+    error.define_method("details", method!(details, 0))?;
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! `MatryoshkaDemoNative::EratosthenesGenerator`: Prime's generator, natively
+//!
+//! `Prime.each`, `Prime.prime?`, `Prime.prime_division` and friends take
+//! the generator to draw primes from as an argument, and only call `succ`,
+//! `rewind`, `upper_bound=` and `each` on it. This class answers those as
+//! `Prime::EratosthenesGenerator` does, from the core's unbounded segmented
+//! iterator, so `Prime.each(10**7, MatryoshkaDemoNative::EratosthenesGenerator.new)`
+//! sieves in Rust and Ruby only sees each prime. It does not require the
+//! `prime` library itself.
+
+use std::cell::{Cell, RefCell};
+
+use magnus::prelude::*;
+use magnus::scan_args::scan_args;
+use magnus::typed_data::Obj;
+use magnus::{method, DataTypeFunctions, Error, Integer, RModule, Ruby, Value};
+use matryoshka_demo_core::Primes;
+
+use crate::clamped_u64;
+use crate::namespace::wrap;
+
+/// The primes in ascending order, as far as one generator has drawn them
+#[derive(Default, DataTypeFunctions)]
+struct RbGenerator {
+    primes: RefCell<Primes>,
+    /// Last number `each` may yield; None for no bound
+    upper_bound: Cell<Option<u64>>,
+}
+
+wrap!(RbGenerator, "EratosthenesGenerator");
+
+impl RbGenerator {
+    /// Start at 2, stopping `each` after `ubound` (nil for never)
+    /// Raises RangeError for bounds past u64
+    fn initialize(ruby: &Ruby, rb_self: &Self, args: &[Value]) -> Result<(), Error> {
+        let args = scan_args::<(), (Option<Option<Integer>>,), (), (), (), ()>(args)?;
+        let (ubound,) = args.optional;
+        Self::set_upper_bound(ruby, rb_self, ubound.flatten())?;
+        rb_self.rewind();
+        Ok(())
+    }
+
+    /// Carry on from where `orig` is, for `dup` and `clone`
+    fn initialize_copy(&self, orig: &Self) {
+        self.primes.replace(orig.primes.borrow().clone());
+        self.upper_bound.set(orig.upper_bound.get());
+    }
+
+    /// The last number `each` yields, or nil
+    fn upper_bound(&self) -> Option<u64> {
+        self.upper_bound.get()
+    }
+
+    /// Bound `each` by `ubound`; nil lets it run forever
+    /// Negative bounds yield nothing; raises RangeError past u64
+    fn set_upper_bound(ruby: &Ruby, rb_self: &Self, ubound: Option<Integer>) -> Result<(), Error> {
+        let ubound = ubound
+            .map(|ubound| clamped_u64(ruby, ubound, "upper_bound"))
+            .transpose()?;
+        rb_self.upper_bound.set(ubound);
+        Ok(())
+    }
+
+    /// The next prime; nil once every prime below 2^64 was drawn
+    fn succ(&self) -> Option<u64> {
+        self.primes.borrow_mut().next()
+    }
+
+    /// Start again from 2
+    fn rewind(&self) {
+        self.primes.replace(Primes::new());
+    }
+
+    /// Yield primes from the current position: up to `upper_bound` and
+    /// returning the block's last value if one is set, forever if not
+    /// Returns an Enumerator without a block
+    fn each(ruby: &Ruby, rb_self: Obj<Self>) -> Result<Value, Error> {
+        if !ruby.block_given() {
+            return Ok(rb_self.enumeratorize("each", ()).as_value());
+        }
+
+        // Read on every step, as the block may rewind or rebound the
+        // generator; only the iterator is borrowed, and never across a yield
+        let mut last = ruby.qnil().as_value();
+        while let Some(p) = rb_self.succ() {
+            if rb_self.upper_bound().is_some_and(|ubound| p > ubound) {
+                break;
+            }
+            last = ruby.yield_value(p)?;
+        }
+        Ok(last)
+    }
+}
+
+/// Define `MatryoshkaDemoNative::EratosthenesGenerator` under `module`
+pub(crate) fn define(ruby: &Ruby, module: RModule) -> Result<(), Error> {
+    let class = module.define_class("EratosthenesGenerator", ruby.class_object())?;
+    class.define_alloc_func::<RbGenerator>();
+    class.define_method("initialize", method!(RbGenerator::initialize, -1))?;
+    class.define_method("initialize_copy", method!(RbGenerator::initialize_copy, 1))?;
+    class.define_method("upper_bound", method!(RbGenerator::upper_bound, 0))?;
+    class.define_method("upper_bound=", method!(RbGenerator::set_upper_bound, 1))?;
+    class.define_method("succ", method!(RbGenerator::succ, 0))?;
+    class.define_method("next", method!(RbGenerator::succ, 0))?;
+    class.define_method("rewind", method!(RbGenerator::rewind, 0))?;
+    class.define_method("each", method!(RbGenerator::each, 0))?;
+    class.include_module(ruby.module_enumerable())?;
+    Ok(())
+}
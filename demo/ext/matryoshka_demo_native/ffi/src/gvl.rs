@@ -0,0 +1,156 @@
+//! Running core computations with the GVL released
+//!
+//! While a sieve runs for seconds, holding the Global VM Lock would stall
+//! every other Ruby thread. The closures passed here only touch Rust data,
+//! so they run under `rb_thread_call_without_gvl` and the result is turned
+//! into Ruby objects once the lock is back.
+//!
+//! A closure may call back into Ruby through `with_gvl`, which takes the
+//! lock again for the duration of the callback.
+//!
+//! Ruby cannot interrupt native code on its own, so the cancellable variant
+//! registers an unblock function: on SIGINT, Thread#raise or a Timeout,
+//! Ruby calls it from another thread, it sets the core CancelToken, and the
+//! computation stops at its next segment. A token past its deadline stops
+//! it the same way, and the call raises Timeout rather than Cancelled.
+
+use std::any::Any;
+use std::ffi::c_void;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use magnus::{Error, Ruby};
+use matryoshka_demo_core::{CancelToken, CoreError};
+
+use crate::call_error;
+
+/// Closure and result slot shared with the C trampoline
+struct Call<F, R> {
+    f: Option<F>,
+    result: Option<Result<R, Box<dyn Any + Send>>>,
+}
+
+/// Run `f` with the GVL released so other Ruby threads keep running
+/// `f` must not create, read or retain any Ruby object outside a
+/// [`with_gvl`] callback; a panic inside it is carried across the C frame
+/// and resumed once the GVL is reacquired
+pub(crate) fn without_gvl<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    call_without_gvl(f, None, ptr::null_mut())
+}
+
+/// Run `f` with the GVL released, handing it a CancelToken that a Ruby
+/// interrupt sets
+/// A pending interrupt is raised as Ruby's own exception (Interrupt for
+/// Ctrl-C, the given one for Thread#raise); core errors map as in error::core
+pub(crate) fn without_gvl_cancellable<F, R>(ruby: &Ruby, f: F) -> Result<R, Error>
+where
+    F: FnOnce(&CancelToken) -> Result<R, CoreError>,
+{
+    without_gvl_cancellable_by(ruby, &CancelToken::new(), f)
+}
+
+/// [`without_gvl_cancellable`] with `cancel`, which a Ruby interrupt sets
+/// as well, so it must be the call's own token (see `call_token`)
+/// Stopping at the token's deadline raises Timeout
+pub(crate) fn without_gvl_cancellable_by<F, R>(
+    ruby: &Ruby,
+    cancel: &CancelToken,
+    f: F,
+) -> Result<R, Error>
+where
+    F: FnOnce(&CancelToken) -> Result<R, CoreError>,
+{
+    let result = call_without_gvl(
+        || f(cancel),
+        Some(unblock),
+        cancel as *const CancelToken as *mut c_void,
+    );
+
+    ruby.thread_check_ints()?;
+    result.map_err(|error| call_error(ruby, cancel, error))
+}
+
+/// Run `f` with the GVL reacquired, from inside a closure that
+/// [`without_gvl`] or [`without_gvl_cancellable`] runs on this thread
+/// `f` may use Ruby again; a panic inside it is resumed on this side
+pub(crate) fn with_gvl<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let mut call = Call {
+        f: Some(f),
+        result: None,
+    };
+
+    // SAFETY: as in call_without_gvl; the calling thread is a Ruby thread
+    // that gave up the GVL through rb_thread_call_without_gvl
+    unsafe {
+        rb_sys::rb_thread_call_with_gvl(
+            Some(trampoline::<F, R>),
+            &mut call as *mut Call<F, R> as *mut c_void,
+        );
+    }
+
+    match call.result.expect("rb_thread_call_with_gvl ran the closure") {
+        Ok(result) => result,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+/// Release the GVL around `f`, with `ubf(data)` as the unblock function
+fn call_without_gvl<F, R>(
+    f: F,
+    ubf: Option<unsafe extern "C" fn(*mut c_void)>,
+    data: *mut c_void,
+) -> R
+where
+    F: FnOnce() -> R,
+{
+    let mut call = Call {
+        f: Some(f),
+        result: None,
+    };
+
+    // SAFETY: `call` and whatever `data` points to outlive the blocking
+    // call, and the trampoline is the only code touching `call` until
+    // rb_thread_call_without_gvl returns
+    unsafe {
+        rb_sys::rb_thread_call_without_gvl(
+            Some(trampoline::<F, R>),
+            &mut call as *mut Call<F, R> as *mut c_void,
+            ubf,
+            data,
+        );
+    }
+    // Records made while the GVL was released can reach Ruby now
+    #[cfg(feature = "log")]
+    crate::logger::flush();
+
+    match call.result.expect("rb_thread_call_without_gvl ran the closure") {
+        Ok(result) => result,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+/// Entry point Ruby calls without the GVL; unwinding must not cross it
+unsafe extern "C" fn trampoline<F, R>(data: *mut c_void) -> *mut c_void
+where
+    F: FnOnce() -> R,
+{
+    // SAFETY: `data` is the `Call` without_gvl passed in, alive and unaliased
+    let call = unsafe { &mut *(data as *mut Call<F, R>) };
+    let f = call.f.take().expect("trampoline runs once");
+    call.result = Some(panic::catch_unwind(AssertUnwindSafe(f)));
+    ptr::null_mut()
+}
+
+/// Unblock function Ruby calls, from another thread, to interrupt the call
+unsafe extern "C" fn unblock(data: *mut c_void) {
+    // SAFETY: `data` is the CancelToken without_gvl_cancellable_by borrows
+    // for the whole call; cancel only stores to an atomic
+    let cancel = unsafe { &*(data as *const CancelToken) };
+    cancel.cancel();
+}
@@ -0,0 +1,117 @@
+//! ActiveSupport::Notifications events for the long-running calls
+//!
+//! When `ActiveSupport::Notifications` is loaded, an instrumented call is
+//! timed in Rust and, once it has returned with the GVL held, published as
+//! `matryoshka_demo.<call>` (e.g. `matryoshka_demo.count_primes`) with a
+//! payload of:
+//!
+//! - `limit:` the largest number the call looked at
+//! - `algorithm:` how it answered, e.g. `:cached`, `:segmented` or `:sublinear`
+//! - `memory:` bytes held by the process-wide sieve afterwards
+//! - `duration:` milliseconds spent, as the event's own duration
+//!
+//! Without ActiveSupport, or outside the main Ractor, where its subscribers
+//! cannot be reached, a call only pays for one constant lookup. Calls that
+//! raise publish nothing.
+
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use magnus::prelude::*;
+use magnus::{Error, RClass, RModule, Ruby, Time, Value};
+
+use crate::in_main_ractor;
+
+/// Prefix of every event name
+const NAMESPACE: &str = "matryoshka_demo";
+
+/// What an instrumented call did, for its event payload
+pub(crate) struct Payload {
+    pub(crate) limit: u64,
+    pub(crate) algorithm: &'static str,
+}
+
+/// `ActiveSupport::Notifications`, if it is loaded and reachable from the
+/// calling Ractor
+fn notifications(ruby: &Ruby) -> Result<Option<RModule>, Error> {
+    let object = ruby.class_object();
+    if !object.funcall::<_, _, bool>("const_defined?", ("ActiveSupport::Notifications",))?
+        || !in_main_ractor(ruby)?
+    {
+        return Ok(None);
+    }
+
+    object
+        .funcall("const_get", ("ActiveSupport::Notifications",))
+        .map(Some)
+}
+
+/// A Ruby Time for `at`
+fn time(ruby: &Ruby, at: SystemTime) -> Result<Time, Error> {
+    let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    ruby.time_new(
+        since_epoch.as_secs() as i64,
+        since_epoch.subsec_micros() as i64,
+    )
+}
+
+/// Run `f` and publish `matryoshka_demo.<call>` for it if
+/// ActiveSupport::Notifications is loaded; with the `log` feature the call
+/// is logged at debug level as well
+/// Subscribers run after `f` returns, with the GVL held; an exception they
+/// raise is raised from the call, as with `instrument`
+pub(crate) fn instrument<R>(
+    ruby: &Ruby,
+    call: &str,
+    payload: Payload,
+    f: impl FnOnce() -> Result<R, Error>,
+) -> Result<R, Error> {
+    #[cfg(feature = "log")]
+    log::debug!(
+        "{NAMESPACE}.{call}: limit {}, algorithm {}",
+        payload.limit,
+        payload.algorithm
+    );
+    let Some(notifications) = notifications(ruby)? else {
+        return f();
+    };
+
+    let started = SystemTime::now();
+    let clock = Instant::now();
+    let result = f()?;
+    let elapsed = clock.elapsed();
+
+    let hash = ruby.hash_new_capa(4);
+    hash.aset(ruby.to_symbol("limit"), payload.limit)?;
+    hash.aset(
+        ruby.to_symbol("algorithm"),
+        ruby.to_symbol(payload.algorithm),
+    )?;
+    hash.aset(
+        ruby.to_symbol("memory"),
+        matryoshka_demo_core::cached_memory_usage(),
+    )?;
+    hash.aset(ruby.to_symbol("duration"), elapsed.as_secs_f64() * 1000.0)?;
+
+    let name = format!("{NAMESPACE}.{call}");
+    let id: Value = notifications
+        .funcall::<_, _, Value>("instrumenter", ())?
+        .funcall("id", ())?;
+    let event = (
+        name.as_str(),
+        time(ruby, started)?,
+        time(ruby, started + elapsed)?,
+        id,
+        hash,
+    );
+    // ActiveSupport 7.1 delivers Event objects for a block taking one
+    // argument only through publish_event; older versions build them from
+    // the five publish arguments themselves
+    if notifications.respond_to("publish_event", false)? {
+        let class: RClass = notifications.const_get("Event")?;
+        let event: Value = class.new_instance(event)?;
+        notifications.funcall::<_, _, Value>("publish_event", (event,))?;
+    } else {
+        notifications.funcall::<_, _, Value>("publish", event)?;
+    }
+    Ok(result)
+}
@@ -0,0 +1,54 @@
+//! `MatryoshkaDemoNative.patch_integer!`: opt-in native methods on Integer
+//!
+//! Nothing touches Integer until `patch_integer!` is called, so loading the
+//! extension never changes core classes behind an application's back. Once
+//! called, `Integer#prime_native?` and `Integer#prime_factors_native` are
+//! defined here, straight onto Integer, and run the same code as
+//! `MatryoshkaDemoNative.prime?` and `.factorize` with no Ruby in between.
+
+use magnus::prelude::*;
+use magnus::{function, method, Error, Integer, RArray, RClass, RModule, Ruby};
+
+use crate::{is_prime_native, prime_division, Output};
+
+/// `n.prime_native?`: whether the receiver is prime, as `prime?` answers
+/// Rust FFI wrapper for Ruby; raises RangeError past 128 bits
+fn prime_native(ruby: &Ruby, n: Integer) -> Result<bool, Error> {
+    is_prime_native(ruby, n)
+}
+
+/// `n.prime_factors_native`: the prime factors of the receiver with
+/// multiplicity, ascending, e.g. `[2, 2, 3]` for 12; negatives start with
+/// -1 and 1 gives an empty Array
+/// Rust FFI wrapper for Ruby; raises ZeroDivisionError for 0, RangeError
+/// past u64
+fn prime_factors_native(ruby: &Ruby, n: Integer) -> Result<Output<RArray>, Error> {
+    let (negative, factors) = prime_division(ruby, n)?;
+    let primes = factors
+        .into_iter()
+        .flat_map(|(p, exponent)| (0..exponent).map(move |_| p));
+    let array = ruby.ary_new();
+    if negative {
+        array.push(-1)?;
+    }
+    for p in primes {
+        array.push(p)?;
+    }
+    Ok(Output(array))
+}
+
+/// Define `prime_native?` and `prime_factors_native` on Integer; calling it
+/// again redefines them, which changes nothing
+/// Rust FFI wrapper for Ruby; returns Integer
+fn patch_integer_native(ruby: &Ruby) -> Result<RClass, Error> {
+    let integer = ruby.class_integer();
+    integer.define_method("prime_native?", method!(prime_native, 0))?;
+    integer.define_method("prime_factors_native", method!(prime_factors_native, 0))?;
+    Ok(integer)
+}
+
+/// Define `MatryoshkaDemoNative.patch_integer!` under `module`
+pub(crate) fn define(module: RModule) -> Result<(), Error> {
+    module.define_module_function("patch_integer!", function!(patch_integer_native, 0))?;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+//! Integer arithmetic and prime stepping, bound by `#[matryoshka::export]`
+//!
+//! Each function here takes and answers plain values; the macro adds its
+//! `_native` wrapper, converting the Integers and raising ArgumentError
+//! for negatives and RangeError past their width, and one `define`
+//! registering them all. A binding needing no more than that belongs here.
+
+pub(crate) use exports::*;
+
+#[matryoshka::export]
+mod exports {
+    use std::num::NonZeroU64;
+
+    /// Integer square root: the largest r with r^2 <= n
+    pub(crate) fn isqrt(n: u64) -> u64 {
+        matryoshka_demo_core::isqrt(n)
+    }
+
+    /// Integer cube root: the largest r with r^3 <= n
+    pub(crate) fn icbrt(n: u64) -> u64 {
+        matryoshka_demo_core::icbrt(n)
+    }
+
+    /// `base ^ exp`, or nil if it overflows a u64
+    pub(crate) fn checked_pow(base: u64, exp: u32) -> Option<u64> {
+        matryoshka_demo_core::checked_pow(base, exp)
+    }
+
+    /// Base-2 logarithm rounded down; 0 raises ArgumentError
+    pub(crate) fn ilog2(n: NonZeroU64) -> u32 {
+        matryoshka_demo_core::ilog2(n.get())
+    }
+
+    /// `(base ^ exponent) mod modulus`; a zero modulus raises ArgumentError
+    pub(crate) fn pow_mod(base: u64, exponent: u64, modulus: NonZeroU64) -> u64 {
+        matryoshka_demo_core::pow_mod(base, exponent, modulus.get())
+    }
+
+    /// Greatest common divisor of `|a|` and `|b|`
+    pub(crate) fn gcd(#[export(abs)] a: u64, #[export(abs)] b: u64) -> u64 {
+        matryoshka_demo_core::gcd(a, b)
+    }
+
+    /// The smallest prime strictly greater than `n`, or nil if it passes u64
+    pub(crate) fn next_prime(#[export(clamped)] n: u64) -> Option<u64> {
+        matryoshka_demo_core::next_prime(n)
+    }
+
+    /// The largest prime strictly less than `n`, or nil below 3
+    pub(crate) fn prev_prime(#[export(clamped)] n: u64) -> Option<u64> {
+        matryoshka_demo_core::prev_prime(n)
+    }
+}
@@ -1,33 +1,1263 @@
-use magnus::{function, Error, Ruby};
-use matryoshka_demo_core;
+use std::io::Write;
+use std::num::NonZeroU64;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use magnus::prelude::*;
+use magnus::scan_args::{get_kwargs, scan_args};
+use magnus::{
+    function, kwargs, method, Error, Integer, RArray, RClass, RHash, RModule, RString, Range, Ruby,
+    Symbol, Value,
+};
+use matryoshka_demo_core::{self, CancelToken, CoreError, CountAlgorithm, CountOptions, Primes};
+use rand_core::OsRng;
+
+mod bang;
+mod batch;
+mod cancel;
+mod concurrency;
+mod config;
+mod error;
+mod generator;
+mod gvl;
+mod instrument;
+mod integer;
+mod intmath;
+#[cfg(feature = "log")]
+mod logger;
+mod memory;
+mod namespace;
+#[cfg(unix)]
+mod nonblocking;
+mod output;
+mod retained;
+mod sieve;
+mod stats;
+mod threads;
+
+use cancel::{call_token, without_gvl_cancellable_opt};
+use config::CONFIG;
+use gvl::{with_gvl, without_gvl, without_gvl_cancellable, without_gvl_cancellable_by};
+use instrument::{instrument, Payload};
+use output::Output;
+use threads::Threads;
+
+/// [`error::core`] for a call that stopped on `cancel`, raising Timeout
+/// rather than Cancelled once the token's deadline passed
+fn call_error(ruby: &Ruby, cancel: &CancelToken, error: CoreError) -> Error {
+    match error {
+        CoreError::Cancelled if cancel.timed_out() => {
+            error::timeout(ruby, "computation timed out".to_owned())
+        }
+        error => error::core(ruby, error),
+    }
+}
+
+/// Whether the calling thread runs in the main Ractor, the only one that
+/// can reach objects such as a Logger or ActiveSupport's subscribers
+fn in_main_ractor(ruby: &Ruby) -> Result<bool, Error> {
+    let ractor: RClass = ruby.class_object().const_get("Ractor")?;
+    let current: Value = ractor.funcall("current", ())?;
+    current.equal(ractor.funcall::<_, _, Value>("main", ())?)
+}
+
+/// Read `value` as an Integer, converting objects that are not Numeric
+/// through `to_int`, so wrappers around an Integer are accepted
+/// Floats and other non-Integer Numerics give None for the caller to reject
+fn integer_like(ruby: &Ruby, value: Value) -> Result<Option<Integer>, Error> {
+    if let Some(n) = Integer::from_value(value) {
+        return Ok(Some(n));
+    }
+    if value.is_kind_of(ruby.class_numeric()) || !value.respond_to("to_int", false)? {
+        return Ok(None);
+    }
+
+    Integer::try_convert(value).map(Some)
+}
+
+/// Read the Integer argument `name`, which must be at least `min`
+/// Raises ArgumentError for non-Integers (Floats included; see
+/// `integer_like`) and values below `min`, RangeError past u64; with `config.strict = false` values below
+/// `min` give None and the caller returns its old empty result (0, nil or
+/// an empty Array)
+fn domain_arg(ruby: &Ruby, value: Value, name: &str, min: u64) -> Result<Option<u64>, Error> {
+    let n = if !CONFIG.strict() {
+        let n = Integer::try_convert(value)?;
+        if n < ruby.integer_from_u64(min) {
+            return Ok(None);
+        }
+        n
+    } else {
+        let Some(n) = integer_like(ruby, value)? else {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                format!("{name} must be an Integer, got {}", value.inspect()),
+            ));
+        };
+        if n < ruby.integer_from_u64(min) {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                format!("{name} must be at least {min}, got {n}"),
+            ));
+        }
+        n
+    };
+    u64_from_integer(ruby, n, name).map(Some)
+}
+
+/// Convert a non-negative Ruby Integer to a u64
+/// Raises ArgumentError for negatives and RangeError past 64 bits
+fn u64_from_integer(ruby: &Ruby, n: Integer, name: &str) -> Result<u64, Error> {
+    if n < ruby.integer_from_u64(0) {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            format!("{name} must not be negative, got {n}"),
+        ));
+    }
+
+    n.to_u64().map_err(|_| {
+        Error::new(
+            ruby.exception_range_error(),
+            format!("{name} must fit in 64 bits, got {n}"),
+        )
+    })
+}
+
+/// Convert a non-negative Ruby Integer to a u32
+/// Raises ArgumentError for negatives and RangeError past 32 bits
+fn u32_from_integer(ruby: &Ruby, n: Integer, name: &str) -> Result<u32, Error> {
+    let wide = u64_from_integer(ruby, n, name)?;
+    u32::try_from(wide).map_err(|_| {
+        Error::new(
+            ruby.exception_range_error(),
+            format!("{name} must fit in 32 bits, got {n}"),
+        )
+    })
+}
+
+/// Convert a positive Ruby Integer to a NonZeroU64
+/// Raises ArgumentError for 0 and negatives, RangeError past 64 bits
+fn non_zero_u64_from_integer(ruby: &Ruby, n: Integer, name: &str) -> Result<NonZeroU64, Error> {
+    NonZeroU64::new(u64_from_integer(ruby, n, name)?).ok_or_else(|| {
+        Error::new(
+            ruby.exception_arg_error(),
+            format!("{name} must be positive, got {n}"),
+        )
+    })
+}
+
+/// Convert a Ruby Integer to a u64 with negatives clamped to 0, for
+/// functions whose answer does not change below 0
+/// Raises RangeError past 64 bits
+fn clamped_u64(ruby: &Ruby, n: Integer, name: &str) -> Result<u64, Error> {
+    if n < ruby.integer_from_u64(0) {
+        return Ok(0);
+    }
+
+    u64_from_integer(ruby, n, name)
+}
+
+/// Split a Ruby Integer into its sign and a u64 magnitude
+/// Raises RangeError if `|n|` does not fit in 64 bits
+fn magnitude(ruby: &Ruby, n: Integer, name: &str) -> Result<(bool, u64), Error> {
+    let zero = ruby.integer_from_u64(0);
+    let negative = n < zero;
+    let abs = if negative { zero - n } else { n };
+    let abs = abs.to_u64().map_err(|_| {
+        Error::new(
+            ruby.exception_range_error(),
+            format!("|{name}| must fit in 64 bits, got {n}"),
+        )
+    })?;
+    Ok((negative, abs))
+}
+
+/// Switch lenient argument handling on or off for the whole process; the
+/// inverse of `config.strict=`
+/// Rust FFI wrapper for Ruby
+fn set_lenient_native(lenient: bool) -> bool {
+    CONFIG.set_strict(!lenient);
+    lenient
+}
+
+/// Whether lenient argument handling is on
+/// Rust FFI wrapper for Ruby
+fn is_lenient_native() -> bool {
+    !CONFIG.strict()
+}
+
+/// Largest limit `count_primes` accepts from Ruby
+/// Counting takes O(n^(3/4)) time and O(sqrt n) memory: about a minute and
+/// 160 MB at this bound, where 2^62 would mean hours and 34 GB
+const MAX_COUNT_LIMIT: u64 = 100_000_000_000_000;
 
 /// Count prime numbers up to and including `limit`
+/// Keywords: `algorithm:` (:auto, :segmented or :sublinear), `threads:` for
+/// the worker pool, `cancel:` a CancelToken another thread may set,
+/// `timeout:` in seconds and `progress:` a callable taking `(done, total)`;
+/// `threads:` defaults to `config.threads`, and `config` sets the segment
+/// size and cached range (answered without checking `timeout:`)
+/// Publishes `matryoshka_demo.count_primes` when ActiveSupport is loaded
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits and
+/// unknown options, ResourceLimit above MAX_COUNT_LIMIT, Cancelled once
+/// `cancel:` is set and Timeout past `timeout:`
+fn count_primes_native(ruby: &Ruby, args: &[Value]) -> Result<u64, Error> {
+    type Options = (
+        Option<Symbol>,
+        Option<Value>,
+        Option<Value>,
+        Option<Value>,
+        Option<Value>,
+    );
+    let args = scan_args::<(Value,), (), (), (), RHash, ()>(args)?;
+    let (limit,) = args.required;
+    let kwargs = get_kwargs::<_, (), Options, ()>(
+        args.keywords,
+        &[],
+        &["algorithm", "threads", "cancel", "timeout", "progress"],
+    )?;
+    let (algorithm, threads, cancel, timeout, progress) = kwargs.optional;
+    let options = CountOptions {
+        algorithm: count_algorithm(ruby, algorithm)?,
+        segment_bytes: CONFIG.segment_bytes(),
+    };
+    let threads = Threads::from_option(ruby, threads)?;
+    let cancel = call_token(ruby, cancel, timeout)?;
+
+    let Some(limit) = domain_arg(ruby, limit, "limit", 0)? else {
+        return Ok(0);
+    };
+    if limit > MAX_COUNT_LIMIT {
+        return Err(error::resource_limit(
+            ruby,
+            format!("limit must be at most {MAX_COUNT_LIMIT}, got {limit}"),
+        ));
+    }
+
+    if let Some(progress) = progress {
+        // The callback needs a Ruby thread, which a pool worker is not
+        if threads.is_pool() {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                "progress: cannot be combined with threads:",
+            ));
+        }
+        if !progress.respond_to("call", false)? {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                format!("progress must respond to call, got {}", progress.inspect()),
+            ));
+        }
+        let payload = Payload {
+            limit,
+            algorithm: algorithm_name(options.algorithm.resolve(0, limit)),
+        };
+        return instrument(ruby, "count_primes", payload, || {
+            count_primes_with_progress(ruby, limit, options, progress, &cancel)
+        });
+    }
+
+    let cached = options.algorithm == CountAlgorithm::Auto && limit <= CONFIG.cache_limit();
+    let payload = Payload {
+        limit,
+        algorithm: if cached {
+            "cached"
+        } else {
+            algorithm_name(options.algorithm.resolve(0, limit))
+        },
+    };
+    // The cached range is sieved in well under a second; above it the
+    // counter checks for Ruby interrupts once per sieving prime
+    let count = |cancel: &CancelToken| {
+        if cached {
+            matryoshka_demo_core::cached_count_primes(limit)
+        } else {
+            threads.install(|| {
+                matryoshka_demo_core::try_count_primes_with_options(..=limit, options, |_, _| {
+                    cancel.observe()
+                })
+            })
+        }
+    };
+    instrument(ruby, "count_primes", payload, || {
+        let count = without_gvl_cancellable_by(ruby, &cancel, count);
+        memory::sync_cache();
+        count
+    })
+}
+
+/// Count the primes up to `limit`, calling `progress.call(done, total)`
+/// with the GVL reacquired as the count advances, in steps of at least
+/// 1/`config.progress_steps` of the work; the process-wide sieve is
+/// bypassed so every count reports
+/// An exception raised by the callback stops the count and is re-raised
+fn count_primes_with_progress(
+    ruby: &Ruby,
+    limit: u64,
+    options: CountOptions,
+    progress: Value,
+    cancel: &CancelToken,
+) -> Result<u64, Error> {
+    let mut raised = None;
+    let mut reported = None;
+    let steps = CONFIG.progress_steps() as u128;
+    let count = without_gvl_cancellable_by(ruby, cancel, |cancel| {
+        matryoshka_demo_core::try_count_primes_with_options(..=limit, options, |done, total| {
+            let step = done as u128 * steps / total.max(1) as u128;
+            if reported != Some(step) {
+                reported = Some(step);
+                let called = with_gvl(|| progress.funcall::<_, _, Value>("call", (done, total)));
+                if let Err(error) = called {
+                    raised = Some(error);
+                    return ControlFlow::Break(());
+                }
+            }
+            cancel.observe()
+        })
+    });
+
+    match raised {
+        Some(error) => Err(error),
+        None => count,
+    }
+}
+
+/// Map an `algorithm:` Symbol to the core counting algorithm
+/// Raises ArgumentError for anything but :auto, :segmented and :sublinear
+fn count_algorithm(ruby: &Ruby, algorithm: Option<Symbol>) -> Result<CountAlgorithm, Error> {
+    let Some(algorithm) = algorithm else {
+        return Ok(CountAlgorithm::Auto);
+    };
+
+    match algorithm.name()?.as_ref() {
+        "auto" => Ok(CountAlgorithm::Auto),
+        "segmented" => Ok(CountAlgorithm::Segmented),
+        "sublinear" => Ok(CountAlgorithm::Sublinear),
+        other => Err(Error::new(
+            ruby.exception_arg_error(),
+            format!("algorithm must be :auto, :segmented or :sublinear, got :{other}"),
+        )),
+    }
+}
+
+/// The `algorithm:` Symbol name for a core counting algorithm
+fn algorithm_name(algorithm: CountAlgorithm) -> &'static str {
+    match algorithm {
+        CountAlgorithm::Auto => "auto",
+        CountAlgorithm::Segmented => "segmented",
+        CountAlgorithm::Sublinear => "sublinear",
+    }
+}
+
+/// Estimate the number of primes up to `limit` from the logarithmic integral
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits
+fn prime_pi_approx_native(ruby: &Ruby, limit: Value) -> Result<u64, Error> {
+    let Some(limit) = domain_arg(ruby, limit, "limit", 0)? else {
+        return Ok(0);
+    };
+
+    Ok(matryoshka_demo_core::prime_pi_approx(limit))
+}
+
+/// Free the process-wide sieve behind `count_primes`
 /// Rust FFI wrapper for Ruby
-fn count_primes_native(limit: i64) -> i64 {
-    if limit < 0 {
-        return 0;
+fn clear_prime_cache_native() {
+    matryoshka_demo_core::clear_cache();
+    memory::sync_cache();
+}
+
+/// Largest limit `prime_bitmap` accepts from Ruby (a 100 MB String)
+const MAX_BITMAP_LIMIT: u64 = 3_000_000_000;
+
+/// Sieve up to `limit` and return the raw wheel bitset as a binary String
+/// Layout as documented on `Sieve::as_bits`: byte k covers 30k + [1, 7, 11,
+/// 13, 17, 19, 23, 29], least significant bit first; 2, 3 and 5 are omitted
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits and
+/// ResourceLimit above MAX_BITMAP_LIMIT
+fn prime_bitmap_native(ruby: &Ruby, limit: Integer) -> Result<Output<RString>, Error> {
+    let limit = u64_from_integer(ruby, limit, "limit")?;
+    if limit > MAX_BITMAP_LIMIT {
+        return Err(error::resource_limit(
+            ruby,
+            format!("limit must be at most {MAX_BITMAP_LIMIT}, got {limit}"),
+        ));
     }
 
-    let result = matryoshka_demo_core::count_primes(limit as usize);
-    result as i64
+    let sieve = without_gvl_cancellable(ruby, |cancel| {
+        matryoshka_demo_core::Sieve::try_new_with_cancel(limit, cancel)
+    })?;
+    Ok(Output(ruby.str_from_slice(sieve.as_bits())))
 }
 
 /// Find the nth prime number (1-indexed)
+/// Rust FFI wrapper for Ruby; raises ArgumentError unless `n` is positive
+/// and OverflowError if p_n does not fit in a u64
+fn nth_prime_native(ruby: &Ruby, n: Value) -> Result<Option<u64>, Error> {
+    let Some(n) = domain_arg(ruby, n, "n", 1)? else {
+        return Ok(None);
+    };
+
+    without_gvl_cancellable(ruby, |cancel| {
+        matryoshka_demo_core::try_nth_prime_with_cancel(n, cancel)
+    })
+    .map(Some)
+}
+
+/// Find the nth prime (1-indexed) strictly greater than `start`
+/// Rust FFI wrapper for Ruby; raises ArgumentError unless `n` is positive,
+/// nil past u64
+fn nth_prime_after_native(ruby: &Ruby, start: Integer, n: Value) -> Result<Option<u64>, Error> {
+    let start = clamped_u64(ruby, start, "start")?;
+    let Some(n) = domain_arg(ruby, n, "n", 1)? else {
+        return Ok(None);
+    };
+
+    Ok(without_gvl(|| matryoshka_demo_core::nth_prime_after(start, n)))
+}
+
+/// Sum the prime numbers up to and including `limit`
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits,
+/// sums past u64 become a Bignum
+fn sum_of_primes_native(ruby: &Ruby, limit: Value) -> Result<Integer, Error> {
+    let limit = domain_arg(ruby, limit, "limit", 0)?.unwrap_or(0);
+    if limit < 2 {
+        return Ok(ruby.integer_from_u64(0));
+    }
+
+    let sum = without_gvl(|| matryoshka_demo_core::sum_of_primes(limit));
+    Ok(integer_from_u128(ruby, sum))
+}
+
+/// Convert a u128 to a Ruby Integer, becoming a Bignum past u64
+fn integer_from_u128(ruby: &Ruby, n: u128) -> Integer {
+    match u64::try_from(n) {
+        Ok(small) => ruby.integer_from_u64(small),
+        Err(_) => {
+            // magnus has no u128 conversion, so rebuild it as hi * 2^64 + lo
+            let half = ruby.integer_from_u64(1 << 32);
+            let hi = ruby.integer_from_u64((n >> 64) as u64);
+            let lo = ruby.integer_from_u64(n as u64);
+            hi * half * half + lo
+        }
+    }
+}
+
+/// Convert a non-negative Ruby Integer of at most 128 bits to a u128
+/// Raises ArgumentError for negatives and RangeError past 128 bits
+fn u128_from_integer(ruby: &Ruby, n: Integer) -> Result<u128, Error> {
+    if n < ruby.integer_from_u64(0) {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            format!("n must not be negative, got {n}"),
+        ));
+    }
+
+    // The inverse of integer_from_u128: split into hi * 2^64 + lo
+    let half = ruby.integer_from_u64(1 << 32);
+    let base = half * half;
+    let hi = n / base;
+    let lo = n - hi * base;
+    let hi = hi.to_u64().map_err(|_| {
+        Error::new(
+            ruby.exception_range_error(),
+            format!("n must fit in 128 bits, got {n}"),
+        )
+    })?;
+    Ok(((hi as u128) << 64) | lo.to_u64()? as u128)
+}
+
+/// Largest limit `primes_up_to` accepts from Ruby
+/// pi(10^9) is about 51 million primes, already a 400 MB Array
+const MAX_LIST_LIMIT: u64 = 1_000_000_000;
+
+/// Collect all prime numbers up to and including `limit` into an Array
+/// The Array is preallocated from the Li(x) estimate and filled segment by
+/// segment, so no intermediate Vec is built
+/// Publishes `matryoshka_demo.primes_up_to` when ActiveSupport is loaded
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits and
+/// ResourceLimit above MAX_LIST_LIMIT
+fn primes_up_to_native(ruby: &Ruby, limit: Value) -> Result<Output<RArray>, Error> {
+    let limit = domain_arg(ruby, limit, "limit", 0)?.unwrap_or(0);
+    if limit < 2 {
+        return Ok(Output(ruby.ary_new()));
+    }
+    if limit > MAX_LIST_LIMIT {
+        return Err(error::resource_limit(
+            ruby,
+            format!("limit must be at most {MAX_LIST_LIMIT}, got {limit}"),
+        ));
+    }
+
+    let payload = Payload {
+        limit,
+        algorithm: "segmented",
+    };
+    instrument(ruby, "primes_up_to", payload, || {
+        let primes = ruby.ary_new_capa(matryoshka_demo_core::prime_pi_approx(limit) as usize);
+        let mut pushed = Ok(());
+        let _ = matryoshka_demo_core::for_each_prime(limit, |p| {
+            pushed = primes.push(p);
+            match pushed {
+                Ok(()) => ControlFlow::Continue(()),
+                Err(_) => ControlFlow::Break(()),
+            }
+        });
+        pushed.map(|()| Output(primes))
+    })
+}
+
+/// Collect the primes up to and including `limit` as a binary String of
+/// little-endian integers, without a Ruby Integer per prime: `format: :u64`
+/// (the default, `unpack("Q<*")`) or `:u32` (`unpack("L<*")`); `cancel:`
+/// takes a CancelToken and `timeout:` seconds
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits and
+/// unknown formats, ResourceLimit above MAX_LIST_LIMIT, Timeout past
+/// `timeout:`
+fn primes_packed_native(ruby: &Ruby, args: &[Value]) -> Result<Output<RString>, Error> {
+    // Every prime a list may hold fits the narrowest format
+    const _: () = assert!(MAX_LIST_LIMIT <= u32::MAX as u64);
+
+    let args = scan_args::<(Value,), (), (), (), RHash, ()>(args)?;
+    let (limit,) = args.required;
+    let kwargs = get_kwargs::<_, (), (Option<Symbol>, Option<Value>, Option<Value>), ()>(
+        args.keywords,
+        &[],
+        &["format", "cancel", "timeout"],
+    )?;
+    let (format, cancel, timeout) = kwargs.optional;
+    let narrow = match format {
+        None => false,
+        Some(format) => match format.name()?.as_ref() {
+            "u64" => false,
+            "u32" => true,
+            other => {
+                return Err(Error::new(
+                    ruby.exception_arg_error(),
+                    format!("format must be :u32 or :u64, got :{other}"),
+                ))
+            }
+        },
+    };
+    let limit = domain_arg(ruby, limit, "limit", 0)?.unwrap_or(0);
+    if limit > MAX_LIST_LIMIT {
+        return Err(error::resource_limit(
+            ruby,
+            format!("limit must be at most {MAX_LIST_LIMIT}, got {limit}"),
+        ));
+    }
+
+    let bytes = without_gvl_cancellable_opt(ruby, cancel, timeout, |cancel| {
+        let width = if narrow { 4 } else { 8 };
+        let estimate = matryoshka_demo_core::prime_pi_approx(limit) as usize;
+        let mut bytes = Vec::with_capacity(estimate * width);
+        let flow = matryoshka_demo_core::for_each_prime(limit, |p| {
+            if narrow {
+                bytes.extend_from_slice(&(p as u32).to_le_bytes());
+            } else {
+                bytes.extend_from_slice(&p.to_le_bytes());
+            }
+            cancel.observe()
+        });
+        match flow {
+            ControlFlow::Continue(()) => Ok(bytes),
+            ControlFlow::Break(()) => Err(CoreError::Cancelled),
+        }
+    })?;
+    Ok(Output(ruby.str_from_slice(&bytes)))
+}
+
+/// Bytes `write_primes` formats before handing them to the IO
+const WRITE_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Write each prime up to and including `limit` to `io`, each followed by
+/// `separator:` ("\n" unless given), and return how many were written;
+/// `cancel:` takes a CancelToken and `timeout:` seconds
+/// Primes are formatted with the GVL released; it is reacquired to pass
+/// each 64 KiB chunk to `io.write`, whose exceptions stop the sweep and are
+/// re-raised
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits and
+/// unless `io` responds to write, Timeout past `timeout:`
+fn write_primes_native(ruby: &Ruby, args: &[Value]) -> Result<u64, Error> {
+    let args = scan_args::<(Value, Value), (), (), (), RHash, ()>(args)?;
+    let (io, limit) = args.required;
+    let kwargs = get_kwargs::<_, (), (Option<RString>, Option<Value>, Option<Value>), ()>(
+        args.keywords,
+        &[],
+        &["separator", "cancel", "timeout"],
+    )?;
+    let (separator, cancel, timeout) = kwargs.optional;
+    if !io.respond_to("write", false)? {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            format!("io must respond to write, got {}", io.inspect()),
+        ));
+    }
+    let separator = match separator {
+        // SAFETY: the bytes are copied before any Ruby code can run
+        Some(separator) => unsafe { separator.as_slice() }.to_vec(),
+        None => b"\n".to_vec(),
+    };
+    let Some(limit) = domain_arg(ruby, limit, "limit", 0)? else {
+        return Ok(0);
+    };
+
+    let mut raised = None;
+    let mut flush = |chunk: &mut Vec<u8>| {
+        let written =
+            with_gvl(|| io.funcall::<_, _, Value>("write", (ruby.str_from_slice(chunk),)));
+        chunk.clear();
+        match written {
+            Ok(_) => ControlFlow::Continue(()),
+            Err(error) => {
+                raised = Some(error);
+                ControlFlow::Break(())
+            }
+        }
+    };
+    let written = without_gvl_cancellable_opt(ruby, cancel, timeout, |cancel| {
+        let mut chunk = Vec::with_capacity(WRITE_CHUNK_BYTES + 20 + separator.len());
+        let mut written = 0;
+        let flow = matryoshka_demo_core::for_each_prime(limit, |p| {
+            // Writing to a Vec cannot fail
+            let _ = write!(chunk, "{p}");
+            chunk.extend_from_slice(&separator);
+            written += 1;
+            if chunk.len() >= WRITE_CHUNK_BYTES {
+                flush(&mut chunk)?;
+            }
+            cancel.observe()
+        });
+        let flow = match flow {
+            ControlFlow::Continue(()) if !chunk.is_empty() => flush(&mut chunk),
+            flow => flow,
+        };
+        match flow {
+            ControlFlow::Continue(()) => Ok(written),
+            ControlFlow::Break(()) => Err(CoreError::Cancelled),
+        }
+    });
+
+    match raised {
+        Some(error) => Err(error),
+        None => written,
+    }
+}
+
+/// Yield each prime up to and including `limit` to the block as it is
+/// sieved, without building an Array; returns an Enumerator without a block
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits
+fn each_prime_native(ruby: &Ruby, rb_self: RModule, limit: Value) -> Result<Value, Error> {
+    // Checked up front so a bad limit raises here, not when enumerated
+    let checked = domain_arg(ruby, limit, "limit", 0)?.unwrap_or(0);
+    if !ruby.block_given() {
+        return Ok(rb_self.enumeratorize("each_prime", (limit,)).as_value());
+    }
+    let limit = checked;
+    if limit < 2 {
+        return Ok(rb_self.as_value());
+    }
+
+    // An exception or `break` in the block stops the sweep and is re-raised
+    let mut yielded = Ok(());
+    let _ = matryoshka_demo_core::for_each_prime(limit, |p| {
+        yielded = ruby.yield_value::<u64, Value>(p).map(|_| ());
+        match yielded {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(_) => ControlFlow::Break(()),
+        }
+    });
+    yielded.map(|()| rb_self.as_value())
+}
+
+/// Primes each Array `each_prime_slice` yields holds unless `batch_size:`
+/// is given
+const DEFAULT_SLICE: u64 = 10_000;
+
+/// Largest `batch_size:` `each_prime_slice` accepts
+const MAX_SLICE: u64 = 1 << 24;
+
+/// Yield the primes up to and including `limit` to the block in Arrays of
+/// `batch_size:` primes (DEFAULT_SLICE unless given), the last one shorter,
+/// as they are sieved; returns an Enumerator without a block
+/// One block call per Array instead of per prime, for blocks cheap enough
+/// that Ruby's call overhead would dominate
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits and
+/// batch sizes outside 1..=MAX_SLICE
+fn each_prime_slice_native(ruby: &Ruby, rb_self: RModule, args: &[Value]) -> Result<Value, Error> {
+    let args = scan_args::<(Value,), (), (), (), RHash, ()>(args)?;
+    let (limit,) = args.required;
+    let kwargs = get_kwargs::<_, (), (Option<Value>,), ()>(args.keywords, &[], &["batch_size"])?;
+    let (batch_size,) = kwargs.optional;
+    let batch_size = match batch_size {
+        Some(batch_size) => domain_arg(ruby, batch_size, "batch_size", 1)?,
+        None => None,
+    }
+    .unwrap_or(DEFAULT_SLICE);
+    if batch_size > MAX_SLICE {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            format!("batch_size must be at most {MAX_SLICE}, got {batch_size}"),
+        ));
+    }
+
+    // Checked up front so a bad limit raises here, not when enumerated
+    let checked = domain_arg(ruby, limit, "limit", 0)?.unwrap_or(0);
+    if !ruby.block_given() {
+        let args = (limit, kwargs!("batch_size" => batch_size));
+        return Ok(rb_self.enumeratorize("each_prime_slice", args).as_value());
+    }
+    let limit = checked;
+
+    // An exception or `break` in the block stops the sweep and is re-raised
+    let capacity = batch_size.min(matryoshka_demo_core::prime_pi_approx(limit) + 1);
+    let mut slice = Vec::with_capacity(capacity as usize);
+    let mut yielded = Ok(());
+    let _ = matryoshka_demo_core::for_each_prime(limit, |p| {
+        slice.push(p);
+        if slice.len() as u64 == batch_size {
+            let primes = ruby.ary_from_iter(slice.drain(..));
+            yielded = ruby.yield_value::<RArray, Value>(primes).map(|_| ());
+            if yielded.is_err() {
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    });
+    yielded?;
+    if !slice.is_empty() {
+        ruby.yield_value::<RArray, Value>(ruby.ary_from_vec(slice))?;
+    }
+    Ok(rb_self.as_value())
+}
+
+/// Primes `primes` sieves per batch, at first and at most; each batch
+/// doubles the last, so `first(10)` sieves little and a long run hands
+/// the GVL back rarely
+const PRIMES_BATCH: (usize, usize) = (64, 1 << 16);
+
+/// Yield every prime in ascending order to the block, with no upper bound;
+/// returns an Enumerator without a block, whose `lazy` chains pull only
+/// the primes they need
+/// Primes are sieved in batches with the GVL released and yielded one by
+/// one; the loop ends only through `break`, an exception or the end of u64
+/// Rust FFI wrapper for Ruby
+fn primes_native(ruby: &Ruby, rb_self: RModule) -> Result<Value, Error> {
+    if !ruby.block_given() {
+        return Ok(rb_self.enumeratorize("primes", ()).as_value());
+    }
+
+    let mut primes = Primes::new();
+    let mut batch = Vec::with_capacity(PRIMES_BATCH.0);
+    let mut size = PRIMES_BATCH.0;
+    loop {
+        without_gvl(|| batch.extend(primes.by_ref().take(size)));
+        if batch.is_empty() {
+            return Ok(rb_self.as_value());
+        }
+        for p in batch.drain(..) {
+            ruby.yield_value::<u64, Value>(p)?;
+        }
+        size = (size * 2).min(PRIMES_BATCH.1);
+    }
+}
+
+/// Read a Range argument as inclusive bounds and the token the call stops
+/// on, from the `cancel:` and `timeout:` keywords; the bounds are None if
+/// the range holds no non-negative number
+/// Exclusive ranges drop their end, a beginless range starts at 0 and an
+/// endless one stops at the `cap:` keyword, which also caps finite ranges
+/// Raises ArgumentError for non-Integer bounds and endless ranges without
+/// `cap:`, RangeError past u64
+fn range_args(
+    ruby: &Ruby,
+    args: &[Value],
+) -> Result<(Option<(u64, u64)>, Arc<CancelToken>), Error> {
+    type Options = (Option<Integer>, Option<Value>, Option<Value>);
+    let args = scan_args::<(Range,), (), (), (), RHash, ()>(args)?;
+    let (range,) = args.required;
+    let kwargs =
+        get_kwargs::<_, (), Options, ()>(args.keywords, &[], &["cap", "cancel", "timeout"])?;
+    let (cap, cancel, timeout) = kwargs.optional;
+    let bounds = range_bounds(ruby, range, cap)?;
+    Ok((bounds, call_token(ruby, cancel, timeout)?))
+}
+
+/// The inclusive bounds of `range` capped at `cap`, as `range_args` reads
+/// them
+fn range_bounds(
+    ruby: &Ruby,
+    range: Range,
+    cap: Option<Integer>,
+) -> Result<Option<(u64, u64)>, Error> {
+    let bound = |value: Value, name: &str| {
+        if value.is_nil() {
+            return Ok(None);
+        }
+        integer_like(ruby, value)?.map(Some).ok_or_else(|| {
+            Error::new(
+                ruby.exception_arg_error(),
+                format!("range {name} must be an Integer, got {}", value.inspect()),
+            )
+        })
+    };
+    let low = match bound(range.beg()?, "begin")? {
+        Some(low) => clamped_u64(ruby, low, "range begin")?,
+        None => 0,
+    };
+    let high = match bound(range.end()?, "end")? {
+        Some(end) if end < ruby.integer_from_u64(0) => return Ok(None),
+        Some(end) => {
+            let end = u64_from_integer(ruby, end, "range end")?;
+            match (range.excl(), end.checked_sub(1)) {
+                (false, _) => end,
+                (true, Some(high)) => high,
+                (true, None) => return Ok(None),
+            }
+        }
+        None if cap.is_some() => u64::MAX,
+        None => {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                "an endless range needs cap:",
+            ))
+        }
+    };
+    let high = match cap {
+        Some(cap) => high.min(clamped_u64(ruby, cap, "cap")?),
+        None => high,
+    };
+    Ok((low <= high).then_some((low, high)))
+}
+
+/// Collect the primes in a Range, e.g. `primes_in(10..1_000)`, sieving only
+/// that window; `cap:` bounds endless ranges, `cancel:` takes a CancelToken
+/// and `timeout:` seconds
+/// Publishes `matryoshka_demo.primes_in` when ActiveSupport is loaded
+/// Rust FFI wrapper for Ruby; raises ArgumentError for non-Integer bounds,
+/// endless ranges without `cap:`, ResourceLimit for windows wider than
+/// MAX_LIST_LIMIT, Timeout past `timeout:`
+fn primes_in_native(ruby: &Ruby, args: &[Value]) -> Result<Output<RArray>, Error> {
+    let (Some((low, high)), cancel) = range_args(ruby, args)? else {
+        return Ok(Output(ruby.ary_new()));
+    };
+    if high - low >= MAX_LIST_LIMIT {
+        return Err(error::resource_limit(
+            ruby,
+            format!("range must span at most {MAX_LIST_LIMIT} numbers, got {low}..{high}"),
+        ));
+    }
+
+    let payload = Payload {
+        limit: high,
+        algorithm: "segmented",
+    };
+    instrument(ruby, "primes_in", payload, || {
+        let primes = without_gvl_cancellable_by(ruby, &cancel, |cancel| {
+            matryoshka_demo_core::try_primes_in_with_cancel(low..=high, cancel)
+        })?;
+        Ok(Output(ruby.ary_from_vec(primes)))
+    })
+}
+
+/// Count the primes in a Range, e.g. `count_primes_in(10...1_000)`; `cap:`
+/// bounds endless ranges, `cancel:` takes a CancelToken and `timeout:`
+/// seconds
+/// Publishes `matryoshka_demo.count_primes_in` when ActiveSupport is loaded
+/// Rust FFI wrapper for Ruby; raises ArgumentError for non-Integer bounds,
+/// endless ranges without `cap:`, ResourceLimit for ends above
+/// MAX_COUNT_LIMIT, Timeout past `timeout:`
+fn count_primes_in_native(ruby: &Ruby, args: &[Value]) -> Result<u64, Error> {
+    let (Some((low, high)), cancel) = range_args(ruby, args)? else {
+        return Ok(0);
+    };
+    if high > MAX_COUNT_LIMIT {
+        return Err(error::resource_limit(
+            ruby,
+            format!("range must end at most at {MAX_COUNT_LIMIT}, got {high}"),
+        ));
+    }
+
+    let options = CountOptions {
+        segment_bytes: CONFIG.segment_bytes(),
+        ..CountOptions::default()
+    };
+    let payload = Payload {
+        limit: high,
+        algorithm: algorithm_name(options.algorithm.resolve(low, high)),
+    };
+    instrument(ruby, "count_primes_in", payload, || {
+        without_gvl_cancellable_by(ruby, &cancel, |cancel| {
+            matryoshka_demo_core::try_count_primes_with_options(low..=high, options, |_, _| {
+                cancel.observe()
+            })
+        })
+    })
+}
+
+/// Collect every twin prime pair `[p, p + 2]` with `p + 2 <= limit`
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits
+fn twin_primes_up_to_native(ruby: &Ruby, limit: Value) -> Result<Output<Vec<(u64, u64)>>, Error> {
+    let Some(limit) = domain_arg(ruby, limit, "limit", 0)? else {
+        return Ok(Output(Vec::new()));
+    };
+
+    Ok(Output(without_gvl(|| {
+        matryoshka_demo_core::twin_primes_up_to(limit)
+    })))
+}
+
+/// Collect every prime `p <= limit` with `2p + 1` prime as well
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits
+fn sophie_germain_primes_up_to_native(
+    ruby: &Ruby,
+    limit: Value,
+) -> Result<Output<Vec<u64>>, Error> {
+    let limit = domain_arg(ruby, limit, "limit", 0)?.unwrap_or(0);
+    if limit < 2 {
+        return Ok(Output(Vec::new()));
+    }
+
+    Ok(Output(without_gvl(|| {
+        matryoshka_demo_core::sophie_germain_primes_up_to(limit)
+    })))
+}
+
+/// Collect every prime up to and including `limit` congruent to `residue`
+/// modulo `modulus`, matched during the sieve scan
+/// A negative residue maps into [0, modulus) as Ruby's modulo does
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits or
+/// a modulus below 1, RangeError past u64 and ResourceLimit above
+/// MAX_LIST_LIMIT
+fn primes_mod_native(
+    ruby: &Ruby,
+    limit: Value,
+    modulus: Integer,
+    residue: Integer,
+) -> Result<Output<Vec<u64>>, Error> {
+    if modulus <= ruby.integer_from_u64(0) {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            format!("modulus must be positive, got {modulus}"),
+        ));
+    }
+    let modulus = u64_from_integer(ruby, modulus, "modulus")?;
+    let (negative, residue) = magnitude(ruby, residue, "residue")?;
+    let residue = residue % modulus;
+    let residue = if negative && residue != 0 {
+        modulus - residue
+    } else {
+        residue
+    };
+
+    let limit = domain_arg(ruby, limit, "limit", 0)?.unwrap_or(0);
+    if limit > MAX_LIST_LIMIT {
+        return Err(error::resource_limit(
+            ruby,
+            format!("limit must be at most {MAX_LIST_LIMIT}, got {limit}"),
+        ));
+    }
+
+    without_gvl(|| matryoshka_demo_core::primes_mod(limit, modulus, residue))
+        .map(Output)
+        .map_err(|e| error::core(ruby, e))
+}
+
+/// Find the first pair of consecutive primes up to `limit` with the largest gap
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits
+fn max_prime_gap_native(ruby: &Ruby, limit: Value) -> Result<Option<Output<(u64, u64)>>, Error> {
+    let Some(limit) = domain_arg(ruby, limit, "limit", 0)? else {
+        return Ok(None);
+    };
+
+    Ok(without_gvl(|| matryoshka_demo_core::max_prime_gap(limit)).map(Output))
+}
+
+/// Check whether `n` is prime: from the process-wide sieve when it covers
+/// `n`, deterministic Miller–Rabin otherwise, the 128-bit test past 2^64
+/// Rust FFI wrapper for Ruby; raises RangeError past 128 bits
+fn is_prime_native(ruby: &Ruby, n: Integer) -> Result<bool, Error> {
+    if n < ruby.integer_from_u64(2) {
+        return Ok(false);
+    }
+
+    match n.to_u64() {
+        Ok(n) => Ok(matryoshka_demo_core::is_prime_hybrid(n)),
+        Err(_) => Ok(matryoshka_demo_core::is_prime_u128(u128_from_integer(ruby, n)?)),
+    }
+}
+
+/// Check whether `n` is prime, accepting Bignums up to 128 bits
+/// (Baillie–PSW plus extra Miller–Rabin rounds)
 /// Rust FFI wrapper for Ruby
-fn nth_prime_native(n: i64) -> Option<i64> {
-    if n <= 0 {
-        return None;
+fn is_prime_u128_native(ruby: &Ruby, n: Integer) -> Result<bool, Error> {
+    if n < ruby.integer_from_u64(0) {
+        return Ok(false);
+    }
+
+    Ok(matryoshka_demo_core::is_prime_u128(u128_from_integer(ruby, n)?))
+}
+
+/// Find the smallest safe prime strictly greater than `n`
+/// Rust FFI wrapper for Ruby; nil if the answer passes u64, RangeError if `n` does
+fn next_safe_prime_native(ruby: &Ruby, n: Integer) -> Result<Option<u64>, Error> {
+    Ok(matryoshka_demo_core::next_safe_prime(clamped_u64(ruby, n, "n")?))
+}
+
+/// Pick a uniformly random prime with exactly `bits:` bits from the OS RNG
+/// Rust FFI wrapper for Ruby; raises ArgumentError unless 2 <= bits <= 64
+fn random_prime_native(ruby: &Ruby, args: &[Value]) -> Result<u64, Error> {
+    let args = scan_args::<(), (), (), (), RHash, ()>(args)?;
+    let kwargs = get_kwargs::<_, (Integer,), (), ()>(args.keywords, &["bits"], &[])?;
+    let (bits,) = kwargs.required;
+    let Some(bits) = bits.to_u64().ok().filter(|bits| (2..=64).contains(bits)) else {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            format!("bits must be between 2 and 64, got {bits}"),
+        ));
+    };
+
+    // Every range [2^(bits - 1), 2^bits - 1] holds a prime (Bertrand)
+    let low = 1u64 << (bits - 1);
+    let high = u64::MAX >> (64 - bits);
+    Ok(matryoshka_demo_core::random_prime_in_range(low, high, &mut OsRng)
+        .expect("Bertrand's postulate guarantees a prime"))
+}
+
+/// Split an even `n >= 4` into two primes `[p, n - p]`, `p` as small as possible
+/// Rust FFI wrapper for Ruby; odd or small `n` give nil, raises RangeError
+/// past u64
+fn goldbach_pair_native(ruby: &Ruby, n: Integer) -> Result<Option<Output<(u64, u64)>>, Error> {
+    let n = clamped_u64(ruby, n, "n")?;
+    if n < 4 {
+        return Ok(None);
+    }
+
+    Ok(matryoshka_demo_core::goldbach_pair(n).map(Output))
+}
+
+/// Whether `n` is negative and the `(prime, exponent)` pairs of `|n|`
+/// Raises ZeroDivisionError for 0, RangeError past u64
+fn prime_division(ruby: &Ruby, n: Integer) -> Result<(bool, Vec<(u64, u32)>), Error> {
+    let (negative, n) = magnitude(ruby, n, "n")?;
+    if n == 0 {
+        return Err(Error::new(
+            ruby.exception_zero_div_error(),
+            "0 has no prime factorization",
+        ));
+    }
+
+    Ok((negative, matryoshka_demo_core::factorize(n)))
+}
+
+/// Factor `n` into a Hash of `{prime => exponent}` in ascending order,
+/// following `Prime.prime_division`: negatives add `-1 => 1` first, 1 gives
+/// an empty Hash and 0 raises ZeroDivisionError
+/// Rust FFI wrapper for Ruby; raises RangeError past u64
+fn factorize_native(ruby: &Ruby, n: Integer) -> Result<Output<RHash>, Error> {
+    let (negative, factors) = prime_division(ruby, n)?;
+    let hash = ruby.hash_new_capa(factors.len() + negative as usize);
+    if negative {
+        hash.aset(-1, 1)?;
+    }
+    for (p, exponent) in factors {
+        hash.aset(p, exponent)?;
+    }
+    Ok(Output(hash))
+}
+
+/// Check whether the Mersenne number 2^p - 1 is prime (Lucas–Lehmer)
+/// Rust FFI wrapper for Ruby; raises ArgumentError above exponent 127
+fn is_mersenne_prime_native(ruby: &Ruby, p: Integer) -> Result<bool, Error> {
+    let max = matryoshka_demo_core::MAX_MERSENNE_EXPONENT;
+    if p < ruby.integer_from_u64(2) {
+        return Ok(false);
+    }
+    if p > ruby.integer_from_u64(max.into()) {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            format!("exponent must be at most {max}, got {p}"),
+        ));
     }
 
-    matryoshka_demo_core::nth_prime(n as usize).map(|p| p as i64)
+    Ok(matryoshka_demo_core::is_mersenne_prime(u32_from_integer(ruby, p, "p")?))
+}
+
+/// Find the inverse of `a` modulo `m`, or nil if they share a factor
+/// Rust FFI wrapper for Ruby; raises ArgumentError for a non-positive modulus,
+/// RangeError past u64
+fn inv_mod_native(ruby: &Ruby, a: Integer, m: Integer) -> Result<Option<u64>, Error> {
+    if m <= ruby.integer_from_u64(0) {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            format!("modulus must be positive, got {m}"),
+        ));
+    }
+    let m = u64_from_integer(ruby, m, "modulus")?;
+    let (negative, a) = magnitude(ruby, a, "a")?;
+
+    // Ruby's modulo keeps the sign of m, so negative a maps into [0, m)
+    let r = a % m;
+    let r = if negative && r != 0 { m - r } else { r };
+    Ok(matryoshka_demo_core::inv_mod(r, m))
+}
+
+/// Least common multiple of `|a|` and `|b|`
+/// Rust FFI wrapper for Ruby; results past u64 become a Bignum, arguments
+/// past u64 raise RangeError
+fn lcm_native(ruby: &Ruby, a: Integer, b: Integer) -> Result<Integer, Error> {
+    let (_, a) = magnitude(ruby, a, "a")?;
+    let (_, b) = magnitude(ruby, b, "b")?;
+    Ok(match matryoshka_demo_core::lcm(a, b) {
+        Some(lcm) => ruby.integer_from_u64(lcm),
+        None => {
+            let g = matryoshka_demo_core::gcd(a, b);
+            integer_from_u128(ruby, (a / g) as u128 * b as u128)
+        }
+    })
+}
+
+/// Extended Euclid: `[g, x, y]` with `a * x + b * y == g` and g = gcd(a, b)
+/// Rust FFI wrapper for Ruby; raises RangeError past u64
+fn ext_gcd_native(ruby: &Ruby, a: Integer, b: Integer) -> Result<Output<(u64, i64, i64)>, Error> {
+    let (a_negative, a) = magnitude(ruby, a, "a")?;
+    let (b_negative, b) = magnitude(ruby, b, "b")?;
+    let (g, x, y) = matryoshka_demo_core::ext_gcd(a, b);
+    // |a| * x = a * (-x) for negative a; the bound |x| <= |b| / 2g keeps it in range
+    let x = if a_negative { -x } else { x };
+    let y = if b_negative { -y } else { y };
+    Ok(Output((g, x, y)))
+}
+
+/// Versions, toolchain, features, target, namespace and profile of this
+/// build as a frozen Hash, recorded at compile time for bug reports
+/// Rust FFI wrapper for Ruby
+fn build_info_native(ruby: &Ruby) -> Result<RHash, Error> {
+    let frozen_str = |s: &str| {
+        let s = ruby.str_new(s);
+        s.freeze();
+        s
+    };
+    let frozen_ary = |names: Vec<&str>| {
+        let ary = ruby.ary_from_iter(names.into_iter().map(frozen_str));
+        ary.freeze();
+        ary
+    };
+    let features = [
+        ("log", cfg!(feature = "log")),
+        ("rayon", cfg!(feature = "rayon")),
+        ("simd", cfg!(feature = "simd")),
+    ];
+    let features = features
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect();
+
+    let info = ruby.hash_new();
+    info.aset(ruby.to_symbol("version"), frozen_str(env!("CARGO_PKG_VERSION")))?;
+    info.aset(ruby.to_symbol("core_version"), frozen_str(matryoshka_demo_core::VERSION))?;
+    info.aset(ruby.to_symbol("rustc"), frozen_str(env!("MATRYOSHKA_RUSTC_VERSION")))?;
+    info.aset(ruby.to_symbol("features"), frozen_ary(features))?;
+    info.aset(
+        ruby.to_symbol("core_features"),
+        frozen_ary(matryoshka_demo_core::features().collect()),
+    )?;
+    info.aset(ruby.to_symbol("target"), frozen_str(env!("MATRYOSHKA_TARGET")))?;
+    info.aset(ruby.to_symbol("namespace"), frozen_str(namespace::NAMESPACE))?;
+    info.aset(ruby.to_symbol("profile"), frozen_str(env!("MATRYOSHKA_PROFILE")))?;
+    info.aset(ruby.to_symbol("opt_level"), frozen_str(env!("MATRYOSHKA_OPT_LEVEL")))?;
+    info.freeze();
+    Ok(info)
+}
+
+/// Whether sieves presieve in SIMD lanes: built with the simd feature for
+/// x86_64 or aarch64, which fall back to scalar code nowhere else
+/// Rust FFI wrapper for Ruby
+fn is_simd_native() -> bool {
+    matryoshka_demo_core::SIMD_PRESIEVE
+}
+
+/// Whether `threads:` can spread a call over several cores: built with the
+/// rayon feature and running on more than one CPU
+/// Rust FFI wrapper for Ruby
+fn is_parallel_native() -> bool {
+    cfg!(feature = "rayon")
+        && std::thread::available_parallelism().is_ok_and(|cpus| cpus.get() > 1)
+}
+
+/// Whether `count_primes` answers small limits from the process-wide sieve,
+/// i.e. `config.cache_limit` is above 0
+/// Rust FFI wrapper for Ruby
+fn is_cache_enabled_native() -> bool {
+    CONFIG.cache_limit() > 0
 }
 
 #[magnus::init]
 fn init(ruby: &Ruby) -> Result<(), Error> {
-    let module = ruby.define_module("MatryoshkaDemoNative")?;
+    // Every method defined below is Ractor-safe: the only process-wide state
+    // is CONFIG (atomics) and the core's sieve cache (behind a RwLock),
+    // and no Ruby object is kept between calls; see concurrency.rs
+    // SAFETY: only sets a flag Ruby reads while this extension's methods
+    // are defined
+    unsafe { rb_sys::rb_ext_ractor_safe(true) };
 
-    module.define_module_function("count_primes", function!(count_primes_native, 1))?;
+    let module = namespace::module(ruby)?;
+    error::define(ruby)?;
+
+    module.define_module_function("build_info", function!(build_info_native, 0))?;
+    module.define_module_function("simd?", function!(is_simd_native, 0))?;
+    module.define_module_function("parallel?", function!(is_parallel_native, 0))?;
+    module.define_module_function("cache_enabled?", function!(is_cache_enabled_native, 0))?;
+    module.define_module_function("lenient=", function!(set_lenient_native, 1))?;
+    module.define_module_function("lenient?", function!(is_lenient_native, 0))?;
+
+    module.define_module_function("count_primes", function!(count_primes_native, -1))?;
+    module.define_module_function("prime_pi_approx", function!(prime_pi_approx_native, 1))?;
+    module.define_module_function("clear_prime_cache", function!(clear_prime_cache_native, 0))?;
+    module.define_module_function("prime_bitmap", function!(prime_bitmap_native, 1))?;
     module.define_module_function("nth_prime", function!(nth_prime_native, 1))?;
+    module.define_module_function("nth_prime_after", function!(nth_prime_after_native, 2))?;
+    module.define_module_function("sum_of_primes", function!(sum_of_primes_native, 1))?;
+    module.define_module_function("primes_up_to", function!(primes_up_to_native, 1))?;
+    module.define_module_function("primes_packed", function!(primes_packed_native, -1))?;
+    module.define_module_function("write_primes", function!(write_primes_native, -1))?;
+    module.define_module_function("each_prime", method!(each_prime_native, 1))?;
+    module.define_module_function("each_prime_slice", method!(each_prime_slice_native, -1))?;
+    module.define_module_function("primes", method!(primes_native, 0))?;
+    module.define_module_function("primes_in", function!(primes_in_native, -1))?;
+    module.define_module_function("count_primes_in", function!(count_primes_in_native, -1))?;
+    module.define_module_function("twin_primes_up_to", function!(twin_primes_up_to_native, 1))?;
+    module.define_module_function(
+        "sophie_germain_primes_up_to",
+        function!(sophie_germain_primes_up_to_native, 1),
+    )?;
+    module.define_module_function("primes_mod", function!(primes_mod_native, 3))?;
+    module.define_module_function("max_prime_gap", function!(max_prime_gap_native, 1))?;
+    module.define_module_function("prime?", function!(is_prime_native, 1))?;
+    module.define_module_function("prime_u128?", function!(is_prime_u128_native, 1))?;
+    module.define_module_function("mersenne_prime?", function!(is_mersenne_prime_native, 1))?;
+    module.define_module_function("next_safe_prime", function!(next_safe_prime_native, 1))?;
+    module.define_module_function("random_prime", function!(random_prime_native, -1))?;
+    module.define_module_function("factorize", function!(factorize_native, 1))?;
+    module.define_module_function("goldbach_pair", function!(goldbach_pair_native, 1))?;
+    module.define_module_function("inv_mod", function!(inv_mod_native, 2))?;
+    module.define_module_function("lcm", function!(lcm_native, 2))?;
+    module.define_module_function("ext_gcd", function!(ext_gcd_native, 2))?;
+
+    bang::define(module)?;
+    cancel::define(ruby, module)?;
+    batch::define(module)?;
+    config::define(ruby, module)?;
+    concurrency::define(module)?;
+    sieve::define(ruby, module)?;
+    generator::define(ruby, module)?;
+    integer::define(module)?;
+    intmath::define(module)?;
+    memory::define(module)?;
+    #[cfg(unix)]
+    nonblocking::define(module)?;
+    stats::define(ruby, module)?;
+    #[cfg(feature = "log")]
+    logger::define(module)?;
 
     Ok(())
 }
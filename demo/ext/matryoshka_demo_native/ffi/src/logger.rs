@@ -0,0 +1,175 @@
+//! Rust `log` records forwarded to Ruby (the `log` feature)
+//!
+//! Records above `config.log_level` are dropped where they are made; the
+//! rest go to stderr until `MatryoshkaDemoNative.logger = Logger.new(...)`
+//! installs a Ruby Logger. From then on they are queued, as they may come
+//! from a pool worker or a thread without the GVL, and handed to
+//! `logger.add` as soon as a native call has the GVL back in the main
+//! Ractor. `tracing` events reach the same bridge through tracing's own
+//! `log` feature.
+
+use std::collections::VecDeque;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use magnus::prelude::*;
+use magnus::{function, Error, RModule, Ruby, Symbol, Value};
+use parking_lot::Mutex;
+
+use crate::{in_main_ractor, namespace};
+
+/// Most records queued for the Ruby logger; the oldest are dropped first
+const MAX_QUEUED: usize = 1024;
+
+/// Level `config.log_level` starts at
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Warn;
+
+/// Whether `logger=` installed a Ruby logger
+static FORWARDING: AtomicBool = AtomicBool::new(false);
+
+/// Records waiting for the GVL, as Logger severity, target and message
+static QUEUE: Mutex<VecDeque<(u8, String, String)>> = Mutex::new(VecDeque::new());
+
+/// The `log` logger of this extension
+struct Bridge;
+
+static BRIDGE: Bridge = Bridge;
+
+impl Log for Bridge {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if !FORWARDING.load(Ordering::Relaxed) {
+            eprintln!(
+                "[matryoshka_demo] {} {}: {}",
+                record.level(),
+                record.target(),
+                record.args()
+            );
+            return;
+        }
+
+        let mut queue = QUEUE.lock();
+        if queue.len() == MAX_QUEUED {
+            queue.pop_front();
+        }
+        queue.push_back((
+            severity(record.level()),
+            record.target().to_owned(),
+            record.args().to_string(),
+        ));
+    }
+
+    fn flush(&self) {}
+}
+
+/// The Ruby Logger severity for `level`: Logger::DEBUG (0) to ERROR (3);
+/// Logger has no trace level, so trace records log as debug
+fn severity(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 2,
+        Level::Info => 1,
+        Level::Debug | Level::Trace => 0,
+    }
+}
+
+/// Hand the queued records to the Ruby logger; call with the GVL held
+/// A logger that raises loses the records still queued, so logging never
+/// fails the call that flushes
+pub(crate) fn flush() {
+    if !FORWARDING.load(Ordering::Relaxed) {
+        return;
+    }
+    let Ok(ruby) = Ruby::get() else {
+        return;
+    };
+    if !in_main_ractor(&ruby).unwrap_or(false) {
+        return;
+    }
+
+    // Taken first, so records the logger itself causes wait for next time
+    let records = mem::take(&mut *QUEUE.lock());
+    if records.is_empty() {
+        return;
+    }
+    let Ok(logger) = ruby_logger(&ruby) else {
+        return;
+    };
+    for (severity, target, message) in records {
+        if logger
+            .funcall::<_, _, Value>("add", (severity, message, target))
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// The installed Ruby logger, kept in an instance variable of the module
+/// so the GC sees it
+fn ruby_logger(ruby: &Ruby) -> Result<Value, Error> {
+    namespace::module(ruby)?.ivar_get("@logger")
+}
+
+/// The Ruby logger records are forwarded to, or nil for stderr
+/// Rust FFI wrapper for Ruby
+fn logger_native(ruby: &Ruby) -> Result<Value, Error> {
+    ruby_logger(ruby)
+}
+
+/// Forward records to `logger`, which must respond to `add` as a Logger
+/// does; nil sends them to stderr again
+/// Rust FFI wrapper for Ruby; raises ArgumentError for objects without
+/// `add`
+fn set_logger_native(ruby: &Ruby, logger: Value) -> Result<Value, Error> {
+    if !logger.is_nil() && !logger.respond_to("add", false)? {
+        return Err(Error::new(
+            ruby.exception_arg_error(),
+            format!("logger must respond to add, got {}", logger.inspect()),
+        ));
+    }
+
+    namespace::module(ruby)?.ivar_set("@logger", logger)?;
+    FORWARDING.store(!logger.is_nil(), Ordering::Relaxed);
+    Ok(logger)
+}
+
+/// The level `config.log_level` reports: :off, :error, :warn, :info,
+/// :debug or :trace
+pub(crate) fn level(ruby: &Ruby) -> Symbol {
+    ruby.to_symbol(log::max_level().as_str().to_ascii_lowercase())
+}
+
+/// Drop records above `level` from now on
+/// Raises ArgumentError for anything but the levels `level` reports
+pub(crate) fn set_level(ruby: &Ruby, level: Symbol) -> Result<(), Error> {
+    let name = level.name()?;
+    let filter = name.parse::<LevelFilter>().map_err(|_| {
+        Error::new(
+            ruby.exception_arg_error(),
+            format!("log_level must be :off, :error, :warn, :info, :debug or :trace, got :{name}"),
+        )
+    })?;
+    log::set_max_level(filter);
+    Ok(())
+}
+
+/// Install the bridge and define `MatryoshkaDemoNative.logger` and
+/// `logger=` under `module`
+pub(crate) fn define(module: RModule) -> Result<(), Error> {
+    // Only fails if another logger was installed first, which then keeps
+    // the records
+    if log::set_logger(&BRIDGE).is_ok() {
+        log::set_max_level(DEFAULT_LEVEL);
+    }
+    module.define_module_function("logger", function!(logger_native, 0))?;
+    module.define_module_function("logger=", function!(set_logger_native, 1))?;
+    Ok(())
+}
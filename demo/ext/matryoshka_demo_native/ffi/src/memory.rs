@@ -0,0 +1,121 @@
+//! Telling Ruby's GC, and `MatryoshkaDemoNative.native_memory_stats`,
+//! about memory held by native code
+//!
+//! A sieve's bitset is allocated by Rust, so Ruby's malloc accounting never
+//! sees it and a process can grow by hundreds of megabytes without the GC
+//! feeling any pressure. Every allocation or release that outlives a call
+//! is reported through `rb_gc_adjust_memory_usage` instead.
+//!
+//! For telling RSS growth apart, every Rust allocation also goes through
+//! the core's CountingAlloc, and the bytes held by Sieve objects, by the
+//! process-wide sieve and as stacks of dedicated thread pools are counted
+//! on their own; `native_memory_stats` reports each as current and peak.
+
+use std::alloc::System;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use magnus::prelude::*;
+use magnus::{function, Error, RHash, RModule, Ruby};
+use matryoshka_demo_core::{CountingAlloc, MemoryCounter};
+
+#[global_allocator]
+static ALLOCATOR: CountingAlloc<System> = CountingAlloc::new(System);
+
+/// Bitsets of live Sieve objects
+static SIEVES: MemoryCounter = MemoryCounter::new();
+
+/// The process-wide sieve
+static CACHE: MemoryCounter = MemoryCounter::new();
+
+/// Stacks reserved by the threads of dedicated pools
+static THREAD_POOLS: MemoryCounter = MemoryCounter::new();
+
+/// Size of the process-wide sieve as last reported to the GC
+static REPORTED_CACHE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Report that native code holds `delta` more bytes (fewer if negative)
+/// Must be called with the GVL held; safe inside a dfree callback
+fn adjust(delta: isize) {
+    if delta != 0 {
+        // SAFETY: only adjusts the GC's malloc counters, never allocates
+        // or starts a collection
+        unsafe { rb_sys::rb_gc_adjust_memory_usage(delta as _) };
+    }
+}
+
+/// Report that Sieve objects hold `delta` more bytes (fewer if negative)
+/// Must be called with the GVL held; safe inside a dfree callback
+pub(crate) fn adjust_sieves(delta: isize) {
+    count(&SIEVES, delta);
+    adjust(delta);
+}
+
+/// Count a dedicated pool thread reserving `stack_bytes` of stack
+/// Called by the pool thread itself as it starts
+pub(crate) fn pool_thread_started(stack_bytes: usize) {
+    THREAD_POOLS.add(stack_bytes);
+}
+
+/// Count a dedicated pool thread releasing `stack_bytes` of stack
+/// Called by the pool thread itself as it exits
+pub(crate) fn pool_thread_exited(stack_bytes: usize) {
+    THREAD_POOLS.sub(stack_bytes);
+}
+
+/// Add `delta` to `counter`, subtracting when it is negative
+fn count(counter: &MemoryCounter, delta: isize) {
+    if delta >= 0 {
+        counter.add(delta as usize);
+    } else {
+        counter.sub(delta.unsigned_abs());
+    }
+}
+
+/// Report the process-wide sieve's current size, after a call that may
+/// have grown or freed it
+/// Swapping the reported size keeps concurrent callers from counting the
+/// same growth twice
+pub(crate) fn sync_cache() {
+    let now = matryoshka_demo_core::cached_memory_usage();
+    let before = REPORTED_CACHE_BYTES.swap(now, Ordering::Relaxed);
+    #[cfg(feature = "log")]
+    if now != before {
+        log::debug!("prime cache resized from {before} to {now} bytes");
+    }
+    count(&CACHE, now as isize - before as isize);
+    adjust(now as isize - before as isize);
+}
+
+/// Bytes held by native code, as `{heap:, sieves:, cache:, thread_pools:}`
+/// each a Hash of `{current:, peak:}`; heap covers every Rust allocation,
+/// the others are the parts held by Sieve objects, by the process-wide
+/// sieve and as the stacks of `threads:` pools (not rayon's global pool)
+/// Rust FFI wrapper for Ruby
+fn native_memory_stats_native(ruby: &Ruby) -> Result<RHash, Error> {
+    sync_cache();
+    let counters = [
+        ("heap", ALLOCATOR.counter()),
+        ("sieves", &SIEVES),
+        ("cache", &CACHE),
+        ("thread_pools", &THREAD_POOLS),
+    ];
+    let stats = ruby.hash_new();
+    for (name, counter) in counters {
+        let entry = ruby.hash_new();
+        entry.aset(ruby.to_symbol("current"), counter.current())?;
+        entry.aset(ruby.to_symbol("peak"), counter.peak())?;
+        entry.freeze();
+        stats.aset(ruby.to_symbol(name), entry)?;
+    }
+    stats.freeze();
+    Ok(stats)
+}
+
+/// Define `MatryoshkaDemoNative.native_memory_stats` under `module`
+pub(crate) fn define(module: RModule) -> Result<(), Error> {
+    module.define_module_function(
+        "native_memory_stats",
+        function!(native_memory_stats_native, 0),
+    )?;
+    Ok(())
+}
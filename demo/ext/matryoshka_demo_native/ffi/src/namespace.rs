@@ -0,0 +1,63 @@
+//! The Ruby module the extension defines everything under
+//!
+//! `MatryoshkaDemoNative` unless the build sets `MATRYOSHKA_NAMESPACE`, as
+//! in `MATRYOSHKA_NAMESPACE=MyGem::Native rake compile`, so a gem can embed
+//! this extension under its own module tree; enclosing modules are defined
+//! as needed. The name is fixed at compile time because the wrapped classes
+//! look themselves up by it, which is also why they use `wrap!` here rather
+//! than magnus's `wrap` attribute, whose class must be a literal.
+
+use magnus::{Error, Module, RModule, Ruby};
+
+/// Constant path of the namespace, such as `MyGem::Native`
+pub(crate) const NAMESPACE: &str = env!("MATRYOSHKA_NAMESPACE");
+
+/// Constant path of `$name` inside the namespace, as a `&'static str`
+macro_rules! qualified {
+    ($name:literal) => {
+        concat!(env!("MATRYOSHKA_NAMESPACE"), "::", $name)
+    };
+}
+pub(crate) use qualified;
+
+/// Implement TypedData for `$type`, wrapped as the namespace's `$class`
+/// As magnus's `wrap`, the object is freed immediately and reports its
+/// size; `mark` and `compact` enable the matching DataTypeFunctions
+macro_rules! wrap {
+    ($type:ty, $class:literal $(, $flag:ident)*) => {
+        unsafe impl magnus::TypedData for $type {
+            fn class(ruby: &magnus::Ruby) -> magnus::RClass {
+                use magnus::prelude::*;
+                static CLASS: magnus::value::Lazy<magnus::RClass> =
+                    magnus::value::Lazy::new(|ruby| {
+                        let class: magnus::RClass = ruby
+                            .class_object()
+                            .funcall("const_get", ($crate::namespace::qualified!($class),))
+                            .unwrap();
+                        class.undef_default_alloc_func();
+                        class
+                    });
+                ruby.get_inner(&CLASS)
+            }
+
+            fn data_type() -> &'static magnus::DataType {
+                static DATA_TYPE: magnus::DataType = magnus::data_type_builder!($type, $class)
+                    .free_immediately()
+                    .size()
+                    $(.$flag())*
+                    .build();
+                &DATA_TYPE
+            }
+        }
+    };
+}
+pub(crate) use wrap;
+
+/// The namespace module, defining it and any module enclosing it first
+pub(crate) fn module(ruby: &Ruby) -> Result<RModule, Error> {
+    let mut names = NAMESPACE.split("::");
+    let outermost = names.next().expect("split yields at least one name");
+    names.try_fold(ruby.define_module(outermost)?, |module, name| {
+        module.define_module(name)
+    })
+}
@@ -0,0 +1,106 @@
+//! `MatryoshkaDemoNative.count_primes_nonblocking`: counting off the Ruby thread
+//!
+//! Releasing the GVL lets other threads run, but the calling thread still
+//! waits, and under a Fiber scheduler (Async, Falcon) that thread is the
+//! reactor every fiber runs on. Here the count runs on a Rust thread of its
+//! own while the caller reads from a socket the worker closes once done.
+//! Ruby hands that read to the scheduler's `io_wait`, so only the calling
+//! fiber waits; without a scheduler the thread waits with the GVL released.
+//!
+//! Unix only, as the wakeup is a UnixStream pair.
+
+use std::os::fd::IntoRawFd;
+use std::os::unix::net::UnixStream;
+use std::panic;
+use std::sync::Arc;
+use std::thread;
+
+use magnus::prelude::*;
+use magnus::scan_args::{get_kwargs, scan_args};
+use magnus::{function, Error, RHash, RModule, Ruby, Value};
+use matryoshka_demo_core::CountOptions;
+
+use crate::cancel::call_token;
+use crate::{call_error, domain_arg, error, memory, CONFIG, MAX_COUNT_LIMIT};
+
+/// Count prime numbers up to and including `limit` on a background thread,
+/// blocking only the calling fiber under a Fiber scheduler
+/// Keywords: `cancel:` a CancelToken and `timeout:` in seconds; limits
+/// within `config.cache_limit` are answered from the process-wide sieve
+/// An exception raised into the waiting fiber (Thread#raise, a scheduler's
+/// timeout) cancels the count as well
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits,
+/// ResourceLimit above MAX_COUNT_LIMIT or when no thread can be started,
+/// Cancelled once `cancel:` is set and Timeout past `timeout:`
+fn count_primes_nonblocking_native(ruby: &Ruby, args: &[Value]) -> Result<u64, Error> {
+    let args = scan_args::<(Value,), (), (), (), RHash, ()>(args)?;
+    let (limit,) = args.required;
+    let kwargs = get_kwargs::<_, (), (Option<Value>, Option<Value>), ()>(
+        args.keywords,
+        &[],
+        &["cancel", "timeout"],
+    )?;
+    let (cancel, timeout) = kwargs.optional;
+    let cancel = call_token(ruby, cancel, timeout)?;
+
+    let Some(limit) = domain_arg(ruby, limit, "limit", 0)? else {
+        return Ok(0);
+    };
+    if limit > MAX_COUNT_LIMIT {
+        return Err(error::resource_limit(
+            ruby,
+            format!("limit must be at most {MAX_COUNT_LIMIT}, got {limit}"),
+        ));
+    }
+
+    let (reader, writer) = UnixStream::pair()
+        .and_then(|(reader, writer)| reader.set_nonblocking(true).map(|()| (reader, writer)))
+        .map_err(|e| error::resource_limit(ruby, format!("cannot open a wakeup socket: {e}")))?;
+    let options = CountOptions {
+        segment_bytes: CONFIG.segment_bytes(),
+        ..CountOptions::default()
+    };
+    let cached = limit <= CONFIG.cache_limit();
+    let token = Arc::clone(&cancel);
+    let worker = thread::Builder::new()
+        .name("matryoshka-count".to_owned())
+        .spawn(move || {
+            let count = if cached {
+                matryoshka_demo_core::cached_count_primes(limit)
+            } else {
+                matryoshka_demo_core::try_count_primes_with_options(..=limit, options, |_, _| {
+                    token.observe()
+                })
+            };
+            // Closing our end is the wakeup; a panic closes it while unwinding
+            drop(writer);
+            count
+        })
+        .map_err(|e| error::resource_limit(ruby, format!("cannot start a thread: {e}")))?;
+
+    // The IO owns the descriptor from here on and closes it
+    let io: Value = ruby.class_io().funcall("for_fd", (reader.into_raw_fd(),))?;
+    let waited = io.funcall::<_, _, Value>("read", ());
+    let _ = io.funcall::<_, _, Value>("close", ());
+    if let Err(error) = waited {
+        // The worker stops at its next check and exits on its own
+        cancel.cancel();
+        return Err(error);
+    }
+
+    // The worker has returned by now, so this only reaps the thread
+    let count = worker
+        .join()
+        .unwrap_or_else(|payload| panic::resume_unwind(payload));
+    memory::sync_cache();
+    count.map_err(|error| call_error(ruby, &cancel, error))
+}
+
+/// Define `MatryoshkaDemoNative.count_primes_nonblocking` under `module`
+pub(crate) fn define(module: RModule) -> Result<(), Error> {
+    module.define_module_function(
+        "count_primes_nonblocking",
+        function!(count_primes_nonblocking_native, -1),
+    )?;
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! `config.freeze_results`: results that can be shared and memoized
+//!
+//! With the setting on, each Array, Hash and String a computation returns
+//! is frozen before Ruby sees it, along with the Arrays and Hashes inside
+//! it, so the result is Ractor-shareable as it stands and no caller can
+//! change a memoized copy under another. A returned String of at most
+//! `MAX_INTERNED_BYTES` is interned as well, so equal results share one
+//! object; larger ones, such as packed primes or a bitmap, are only frozen,
+//! as the table would hold every one for good. Computations opt in by
+//! returning an `Output`; wrapped objects such as a Sieve are never frozen,
+//! and with the setting off (the default) results are returned as before.
+
+use magnus::prelude::*;
+use magnus::r_hash::ForEach;
+use magnus::{IntoValue, RArray, RHash, RString, Ruby, Value};
+
+use crate::CONFIG;
+
+/// Longest String result interned rather than just frozen
+const MAX_INTERNED_BYTES: usize = 64;
+
+/// A computation's result, frozen on the way out when
+/// `config.freeze_results` is set
+pub(crate) struct Output<T>(pub(crate) T);
+
+impl<T: IntoValue> IntoValue for Output<T> {
+    fn into_value_with(self, ruby: &Ruby) -> Value {
+        let value = self.0.into_value_with(ruby);
+        if !CONFIG.freeze_results() {
+            return value;
+        }
+        if let Some(string) = RString::from_value(value)
+            && string.len() <= MAX_INTERNED_BYTES
+        {
+            return string.to_interned_str().as_value();
+        }
+        freeze(value);
+        value
+    }
+}
+
+/// Freeze `value` and, for an Array or Hash, everything it holds
+/// Frozen objects are left as they are, as they were frozen whole
+fn freeze(value: Value) {
+    if value.is_frozen() {
+        return;
+    }
+    if let Some(array) = RArray::from_value(value) {
+        for i in 0..array.len() {
+            if let Ok(element) = array.entry::<Value>(i as isize) {
+                freeze(element);
+            }
+        }
+    } else if let Some(hash) = RHash::from_value(value) {
+        // Only fails if the block does, and this one never does
+        let _ = hash.foreach(|key: Value, value: Value| {
+            freeze(key);
+            freeze(value);
+            Ok(ForEach::Continue)
+        });
+    }
+    value.freeze();
+}
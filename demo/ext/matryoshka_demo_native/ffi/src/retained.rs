@@ -0,0 +1,69 @@
+//! Ruby objects kept inside wrapped native objects
+//!
+//! A VALUE stored in a TypedData struct is invisible to the GC unless the
+//! type's mark function reports it, and under compaction (`GC.compact` or
+//! `GC.auto_compact = true`) the object may move, leaving the stored
+//! address dangling unless the compact function rewrites it. `Retained`
+//! does both: a wrapped type holding one derives TypedData with `mark` and
+//! `compact` and forwards its DataTypeFunctions to `Retained::mark` and
+//! `Retained::compact`, so the object stays movable rather than pinned.
+
+use magnus::gc::{Compactor, Marker};
+use magnus::value::{Opaque, ReprValue};
+use magnus::{Error, Ruby};
+use parking_lot::{Mutex, MutexGuard};
+
+/// A Ruby object stored once and then kept for the owner's lifetime
+pub(crate) struct Retained<T>(Mutex<Option<Opaque<T>>>);
+
+impl<T: ReprValue> Retained<T> {
+    /// Nothing stored yet
+    pub(crate) const fn new() -> Self {
+        Self(Mutex::new(None))
+    }
+
+    fn slot(&self) -> MutexGuard<'_, Option<Opaque<T>>> {
+        self.0.lock()
+    }
+
+    /// The stored object, or the one `init` builds, stored for next time
+    /// `init` runs without the lock, as allocating may start a GC that
+    /// marks this; if another thread stored an object meanwhile, that one
+    /// is kept and returned
+    pub(crate) fn get_or_try_init(
+        &self,
+        ruby: &Ruby,
+        init: impl FnOnce() -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        if let Some(value) = *self.slot() {
+            return Ok(ruby.get_inner(value));
+        }
+
+        let value = Opaque::from(init()?);
+        Ok(ruby.get_inner(*self.slot().get_or_insert(value)))
+    }
+
+    /// The stored object, if one was stored
+    pub(crate) fn get(&self, ruby: &Ruby) -> Option<T> {
+        self.slot().map(|value| ruby.get_inner(value))
+    }
+
+    /// Forget the stored object, so the next call builds a new one
+    pub(crate) fn clear(&self) {
+        *self.slot() = None;
+    }
+
+    /// Report the stored object to the GC, leaving it free to move
+    pub(crate) fn mark(&self, marker: &Marker) {
+        if let Some(value) = *self.slot() {
+            marker.mark_movable(value);
+        }
+    }
+
+    /// Follow the stored object to where compaction moved it
+    pub(crate) fn compact(&self, compactor: &Compactor) {
+        if let Some(value) = self.slot().as_mut() {
+            *value = compactor.location(*value);
+        }
+    }
+}
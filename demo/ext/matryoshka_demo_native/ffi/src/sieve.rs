@@ -0,0 +1,297 @@
+//! `MatryoshkaDemoNative::Sieve`: a core sieve kept alive between calls
+//!
+//! The module functions sieve afresh (or share one process-wide cache);
+//! a Sieve object owns its bitset, so repeated queries against the same
+//! range cost a lookup each. Ruby's GC frees the bitset with the object.
+//!
+//! Sieve has an allocator and `initialize`, so subclasses, `allocate` and
+//! `dup` work as for any Ruby class; an allocated Sieve covers no numbers
+//! until `initialize` sieves. `dup` and `clone` copy the bitset, so the
+//! copy is independent of the original, and a frozen Sieve (still free to
+//! answer queries) cannot be reinitialized.
+//!
+//! A Sieve is Enumerable over its primes (`each` walks the bitset) and
+//! Comparable by limit; `count` without arguments and `include?` answer
+//! from the bitset rather than enumerating. `inspect` (and so `pp` and
+//! IRB) shows the limit, bitset size and, once `primes` was built, the
+//! prime count, without walking the bitset.
+//!
+//! `Marshal.dump` stores the core's byte serialization (header and bitset),
+//! so a Sieve can be cached (e.g. in Rails.cache) or sent to another
+//! process and loaded there without sieving again.
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+
+use magnus::gc::{Compactor, Marker};
+use magnus::prelude::*;
+use magnus::typed_data::Obj;
+use magnus::{
+    method, DataTypeFunctions, Error, Integer, RArray, RClass, RModule, RString, Ruby, Value,
+};
+use matryoshka_demo_core::Sieve;
+
+use crate::namespace::wrap;
+use crate::retained::Retained;
+use crate::{
+    clamped_u64, domain_arg, error, memory, without_gvl_cancellable, MAX_BITMAP_LIMIT,
+};
+
+/// A core Sieve wrapped as Ruby TypedData
+/// dfree drops the Box and with it the bitset; dsize reports the bitset, so
+/// `ObjectSpace.memsize_of` sees the real footprint, and the bitset is
+/// reported to the GC's malloc accounting for as long as the object lives
+/// The cached `primes` Array is marked movable and followed on compaction
+/// The core sieve is replaced by `initialize`, never while it is borrowed
+struct RbSieve {
+    sieve: RefCell<Sieve>,
+    primes: Retained<RArray>,
+}
+
+wrap!(RbSieve, "Sieve", mark, compact);
+
+impl Default for RbSieve {
+    /// What the allocator hands `initialize`: a sieve up to 0
+    fn default() -> Self {
+        Self::from_sieve(Sieve::new(0))
+    }
+}
+
+impl Drop for RbSieve {
+    /// Runs from dfree; hands the bitset's bytes back to the GC's accounting
+    fn drop(&mut self) {
+        memory::adjust_sieves(-(self.sieve.get_mut().memory_usage() as isize));
+    }
+}
+
+impl DataTypeFunctions for RbSieve {
+    fn size(&self) -> usize {
+        size_of::<Self>() + self.sieve.borrow().memory_usage()
+    }
+
+    fn mark(&self, marker: &Marker) {
+        self.primes.mark(marker);
+    }
+
+    fn compact(&self, compactor: &Compactor) {
+        self.primes.compact(compactor);
+    }
+}
+
+impl RbSieve {
+    /// Sieve up to and including `limit` with the GVL released
+    /// Raises ArgumentError for negative limits, ResourceLimit above
+    /// MAX_BITMAP_LIMIT, FrozenError for frozen sieves
+    fn initialize(ruby: &Ruby, rb_self: Obj<Self>, limit: Value) -> Result<(), Error> {
+        rb_self.check_frozen()?;
+        let limit = domain_arg(ruby, limit, "limit", 0)?.unwrap_or(0);
+        if limit > MAX_BITMAP_LIMIT {
+            return Err(error::resource_limit(
+                ruby,
+                format!("limit must be at most {MAX_BITMAP_LIMIT}, got {limit}"),
+            ));
+        }
+
+        let sieve =
+            without_gvl_cancellable(ruby, |cancel| Sieve::try_new_with_cancel(limit, cancel))?;
+        rb_self.replace(ruby, sieve)
+    }
+
+    /// Copy the bitset of `orig`, for `dup` and `clone`
+    /// Raises FrozenError for frozen sieves, TypeError unless `orig` is a
+    /// Sieve
+    fn initialize_copy(ruby: &Ruby, rb_self: Obj<Self>, orig: &Self) -> Result<(), Error> {
+        rb_self.check_frozen()?;
+        let sieve = orig.sieve.borrow().clone();
+        rb_self.replace(ruby, sieve)
+    }
+
+    /// Wrap `sieve`, reporting its bitset to the GC
+    fn from_sieve(sieve: Sieve) -> Self {
+        memory::adjust_sieves(sieve.memory_usage() as isize);
+        Self {
+            sieve: RefCell::new(sieve),
+            primes: Retained::new(),
+        }
+    }
+
+    /// Swap in `sieve`, moving the GC's accounting to its bitset and
+    /// dropping the cached `primes`
+    /// Raises RuntimeError while `each` is walking the current one
+    fn replace(&self, ruby: &Ruby, sieve: Sieve) -> Result<(), Error> {
+        let Ok(mut current) = self.sieve.try_borrow_mut() else {
+            return Err(Error::new(
+                ruby.exception_runtime_error(),
+                "can't reinitialize a Sieve during iteration",
+            ));
+        };
+        memory::adjust_sieves(sieve.memory_usage() as isize - current.memory_usage() as isize);
+        *current = sieve;
+        self.primes.clear();
+        Ok(())
+    }
+
+    /// The sieve as a binary String for `Marshal.dump`
+    fn dump(ruby: &Ruby, rb_self: &Self, _level: i64) -> RString {
+        ruby.str_from_slice(&rb_self.sieve.borrow().to_bytes())
+    }
+
+    /// Rebuild a sieve from what `_dump` returned, for `Marshal.load`, as
+    /// an instance of `class` (Sieve or the subclass that was dumped)
+    /// Raises ArgumentError for malformed data, ResourceLimit for a limit
+    /// above MAX_BITMAP_LIMIT
+    fn load(ruby: &Ruby, class: RClass, bytes: RString) -> Result<Obj<Self>, Error> {
+        // SAFETY: the slice is copied before any Ruby code can run
+        let sieve = Sieve::from_bytes(unsafe { bytes.as_slice() })
+            .map_err(|error| Error::new(ruby.exception_arg_error(), error.to_string()))?;
+        let limit = sieve.limit();
+        if limit > MAX_BITMAP_LIMIT {
+            return Err(error::resource_limit(
+                ruby,
+                format!("limit must be at most {MAX_BITMAP_LIMIT}, got {limit}"),
+            ));
+        }
+
+        Ok(ruby.obj_wrap_as(Self::from_sieve(sieve), class))
+    }
+
+    /// Upper bound (inclusive) this sieve covers
+    fn limit(&self) -> u64 {
+        self.sieve.borrow().limit()
+    }
+
+    /// Check whether `n` is prime; numbers above `limit` report false
+    /// Raises RangeError past u64
+    fn is_prime(ruby: &Ruby, rb_self: &Self, n: Integer) -> Result<bool, Error> {
+        Ok(rb_self.sieve.borrow().is_prime(clamped_u64(ruby, n, "n")?))
+    }
+
+    /// Bytes this object holds, as `ObjectSpace.memsize_of` reports them
+    fn memsize(&self) -> usize {
+        self.size()
+    }
+
+    /// `#<MatryoshkaDemoNative::Sieve limit=1000000 primes=78498 mem=33KiB>`,
+    /// with `primes=` only once the `primes` Array exists; every Sieve is
+    /// the same wheel Eratosthenes, so no algorithm is shown
+    fn inspect(ruby: &Ruby, rb_self: Obj<Self>) -> Result<String, Error> {
+        let class = rb_self.class().inspect();
+        let mut inspect = format!("#<{class} limit={}", rb_self.limit());
+        if let Some(primes) = rb_self.primes.get(ruby) {
+            inspect.push_str(&format!(" primes={}", primes.len()));
+        }
+        inspect.push_str(&format!(" mem={}>", human_bytes(rb_self.memsize())));
+        Ok(inspect)
+    }
+
+    /// Print as `inspect` does, for `pp` and IRB
+    fn pretty_print(rb_self: Obj<Self>, printer: Value) -> Result<Value, Error> {
+        printer.funcall("text", (rb_self.inspect(),))
+    }
+
+    /// Count the primes up to and including `limit`; with an argument or a
+    /// block, counts as Enumerable#count does
+    fn count(ruby: &Ruby, rb_self: &Self, args: &[Value]) -> Result<Value, Error> {
+        if args.is_empty() && !ruby.block_given() {
+            let count = rb_self.sieve.borrow().count();
+            return Ok(ruby.integer_from_u64(count).as_value());
+        }
+
+        ruby.call_super(args)
+    }
+
+    /// Yield each prime up to `limit` in order; returns an Enumerator
+    /// without a block
+    fn each(ruby: &Ruby, rb_self: Obj<Self>) -> Result<Value, Error> {
+        if !ruby.block_given() {
+            return Ok(rb_self.enumeratorize("each", ()).as_value());
+        }
+
+        // An exception or `break` in the block stops the walk and is
+        // re-raised; `rb_self` keeps the bitset alive and the borrow keeps
+        // `initialize` from replacing it meanwhile
+        let sieve = rb_self.sieve.borrow();
+        for p in sieve.iter() {
+            ruby.yield_value::<u64, Value>(p)?;
+        }
+        Ok(rb_self.as_value())
+    }
+
+    /// Every prime up to `limit` as a frozen Array, built on the first call
+    /// and returned again by later ones
+    fn primes(ruby: &Ruby, rb_self: &Self) -> Result<RArray, Error> {
+        rb_self.primes.get_or_try_init(ruby, || {
+            let primes = ruby.ary_from_iter(rb_self.sieve.borrow().iter());
+            primes.freeze();
+            Ok(primes)
+        })
+    }
+
+    /// Whether `n` is one of the primes, without enumerating them
+    fn includes(&self, n: Value) -> bool {
+        Integer::from_value(n)
+            .and_then(|n| n.to_u64().ok())
+            .is_some_and(|n| self.sieve.borrow().is_prime(n))
+    }
+
+    /// Order sieves by limit; nil for anything but a Sieve
+    fn cmp(&self, other: Value) -> Option<i64> {
+        let other = <&Self>::try_convert(other).ok()?;
+        Some(match self.limit().cmp(&other.limit()) {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        })
+    }
+
+    /// Find the nth prime (1-indexed), or nil if it lies above `limit`
+    /// Raises ArgumentError unless `n` is positive
+    fn nth(ruby: &Ruby, rb_self: &Self, n: Value) -> Result<Option<u64>, Error> {
+        let Some(n) = domain_arg(ruby, n, "n", 1)? else {
+            return Ok(None);
+        };
+
+        Ok(rb_self.sieve.borrow().nth(n))
+    }
+}
+
+/// `bytes` rounded to the largest binary unit it fills, e.g. `33KiB`
+fn human_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes}B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{}{}", size.round(), UNITS[unit])
+}
+
+/// Define `MatryoshkaDemoNative::Sieve` under `module`
+pub(crate) fn define(ruby: &Ruby, module: RModule) -> Result<(), Error> {
+    let class = module.define_class("Sieve", ruby.class_object())?;
+    class.define_alloc_func::<RbSieve>();
+    class.define_method("initialize", method!(RbSieve::initialize, 1))?;
+    class.define_method("initialize_copy", method!(RbSieve::initialize_copy, 1))?;
+    class.define_method("limit", method!(RbSieve::limit, 0))?;
+    class.define_method("prime?", method!(RbSieve::is_prime, 1))?;
+    class.define_method("count", method!(RbSieve::count, -1))?;
+    class.define_method("each", method!(RbSieve::each, 0))?;
+    class.define_method("primes", method!(RbSieve::primes, 0))?;
+    class.define_method("include?", method!(RbSieve::includes, 1))?;
+    class.define_method("member?", method!(RbSieve::includes, 1))?;
+    class.define_method("<=>", method!(RbSieve::cmp, 1))?;
+    class.include_module(ruby.module_enumerable())?;
+    class.include_module(ruby.module_comparable())?;
+    class.define_method("nth", method!(RbSieve::nth, 1))?;
+    class.define_method("memsize", method!(RbSieve::memsize, 0))?;
+    class.define_method("inspect", method!(RbSieve::inspect, 0))?;
+    class.define_method("pretty_print", method!(RbSieve::pretty_print, 1))?;
+    class.define_method("_dump", method!(RbSieve::dump, 1))?;
+    class.define_singleton_method("_load", method!(RbSieve::load, 1))?;
+    Ok(())
+}
@@ -0,0 +1,102 @@
+//! `MatryoshkaDemoNative.count_primes_with_stats`: a count and what it cost
+//!
+//! Benchmarks want the time and memory of each count next to its answer.
+//! Measured from Ruby, the time includes argument conversion and the GVL
+//! hand-off and the memory is invisible to ObjectSpace; measured here, the
+//! time is the computation alone and the memory is the core's own peak.
+//! The result is a frozen `MatryoshkaDemoNative::CountStats` Struct, so it
+//! can be shared with other Ractors.
+
+use std::time::Instant;
+
+use magnus::prelude::*;
+use magnus::scan_args::{get_kwargs, scan_args};
+use magnus::value::Lazy;
+use magnus::{function, Error, RClass, RHash, RModule, Ruby, Symbol, Value};
+use matryoshka_demo_core::CountOptions;
+
+use crate::cancel::call_token;
+use crate::namespace;
+use crate::threads::Threads;
+use crate::{
+    algorithm_name, count_algorithm, domain_arg, error, without_gvl_cancellable_by, CONFIG,
+    MAX_COUNT_LIMIT,
+};
+
+/// `MatryoshkaDemoNative::CountStats`, in the order `new` takes its members
+static COUNT_STATS: Lazy<RClass> = Lazy::new(|ruby| {
+    let members = ("count", "elapsed_ms", "peak_bytes", "algorithm", "segments");
+    ruby.define_struct(None, members)
+        .and_then(|class| {
+            namespace::module(ruby)?.const_set("CountStats", class)?;
+            Ok(class)
+        })
+        .expect("MatryoshkaDemoNative::CountStats can be defined")
+});
+
+/// Count prime numbers up to and including `limit`, returning a frozen
+/// CountStats of the count, the milliseconds it took, its peak working
+/// memory in bytes, the algorithm (:segmented or :sublinear) and the
+/// segments sieved (0 for :sublinear)
+/// Keywords as for `count_primes`, less `progress:`; the process-wide sieve
+/// is bypassed so every call measures a count
+/// Rust FFI wrapper for Ruby; raises ArgumentError for negative limits and
+/// unknown options, ResourceLimit above MAX_COUNT_LIMIT, Cancelled once
+/// `cancel:` is set and Timeout past `timeout:`
+fn count_primes_with_stats_native(ruby: &Ruby, args: &[Value]) -> Result<Value, Error> {
+    type Options = (Option<Symbol>, Option<Value>, Option<Value>, Option<Value>);
+    let args = scan_args::<(Value,), (), (), (), RHash, ()>(args)?;
+    let (limit,) = args.required;
+    let kwargs = get_kwargs::<_, (), Options, ()>(
+        args.keywords,
+        &[],
+        &["algorithm", "threads", "cancel", "timeout"],
+    )?;
+    let (algorithm, threads, cancel, timeout) = kwargs.optional;
+    let options = CountOptions {
+        algorithm: count_algorithm(ruby, algorithm)?,
+        segment_bytes: CONFIG.segment_bytes(),
+    };
+    let threads = Threads::from_option(ruby, threads)?;
+    let cancel = call_token(ruby, cancel, timeout)?;
+
+    // Out-of-domain limits count nothing, as with count_primes
+    let limit = domain_arg(ruby, limit, "limit", 0)?.unwrap_or(0);
+    if limit > MAX_COUNT_LIMIT {
+        return Err(error::resource_limit(
+            ruby,
+            format!("limit must be at most {MAX_COUNT_LIMIT}, got {limit}"),
+        ));
+    }
+
+    let (stats, elapsed) = without_gvl_cancellable_by(ruby, &cancel, |cancel| {
+        let clock = Instant::now();
+        let stats = threads.install(|| {
+            matryoshka_demo_core::try_count_primes_with_stats(..=limit, options, |_, _| {
+                cancel.observe()
+            })
+        })?;
+        Ok((stats, clock.elapsed()))
+    })?;
+
+    let stats = ruby.get_inner(&COUNT_STATS).new_instance((
+        stats.count,
+        elapsed.as_secs_f64() * 1000.0,
+        stats.peak_bytes,
+        ruby.to_symbol(algorithm_name(stats.algorithm)),
+        stats.segments,
+    ))?;
+    stats.freeze();
+    Ok(stats)
+}
+
+/// Define `MatryoshkaDemoNative::CountStats` and
+/// `MatryoshkaDemoNative.count_primes_with_stats` under `module`
+pub(crate) fn define(ruby: &Ruby, module: RModule) -> Result<(), Error> {
+    Lazy::force(&COUNT_STATS, ruby);
+    module.define_module_function(
+        "count_primes_with_stats",
+        function!(count_primes_with_stats_native, -1),
+    )?;
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//! Worker pools for the `threads:` option
+//!
+//! With the rayon feature the sublinear count splits its rounds across a
+//! pool; `threads: n` (or `config.threads = n`) runs the call on a pool of
+//! n workers instead of rayon's global one. That pool is started by the
+//! first call asking for n and reused until a call asks for a different
+//! count, so changing `config.threads` takes effect on the next call.
+//! Without the feature every call is serial, so only `threads: 1` is
+//! accepted. The stacks of a dedicated pool's threads are counted in
+//! `native_memory_stats`.
+
+#[cfg(feature = "rayon")]
+use std::sync::Arc;
+
+use magnus::{Error, Ruby, Value};
+#[cfg(feature = "rayon")]
+use parking_lot::Mutex;
+
+#[cfg(feature = "rayon")]
+use crate::memory;
+use crate::{domain_arg, CONFIG};
+
+/// Most workers a call may ask for
+pub(crate) const MAX_THREADS: u64 = 1024;
+
+/// Stack reserved by each dedicated pool thread, std's default made
+/// explicit so it can be counted
+#[cfg(feature = "rayon")]
+const STACK_BYTES: usize = 2 << 20;
+
+/// The dedicated pool last asked for, shared by every call using its count
+#[cfg(feature = "rayon")]
+static POOL: Mutex<Option<Arc<rayon::ThreadPool>>> = Mutex::new(None);
+
+/// The pool a computation runs on, None for the default
+pub(crate) struct Threads(#[cfg(feature = "rayon")] Option<Arc<rayon::ThreadPool>>);
+
+impl Threads {
+    /// Build the pool a `threads:` keyword asks for, `config.threads` when
+    /// it is absent
+    /// Raises ArgumentError unless `threads` is an Integer in
+    /// 1..=MAX_THREADS
+    pub(crate) fn from_option(ruby: &Ruby, threads: Option<Value>) -> Result<Self, Error> {
+        let threads = match threads {
+            Some(threads) => domain_arg(ruby, threads, "threads", 1)?,
+            None => CONFIG.threads(),
+        };
+        if let Some(threads) = threads.filter(|&threads| threads > MAX_THREADS) {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                format!("threads must be at most {MAX_THREADS}, got {threads}"),
+            ));
+        }
+        Self::new(ruby, threads)
+    }
+
+    /// The pool for `threads` workers, started now unless the last one
+    /// asked for has that many; None keeps the default
+    /// Raises ArgumentError for more than one thread without the rayon
+    /// feature, RuntimeError if the pool cannot be started
+    #[cfg(feature = "rayon")]
+    pub(crate) fn new(ruby: &Ruby, threads: Option<u64>) -> Result<Self, Error> {
+        let Some(threads) = threads else {
+            return Ok(Self(None));
+        };
+
+        let mut pool = POOL.lock();
+        if let Some(pool) = pool.as_ref()
+            && pool.current_num_threads() == threads as usize
+        {
+            return Ok(Self(Some(Arc::clone(pool))));
+        }
+        // A replaced pool shuts down once the calls still using it finish
+        let started = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads as usize)
+            .stack_size(STACK_BYTES)
+            .start_handler(|_| memory::pool_thread_started(STACK_BYTES))
+            .exit_handler(|_| memory::pool_thread_exited(STACK_BYTES))
+            .build()
+            .map_err(|error| Error::new(ruby.exception_runtime_error(), error.to_string()))?;
+        #[cfg(feature = "log")]
+        log::debug!("started a pool of {threads} threads");
+        let started = Arc::new(started);
+        *pool = Some(Arc::clone(&started));
+        Ok(Self(Some(started)))
+    }
+
+    /// Build the pool for `threads` workers; None keeps the default
+    /// Raises ArgumentError for more than one thread without the rayon
+    /// feature, RuntimeError if the pool cannot be started
+    #[cfg(not(feature = "rayon"))]
+    pub(crate) fn new(ruby: &Ruby, threads: Option<u64>) -> Result<Self, Error> {
+        if let Some(threads) = threads {
+            check_supported(ruby, threads)?;
+        }
+        Ok(Self())
+    }
+
+    /// Whether calls run on a dedicated pool rather than the calling thread
+    pub(crate) fn is_pool(&self) -> bool {
+        #[cfg(feature = "rayon")]
+        let pooled = self.0.is_some();
+        #[cfg(not(feature = "rayon"))]
+        let pooled = false;
+        pooled
+    }
+
+    /// Run `f` on this pool; must not touch Ruby
+    pub(crate) fn install<R: Send>(&self, f: impl FnOnce() -> R + Send) -> R {
+        #[cfg(feature = "rayon")]
+        if let Some(pool) = &self.0 {
+            return pool.install(f);
+        }
+        f()
+    }
+
+    /// Apply `f` to every item in order, spread across this pool (or
+    /// rayon's global one) with the rayon feature; must not touch Ruby
+    pub(crate) fn map<T: Sync, R: Send>(
+        &self,
+        items: &[T],
+        f: impl Fn(&T) -> R + Send + Sync,
+    ) -> Vec<R> {
+        #[cfg(feature = "rayon")]
+        let mapped = {
+            use rayon::prelude::*;
+            self.install(|| items.par_iter().map(f).collect())
+        };
+        #[cfg(not(feature = "rayon"))]
+        let mapped = items.iter().map(f).collect();
+        mapped
+    }
+}
+
+/// Check that this build can run `threads` workers
+/// Raises ArgumentError for more than one thread without the rayon feature
+pub(crate) fn check_supported(ruby: &Ruby, threads: u64) -> Result<(), Error> {
+    if cfg!(feature = "rayon") || threads <= 1 {
+        return Ok(());
+    }
+
+    Err(Error::new(
+        ruby.exception_arg_error(),
+        format!("threads: {threads} needs the rayon feature"),
+    ))
+}
@@ -0,0 +1,428 @@
+//! `#[matryoshka::export]`: Ruby bindings for plain Rust functions
+//!
+//! Most of the extension's glue converts arguments, calls one core
+//! function and converts the answer back. Applied to a function taking and
+//! returning plain values, the attribute keeps it and adds the magnus
+//! wrapper `<name>_native` and `define_<name>(module)` registering it.
+//! Applied to an inline module, it does that for every function inside and
+//! adds one `define(module)` registering them all, so a new binding is one
+//! function and nothing else.
+//!
+//! The wrapper takes `&Ruby` first, then each argument:
+//!
+//! - `u64` and `u32` arguments arrive as Ruby Integers and go through the
+//!   binding crate's `u64_from_integer` / `u32_from_integer`, raising
+//!   ArgumentError for negatives and RangeError past the type, naming the
+//!   argument
+//! - a `u64` marked `#[export(clamped)]` takes negatives as 0 (`clamped_u64`),
+//!   one marked `#[export(abs)]` takes their magnitude (`magnitude`)
+//! - `NonZeroU64` arguments go through `non_zero_u64_from_integer`, raising
+//!   ArgumentError for 0 as well
+//! - any other argument is converted by magnus
+//! - a `Result<T, CoreError>` answer raises through the binding crate's
+//!   `error::core`; any other answer is converted by magnus, so None
+//!   becomes nil
+//!
+//! The Ruby name is the function's unless set with `name = "prime?"`, on
+//! the attribute or, inside an exported module, on `#[export(..)]`.
+
+use proc_macro2::{Literal, Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, FnArg, GenericArgument, Ident, Item, ItemFn, LitStr, Pat, PathArguments, ReturnType,
+    Type,
+};
+
+/// Export a function, or every function in an inline module, to Ruby
+#[proc_macro_attribute]
+pub fn export(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    expand(attr.into(), item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Expand `#[export(attr)] item`
+fn expand(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    match syn::parse2(item)? {
+        Item::Fn(mut function) => {
+            let name = ruby_name(attr)?;
+            let (wrapper, registration) = bind(&mut function, name)?;
+            let define = format_ident!("define_{}", function.sig.ident);
+            let vis = &function.vis;
+            Ok(quote! {
+                #function
+                #wrapper
+
+                #[allow(dead_code)]
+                #vis fn #define(module: ::magnus::RModule) -> ::core::result::Result<(), ::magnus::Error> {
+                    #registration
+                    Ok(())
+                }
+            })
+        }
+        Item::Mod(mut module) => {
+            if !attr.is_empty() {
+                return Err(syn::Error::new(
+                    attr.span(),
+                    "name each function with #[export(name = ..)] instead",
+                ));
+            }
+            let Some((_, items)) = &mut module.content else {
+                return Err(syn::Error::new(
+                    module.span(),
+                    "only an inline module can be exported",
+                ));
+            };
+
+            let mut generated = Vec::new();
+            let mut registrations = Vec::new();
+            for item in items.iter_mut() {
+                let Item::Fn(function) = item else {
+                    continue;
+                };
+                let name = take_export_attr(&mut function.attrs)?;
+                let (wrapper, registration) = bind(function, name)?;
+                generated.push(wrapper);
+                registrations.push(registration);
+            }
+            items.extend(generated.into_iter().map(Item::Verbatim));
+            items.push(Item::Verbatim(quote! {
+                /// Define every function exported from this module under `module`
+                pub(crate) fn define(module: ::magnus::RModule) -> ::core::result::Result<(), ::magnus::Error> {
+                    #(#registrations)*
+                    Ok(())
+                }
+            }));
+            Ok(quote!(#module))
+        }
+        item => Err(syn::Error::new(
+            item.span(),
+            "#[matryoshka::export] applies to a function or an inline module",
+        )),
+    }
+}
+
+/// The Ruby name set by `name = ".."` in `attr`, if any
+fn ruby_name(attr: TokenStream) -> syn::Result<Option<LitStr>> {
+    let mut name = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("expected `name = \"..\"`"))
+        }
+    });
+    syn::parse::Parser::parse2(parser, attr)?;
+    Ok(name)
+}
+
+/// Remove a function's `#[export(..)]` attributes, returning the Ruby name
+/// the last one sets
+fn take_export_attr(attrs: &mut Vec<Attribute>) -> syn::Result<Option<LitStr>> {
+    let mut name = None;
+    let mut result = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path().is_ident("export") {
+            return true;
+        }
+        match attr.meta.require_list() {
+            Ok(list) => match ruby_name(list.tokens.clone()) {
+                Ok(set) => name = set.or(name.take()),
+                Err(error) => result = Err(error),
+            },
+            Err(error) => result = Err(error),
+        }
+        false
+    });
+    result.map(|()| name)
+}
+
+/// The wrapper for `function` and the statement registering it as `name`
+/// (the function's own name when None); removes its arguments' `#[export(..)]`
+fn bind(function: &mut ItemFn, name: Option<LitStr>) -> syn::Result<(TokenStream, TokenStream)> {
+    let sig = &mut function.sig;
+    if let Some(token) = sig.asyncness {
+        return Err(syn::Error::new(token.span(), "cannot export an async fn"));
+    }
+    if let Some(token) = sig.unsafety {
+        return Err(syn::Error::new(token.span(), "cannot export an unsafe fn"));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            sig.generics.span(),
+            "cannot export a generic fn",
+        ));
+    }
+
+    let mut params = Vec::new();
+    let mut conversions = Vec::new();
+    let mut args = Vec::new();
+    for input in &mut sig.inputs {
+        let FnArg::Typed(input) = input else {
+            return Err(syn::Error::new(input.span(), "cannot export a method"));
+        };
+        let Pat::Ident(pat) = &*input.pat else {
+            return Err(syn::Error::new(
+                input.pat.span(),
+                "exported arguments must be plain names",
+            ));
+        };
+        let arg = pat.ident.clone();
+        let label = arg.to_string();
+        let ty = &input.ty;
+        let mode = take_integer_mode(&mut input.attrs)?;
+        match integer_conversion(ty, mode, &arg, &label)? {
+            Some(convert) => {
+                params.push(quote!(#arg: ::magnus::Integer));
+                conversions.push(quote!(let #arg = #convert;));
+            }
+            None => params.push(quote!(#arg: #ty)),
+        }
+        args.push(arg);
+    }
+    let sig = &function.sig;
+
+    let ident = &sig.ident;
+    let output = match &sig.output {
+        ReturnType::Default => None,
+        ReturnType::Type(_, ty) => Some((ty, core_result(ty))),
+    };
+    let (answer, call) = match output {
+        None => (quote!(()), quote!(Ok(#ident(#(#args),*)))),
+        Some((_, Some(value))) => (
+            quote!(#value),
+            quote!(#ident(#(#args),*).map_err(|error| crate::error::core(ruby, error))),
+        ),
+        Some((ty, None)) => (quote!(#ty), quote!(Ok(#ident(#(#args),*)))),
+    };
+    let uses_ruby = !conversions.is_empty() || matches!(output, Some((_, Some(_))));
+    let ruby = if uses_ruby {
+        Ident::new("ruby", Span::call_site())
+    } else {
+        Ident::new("_ruby", Span::call_site())
+    };
+
+    let vis = &function.vis;
+    let wrapper = format_ident!("{}_native", ident);
+    let arity = Literal::usize_unsuffixed(params.len());
+    let name = name.unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+    let wrapper_fn = quote! {
+        #[allow(dead_code)]
+        #vis fn #wrapper(
+            #ruby: &::magnus::Ruby,
+            #(#params),*
+        ) -> ::core::result::Result<#answer, ::magnus::Error> {
+            #(#conversions)*
+            #call
+        }
+    };
+    let registration = quote! {
+        module.define_module_function(#name, ::magnus::function!(#wrapper, #arity))?;
+    };
+    Ok((wrapper_fn, registration))
+}
+
+/// Remove an argument's `#[export(clamped)]` or `#[export(abs)]`,
+/// returning which one it had
+fn take_integer_mode(attrs: &mut Vec<Attribute>) -> syn::Result<Option<Ident>> {
+    let mut mode = None;
+    let mut result = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path().is_ident("export") {
+            return true;
+        }
+        match attr.parse_args::<Ident>() {
+            Ok(ident) if ident == "clamped" || ident == "abs" => mode = Some(ident),
+            Ok(ident) => result = Err(syn::Error::new(ident.span(), "expected `clamped` or `abs`")),
+            Err(error) => result = Err(error),
+        }
+        false
+    });
+    result.map(|()| mode)
+}
+
+/// The expression converting the Ruby Integer `arg` to `ty` through the
+/// binding crate, if `ty` takes one, in `mode` if set
+fn integer_conversion(
+    ty: &Type,
+    mode: Option<Ident>,
+    arg: &Ident,
+    label: &str,
+) -> syn::Result<Option<TokenStream>> {
+    let ident = match ty {
+        Type::Path(path) => path.path.segments.last().map(|segment| &segment.ident),
+        _ => None,
+    };
+    let convert = match (ident, &mode) {
+        (Some(ident), None) if ident == "u64" => quote!(u64_from_integer),
+        (Some(ident), None) if ident == "u32" => quote!(u32_from_integer),
+        (Some(ident), None) if ident == "NonZeroU64" => quote!(non_zero_u64_from_integer),
+        (Some(ident), Some(mode)) if ident == "u64" && mode == "clamped" => quote!(clamped_u64),
+        (Some(ident), Some(mode)) if ident == "u64" && mode == "abs" => {
+            return Ok(Some(quote!(crate::magnitude(ruby, #arg, #label)?.1)));
+        }
+        (_, Some(mode)) => {
+            return Err(syn::Error::new(
+                mode.span(),
+                "only a u64 can be clamped or abs",
+            ));
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(quote!(crate::#convert(ruby, #arg, #label)?)))
+}
+
+/// `T` when `ty` is `Result<T, CoreError>`
+fn core_result(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Result" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(generics) = &segment.arguments else {
+        return None;
+    };
+    let mut generics = generics.args.iter();
+    let (Some(GenericArgument::Type(value)), Some(GenericArgument::Type(Type::Path(error))), None) =
+        (generics.next(), generics.next(), generics.next())
+    else {
+        return None;
+    };
+    let error = error.path.segments.last()?;
+    (error.ident == "CoreError").then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `expand`'s output as a token string
+    fn expanded(attr: TokenStream, item: TokenStream) -> String {
+        expand(attr, item).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_export_fn() {
+        let output = expanded(
+            quote!(),
+            quote! {
+                pub(crate) fn checked_pow(base: u64, exp: u32) -> Option<u64> {
+                    base.checked_pow(exp)
+                }
+            },
+        );
+        let wrapper = quote! {
+            pub(crate) fn checked_pow_native(
+                ruby: &::magnus::Ruby,
+                base: ::magnus::Integer,
+                exp: ::magnus::Integer
+            ) -> ::core::result::Result<Option<u64>, ::magnus::Error> {
+                let base = crate::u64_from_integer(ruby, base, "base")?;
+                let exp = crate::u32_from_integer(ruby, exp, "exp")?;
+                Ok(checked_pow(base, exp))
+            }
+        };
+        assert!(output.contains(&wrapper.to_string()));
+        let registration = quote! {
+            module.define_module_function("checked_pow", ::magnus::function!(checked_pow_native, 2))?;
+        };
+        assert!(output.contains(&registration.to_string()));
+        assert!(output.contains("pub (crate) fn define_checked_pow"));
+    }
+
+    #[test]
+    fn test_export_core_result() {
+        let output = expanded(
+            quote!(name = "nth_prime?"),
+            quote! {
+                fn nth(n: u64, strict: bool) -> Result<u64, matryoshka_demo_core::CoreError> {
+                    todo!()
+                }
+            },
+        );
+        assert!(output.contains("strict : bool"));
+        assert!(output.contains("-> :: core :: result :: Result < u64 , :: magnus :: Error >"));
+        assert!(output.contains(
+            &quote!(nth(n, strict).map_err(|error| crate::error::core(ruby, error))).to_string()
+        ));
+        assert!(output.contains("\"nth_prime?\""));
+    }
+
+    #[test]
+    fn test_export_plain_args() {
+        let output = expanded(
+            quote!(),
+            "fn answer(flag: bool) -> bool { flag }".parse().unwrap(),
+        );
+        assert!(output.contains("_ruby : & :: magnus :: Ruby"));
+        assert!(output.contains(&quote!(Ok(answer(flag))).to_string()));
+    }
+
+    #[test]
+    fn test_export_integer_modes() {
+        let output = expanded(
+            quote!(),
+            quote! {
+                fn step(#[export(clamped)] n: u64, #[export(abs)] a: u64, m: NonZeroU64) -> u64 {
+                    n
+                }
+            },
+        );
+        assert!(!output.contains("# [export"));
+        assert!(output.contains(&quote!(let n = crate::clamped_u64(ruby, n, "n")?;).to_string()));
+        assert!(output.contains(&quote!(let a = crate::magnitude(ruby, a, "a")?.1;).to_string()));
+        assert!(output.contains(
+            &quote!(let m = crate::non_zero_u64_from_integer(ruby, m, "m")?;).to_string()
+        ));
+    }
+
+    #[test]
+    fn test_export_mod() {
+        let output = expanded(
+            quote!(),
+            quote! {
+                mod exports {
+                    fn isqrt(n: u64) -> u64 { n }
+
+                    #[export(name = "root?")]
+                    fn is_root(n: u64) -> bool { n == 0 }
+
+                    const UNTOUCHED: u8 = 0;
+                }
+            },
+        );
+        assert!(!output.contains("# [export"));
+        assert!(output.contains("fn isqrt_native"));
+        assert!(output.contains("fn is_root_native"));
+        assert!(output.contains("const UNTOUCHED"));
+        assert!(output.contains("pub (crate) fn define (module"));
+        assert!(output.contains("\"isqrt\" , :: magnus :: function ! (isqrt_native , 1)"));
+        assert!(output.contains("\"root?\" , :: magnus :: function ! (is_root_native , 1)"));
+    }
+
+    #[test]
+    fn test_export_rejects() {
+        let cases = [
+            ("", "struct NotAFunction;"),
+            ("", "mod outline;"),
+            ("", "fn generic<T>(t: T) {}"),
+            ("", "async fn later() {}"),
+            ("", "fn destructured((a, b): (u64, u64)) {}"),
+            ("", "fn clamped(#[export(clamped)] n: u32) {}"),
+            ("", "fn unknown(#[export(wrapped)] n: u64) {}"),
+            ("names = \"x\"", "fn misspelled() {}"),
+            ("name = \"x\"", "mod exports {}"),
+        ];
+        for (attr, item) in cases {
+            let result = expand(attr.parse().unwrap(), item.parse().unwrap());
+            assert!(result.is_err(), "{attr} {item}");
+        }
+    }
+}
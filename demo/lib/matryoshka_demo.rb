@@ -5,6 +5,16 @@ require_relative 'matryoshka_demo/prime_counter'
 module MatryoshkaDemo
   VERSION = '0.1.0'
 
+  class << self
+    # Return the old quiet results (0 or nil) for out-of-domain arguments
+    # instead of raising ArgumentError
+    attr_writer :lenient
+
+    def lenient?
+      @lenient == true
+    end
+  end
+
   # Public API delegates to PrimeCounter
   def self.count_primes(limit)
     PrimeCounter.count_primes(limit)
@@ -24,11 +24,16 @@ begin
   module MatryoshkaDemo
     module JvmSpeedup
       module ClassMethods
+        # Arguments are checked as in pure Ruby before reaching Java
         def count_primes(limit)
+          return 0 unless domain_argument?(limit, 'limit', 0)
+
           Java::IoMatryoshkaDemo::PrimeCounter.countPrimes(limit)
         end
 
         def nth_prime(n)
+          return nil unless domain_argument?(n, 'n', 1)
+
           Java::IoMatryoshkaDemo::PrimeCounter.nthPrime(n)
         end
       end
@@ -29,10 +29,19 @@ begin
       def self.prepended(base)
         base.singleton_class.prepend(ClassMethods)
       end
+
+      # Keep the extension's argument checking in step with MatryoshkaDemo
+      module Lenient
+        def lenient=(lenient)
+          super
+          MatryoshkaDemoNative.lenient = lenient == true
+        end
+      end
     end
 
     # Prepend native methods - they take precedence in method lookup
     PrimeCounter.prepend(NativeSpeedup)
+    singleton_class.prepend(NativeSpeedup::Lenient)
   end
 
   # Optional: log success
@@ -7,7 +7,9 @@ module MatryoshkaDemo
     # Count all prime numbers up to the given limit
     # @param limit [Integer] upper bound (inclusive)
     # @return [Integer] count of primes
+    # @raise [ArgumentError] unless limit is a non-negative Integer
     def self.count_primes(limit)
+      return 0 unless domain_argument?(limit, 'limit', 0)
       return 0 if limit < 2
 
       # Create array: true = potentially prime
@@ -36,8 +38,9 @@ module MatryoshkaDemo
     # Find the nth prime number (1-indexed)
     # @param n [Integer] which prime to find (1 = first prime = 2)
     # @return [Integer] the nth prime
+    # @raise [ArgumentError] unless n is a positive Integer
     def self.nth_prime(n)
-      return nil if n < 1
+      return nil unless domain_argument?(n, 'n', 1)
 
       # Estimate upper bound for nth prime
       # Using approximation: nth prime ≈ n * ln(n) for n > 5
@@ -73,5 +76,16 @@ module MatryoshkaDemo
 
       nil # Should not reach here with proper limit estimation
     end
+
+    # Check that value is an Integer of at least min, raising ArgumentError
+    # otherwise; in lenient mode return false so the caller returns 0 or nil
+    def self.domain_argument?(value, name, min)
+      return value >= min if MatryoshkaDemo.lenient?
+      raise ArgumentError, "#{name} must be an Integer, got #{value.inspect}" unless value.is_a?(Integer)
+      raise ArgumentError, "#{name} must be at least #{min}, got #{value}" if value < min
+
+      true
+    end
+    private_class_method :domain_argument?
   end
 end
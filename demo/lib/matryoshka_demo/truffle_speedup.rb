@@ -25,11 +25,16 @@ begin
     module MatryoshkaDemo
       module TruffleSpeedup
         module ClassMethods
+          # Arguments are checked as in pure Ruby before reaching Java
           def count_primes(limit)
+            return 0 unless domain_argument?(limit, 'limit', 0)
+
             Polyglot.eval('java', 'io.matryoshka.demo.PrimeCounter').countPrimes(limit)
           end
 
           def nth_prime(n)
+            return nil unless domain_argument?(n, 'n', 1)
+
             Polyglot.eval('java', 'io.matryoshka.demo.PrimeCounter').nthPrime(n)
           end
         end
@@ -0,0 +1,1025 @@
+# frozen_string_literal: true
+
+require 'minitest/autorun'
+require 'pp'
+require 'stringio'
+require_relative '../lib/matryoshka_demo'
+
+# Exercises methods that only exist on the native extension
+class NativeTest < Minitest::Test
+  def setup
+    skip 'native extension not loaded' unless defined?(MatryoshkaDemoNative)
+  end
+
+  def test_count_primes_rejects_huge_limits
+    assert_equal 37_607_912_018, MatryoshkaDemoNative.count_primes(10**12)
+    assert_raises(MatryoshkaDemoNative::ResourceLimit) { MatryoshkaDemoNative.count_primes(2**62) }
+  end
+
+  def test_long_count_releases_gvl
+    ticks = 0
+    ticker = Thread.new { loop { ticks += 1; sleep 0.001 } }
+    assert_equal 4_118_054_813, MatryoshkaDemoNative.count_primes(10**11)
+    ticker.kill
+    assert_operator ticks, :>, 10
+  end
+
+  def test_thread_raise_interrupts_long_count
+    worker = Thread.new do
+      Thread.current.report_on_exception = false
+      MatryoshkaDemoNative.count_primes(10**14)
+    end
+    sleep 0.2
+    started = Process.clock_gettime(Process::CLOCK_MONOTONIC)
+    worker.raise(Interrupt)
+    assert_raises(Interrupt) { worker.join }
+    assert_operator Process.clock_gettime(Process::CLOCK_MONOTONIC) - started, :<, 1
+  end
+
+  def test_invalid_arguments_raise
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.count_primes(-5) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.count_primes(10.5) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.count_primes('10') }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.nth_prime(0) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.primes_up_to(-1) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.each_prime(-1) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.max_prime_gap(-1) }
+    error = assert_raises(ArgumentError) { MatryoshkaDemoNative.nth_prime(-3) }
+    assert_equal 'n must be at least 1, got -3', error.message
+  end
+
+  def test_lenient_mode
+    MatryoshkaDemoNative.lenient = true
+    assert MatryoshkaDemoNative.lenient?
+    assert_equal 0, MatryoshkaDemoNative.count_primes(-5)
+    assert_nil MatryoshkaDemoNative.nth_prime(0)
+    assert_equal 0, MatryoshkaDemoNative.sum_of_primes(-5)
+    assert_equal [], MatryoshkaDemoNative.primes_up_to(-1)
+    assert_nil MatryoshkaDemoNative.max_prime_gap(-1)
+  ensure
+    MatryoshkaDemoNative.lenient = false
+  end
+
+  def test_bang_variants
+    assert_equal 7_919, MatryoshkaDemoNative.nth_prime!(1_000)
+    assert_equal 101, MatryoshkaDemoNative.next_prime!(100)
+    assert_equal [5, 23], MatryoshkaDemoNative.goldbach_pair!(28)
+    assert_equal 4, MatryoshkaDemoNative.inv_mod!(3, 11)
+    assert_equal 1_024, MatryoshkaDemoNative.checked_pow!(2, 10)
+
+    assert_raises(MatryoshkaDemoNative::OverflowError) { MatryoshkaDemoNative.next_prime!(2**64 - 59) }
+    assert_raises(MatryoshkaDemoNative::OverflowError) { MatryoshkaDemoNative.checked_pow!(2, 64) }
+    assert_raises(MatryoshkaDemoNative::InvalidArgument) { MatryoshkaDemoNative.prev_prime!(2) }
+    assert_raises(MatryoshkaDemoNative::InvalidArgument) { MatryoshkaDemoNative.goldbach_pair!(27) }
+    error = assert_raises(MatryoshkaDemoNative::InvalidArgument) { MatryoshkaDemoNative.inv_mod!(4, 8) }
+    assert_equal '4 has no inverse modulo 8', error.message
+
+    MatryoshkaDemoNative.lenient = true
+    assert_nil MatryoshkaDemoNative.nth_prime(0)
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.nth_prime!(0) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.max_prime_gap!(-1) }
+    assert MatryoshkaDemoNative.lenient?
+  ensure
+    MatryoshkaDemoNative.lenient = false
+  end
+
+  def test_count_primes_keywords
+    assert_equal 78_498, MatryoshkaDemoNative.count_primes(1_000_000, algorithm: :segmented)
+    assert_equal 78_498, MatryoshkaDemoNative.count_primes(1_000_000, algorithm: :sublinear)
+    assert_equal 78_498, MatryoshkaDemoNative.count_primes(1_000_000, algorithm: :auto, threads: 1)
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.count_primes(100, algorithm: :atkin) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.count_primes(100, threads: 0) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.count_primes(100, color: :red) }
+  end
+
+  def test_count_primes_cancel_token
+    token = MatryoshkaDemoNative::CancelToken.new
+    refute token.cancelled?
+    assert_equal 168, MatryoshkaDemoNative.count_primes(1_000, cancel: token)
+
+    worker = Thread.new do
+      Thread.current.report_on_exception = false
+      MatryoshkaDemoNative.count_primes(10**14, cancel: token)
+    end
+    sleep 0.2
+    token.cancel
+    assert token.cancelled?
+    assert_raises(MatryoshkaDemoNative::Cancelled) { worker.join }
+    assert_raises(MatryoshkaDemoNative::Cancelled) { MatryoshkaDemoNative.count_primes(10**12, cancel: token) }
+    assert_raises(TypeError) { MatryoshkaDemoNative.count_primes(100, cancel: Object.new) }
+
+    # An interrupt stops only the call it hits, never the caller's token
+    token = MatryoshkaDemoNative::CancelToken.new
+    worker = Thread.new do
+      Thread.current.report_on_exception = false
+      MatryoshkaDemoNative.count_primes(10**14, algorithm: :segmented, cancel: token)
+    end
+    sleep 0.1
+    worker.raise(Interrupt)
+    assert_raises(Interrupt) { worker.join }
+    refute token.cancelled?
+    assert_equal 664_579, MatryoshkaDemoNative.count_primes(10**7, algorithm: :segmented, cancel: token)
+  end
+
+  def test_count_primes_progress
+    reports = []
+    count = MatryoshkaDemoNative.count_primes(10**10, progress: ->(done, total) { reports << [done, total] })
+    assert_equal 455_052_511, count
+    assert_operator reports.size, :>, 10
+    assert_operator reports.size, :<=, 1_001
+    assert_equal reports.last[1], reports.last[0]
+    assert_equal reports.map(&:first), reports.map(&:first).sort
+
+    seen = 0
+    assert_equal 168, MatryoshkaDemoNative.count_primes(1_000, progress: ->(*) { seen += 1 })
+    assert_operator seen, :>=, 1
+
+    assert_raises(RuntimeError) do
+      MatryoshkaDemoNative.count_primes(10**12, progress: ->(*) { raise 'stop' })
+    end
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.count_primes(100, progress: 42) }
+  end
+
+  def test_range_arguments
+    assert_equal [11, 13, 17, 19], MatryoshkaDemoNative.primes_in(10..20)
+    assert_equal [11, 13, 17], MatryoshkaDemoNative.primes_in(10...19)
+    assert_equal [2, 3, 5, 7], MatryoshkaDemoNative.primes_in(..10)
+    assert_equal [101, 103], MatryoshkaDemoNative.primes_in(100.., cap: 105)
+    assert_equal [], MatryoshkaDemoNative.primes_in(-10..1)
+    assert_equal 168, MatryoshkaDemoNative.count_primes_in(-5...1_000)
+    assert_equal 4, MatryoshkaDemoNative.count_primes_in(10...1_000, cap: 20)
+    assert_equal 37_607_912_018 - 4, MatryoshkaDemoNative.count_primes_in(9..10**12)
+    assert_equal 0, MatryoshkaDemoNative.count_primes_in(20..10)
+
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.primes_in(10..) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.primes_in(1.5..10) }
+    assert_raises(MatryoshkaDemoNative::ResourceLimit) { MatryoshkaDemoNative.primes_in(0..10**10) }
+    assert_raises(MatryoshkaDemoNative::ResourceLimit) { MatryoshkaDemoNative.count_primes_in(0..2**62) }
+    assert_raises(TypeError) { MatryoshkaDemoNative.count_primes_in(10) }
+  end
+
+  def test_batch_entry_points
+    limits = [0, 10, 1_000, 100, 10**12]
+    assert_equal [0, 4, 168, 25, 37_607_912_018], MatryoshkaDemoNative.count_primes_many(limits)
+    assert_equal limits.map { |limit| MatryoshkaDemoNative.count_primes(limit) },
+                 MatryoshkaDemoNative.count_primes_many(limits, threads: 1)
+    assert_equal [], MatryoshkaDemoNative.count_primes_many([])
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.count_primes_many([10, -1]) }
+    assert_raises(MatryoshkaDemoNative::ResourceLimit) { MatryoshkaDemoNative.count_primes_many([2**62]) }
+
+    limits = Array.new(500) { |i| (i * 7_919) % 10_007 * 100 }
+    assert_equal MatryoshkaDemoNative.count_primes_many(limits, threads: 1),
+                 MatryoshkaDemoNative.count_primes_parallel(limits)
+    assert_equal [25, 4, 0], MatryoshkaDemoNative.count_primes_parallel([100, 10, 1], threads: 1)
+
+    numbers = [7, 1, -7, 91, 2**64 + 13, 97, 7]
+    expected = { 7 => true, 1 => false, -7 => false, 91 => false, 2**64 + 13 => true, 97 => true }
+    assert_equal expected, MatryoshkaDemoNative.prime_map(numbers)
+    assert_equal expected.keys, MatryoshkaDemoNative.prime_map(numbers).keys
+    assert_equal (1..1_000).select { |n| MatryoshkaDemoNative.prime?(n) },
+                 MatryoshkaDemoNative.prime_map((1..1_000).to_a).select { |_, prime| prime }.keys
+    assert_raises(RangeError) { MatryoshkaDemoNative.prime_map([2**128]) }
+  end
+
+  def test_error_hierarchy
+    assert_operator MatryoshkaDemoNative::Error, :<, StandardError
+    %i[InvalidArgument OverflowError Cancelled Timeout ResourceLimit].each do |name|
+      assert_operator MatryoshkaDemoNative.const_get(name), :<, MatryoshkaDemoNative::Error
+    end
+
+    error = assert_raises(MatryoshkaDemoNative::Error) { MatryoshkaDemoNative.prime_bitmap(10**10) }
+    assert_kind_of MatryoshkaDemoNative::ResourceLimit, error
+    assert_match(/at most 3000000000/, error.message)
+
+    token = MatryoshkaDemoNative::CancelToken.new
+    token.cancel
+    assert_raises(MatryoshkaDemoNative::Cancelled) { MatryoshkaDemoNative.count_primes(10**12, cancel: token) }
+  end
+
+  def test_error_code_and_details
+    token = MatryoshkaDemoNative::CancelToken.new
+    token.cancel
+    error = assert_raises(MatryoshkaDemoNative::Cancelled) do
+      MatryoshkaDemoNative.count_primes(10**12, cancel: token)
+    end
+    assert_equal :cancelled, error.code
+    assert_equal({}, error.details)
+    assert_predicate error.details, :frozen?
+
+    error = assert_raises(MatryoshkaDemoNative::ResourceLimit) { MatryoshkaDemoNative.prime_bitmap(10**10) }
+    assert_equal :resource_limit, error.code
+    error = assert_raises(MatryoshkaDemoNative::InvalidArgument) { MatryoshkaDemoNative.prev_prime!(2) }
+    assert_equal :invalid_argument, error.code
+    error = assert_raises(MatryoshkaDemoNative::Timeout) do
+      MatryoshkaDemoNative.count_primes(10**14, algorithm: :segmented, timeout: 0.05)
+    end
+    assert_equal :timeout, error.code
+
+    # Raised from Ruby, an error answers its class's code
+    assert_equal :overflow, MatryoshkaDemoNative::OverflowError.new('x').code
+    assert_equal :error, MatryoshkaDemoNative::Error.new.code
+    assert_equal({}, MatryoshkaDemoNative::Error.new.details)
+  end
+
+  def test_core_error_classes
+    assert_operator MatryoshkaDemoNative::LimitTooLarge, :<, MatryoshkaDemoNative::ResourceLimit
+    assert_operator MatryoshkaDemoNative::EstimateExceeded, :<, MatryoshkaDemoNative::OverflowError
+    assert_equal MatryoshkaDemoNative::Error, MatryoshkaDemoNative::InvalidArgument.superclass
+    assert_equal MatryoshkaDemoNative::Error, MatryoshkaDemoNative::Cancelled.superclass
+    assert_equal :limit_too_large, MatryoshkaDemoNative::LimitTooLarge.new('x').code
+    assert_equal :estimate_exceeded, MatryoshkaDemoNative::EstimateExceeded.new('x').code
+    assert_equal :resource_limit, MatryoshkaDemoNative::ResourceLimit.new('x').code
+  end
+
+  def test_config_threads
+    config = MatryoshkaDemoNative.config
+    unless MatryoshkaDemoNative.build_info[:features].include?('rayon')
+      assert_raises(ArgumentError) { config.threads = 4 }
+      return
+    end
+
+    config.threads = 4
+    assert_equal 4, config.threads
+    assert_equal 37_607_912_018, MatryoshkaDemoNative.count_primes(10**12)
+    config.threads = 2
+    assert_equal 37_607_912_018, MatryoshkaDemoNative.count_primes(10**12)
+    assert_equal 37_607_912_018, MatryoshkaDemoNative.count_primes(10**12, threads: 3)
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.count_primes(100, threads: 10_000) }
+  ensure
+    config.threads = nil
+  end
+
+  def test_native_memory_stats
+    stats = MatryoshkaDemoNative.native_memory_stats
+    assert_equal %i[heap sieves cache thread_pools], stats.keys
+    assert_predicate stats, :frozen?
+    stats.each_value do |counter|
+      assert_equal %i[current peak], counter.keys
+      assert_operator counter[:current], :<=, counter[:peak]
+    end
+
+    sieve = MatryoshkaDemoNative::Sieve.new(3_000_000)
+    MatryoshkaDemoNative.count_primes(100_000)
+    stats = MatryoshkaDemoNative.native_memory_stats
+    assert_operator stats[:sieves][:current], :>=, sieve.memsize
+    assert_operator stats[:cache][:current], :>=, 100_000 / 30
+    assert_operator stats[:heap][:current], :>=, stats[:sieves][:current] + stats[:cache][:current]
+  end
+
+  def test_build_info
+    info = MatryoshkaDemoNative.build_info
+    assert_predicate info, :frozen?
+    assert_equal %i[version core_version rustc features core_features target namespace profile opt_level],
+                 info.keys
+    assert_equal 'MatryoshkaDemoNative', info[:namespace]
+    assert_match(/\A\d+\.\d+\.\d+/, info[:core_version])
+    assert_match(/\Arustc /, info[:rustc])
+    assert_includes info[:core_features], 'cache'
+    assert info.values.all?(&:frozen?)
+    assert info[:features].all?(&:frozen?)
+  end
+
+  def test_capability_predicates
+    features = MatryoshkaDemoNative.build_info[:features]
+    simd_cpu = RbConfig::CONFIG['host_cpu'].match?(/\A(x86_64|aarch64|arm64)/)
+    assert_equal features.include?('simd') && simd_cpu, MatryoshkaDemoNative.simd?
+    assert_equal false, MatryoshkaDemoNative.parallel? unless features.include?('rayon')
+    if ENV['EXPECT_MATRYOSHKADEMO_PARALLEL'] == '1'
+      assert_includes features, 'rayon'
+      assert MatryoshkaDemoNative.parallel?, 'count_primes_parallel would run serially'
+    end
+
+    config = MatryoshkaDemoNative.config
+    limit = config.cache_limit
+    assert MatryoshkaDemoNative.cache_enabled?
+    config.cache_limit = 0
+    refute MatryoshkaDemoNative.cache_enabled?
+  ensure
+    config.cache_limit = limit
+  end
+
+  def test_concurrency_check
+    assert_equal 4 * 60, MatryoshkaDemoNative._concurrency_check(4, 64)
+
+    # Ruby threads counting and reconfiguring alongside the native ones
+    config = MatryoshkaDemoNative.config
+    segment_bytes = config.segment_bytes
+    counters = Array.new(4) do |i|
+      Thread.new do
+        config.segment_bytes = 1 << (10 + i)
+        20.times.map { MatryoshkaDemoNative.count_primes(1_000_000) }.uniq
+      end
+    end
+    assert_operator MatryoshkaDemoNative._concurrency_check, :>, 0
+    counters.each { |thread| assert_equal [78_498], thread.value }
+
+    assert_raises(ArgumentError) { MatryoshkaDemoNative._concurrency_check(0) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative._concurrency_check(1, 0) }
+  ensure
+    config.segment_bytes = segment_bytes
+  end
+
+  def test_freeze_results
+    config = MatryoshkaDemoNative.config
+    refute config.freeze_results?
+    refute_predicate MatryoshkaDemoNative.primes_up_to(10), :frozen?
+
+    config.freeze_results = true
+    assert config.freeze_results?
+    primes = MatryoshkaDemoNative.primes_up_to(100)
+    assert_predicate primes, :frozen?
+    assert Ractor.shareable?(primes)
+    assert_raises(FrozenError) { primes << 101 }
+
+    twins = MatryoshkaDemoNative.twin_primes_up_to(20)
+    assert_predicate twins, :frozen?
+    assert twins.all?(&:frozen?)
+    assert Ractor.shareable?(MatryoshkaDemoNative.factorize(360))
+    assert Ractor.shareable?(MatryoshkaDemoNative.goldbach_pair!(100))
+    assert_nil MatryoshkaDemoNative.goldbach_pair(7)
+
+    packed = MatryoshkaDemoNative.primes_packed(1_000)
+    assert_predicate packed, :frozen?
+    assert Ractor.shareable?(packed)
+    assert_equal packed, MatryoshkaDemoNative.primes_packed(1_000)
+  ensure
+    config.freeze_results = false
+  end
+
+  def test_config
+    config = MatryoshkaDemoNative.config
+    defaults = config.to_h
+    assert_equal({ threads: nil, segment_bytes: 32_768, cache_limit: 100_000_000, strict: true, progress_steps: 1_000,
+                   freeze_results: false },
+                 defaults)
+
+    config.segment_bytes = 1_024
+    config.cache_limit = 0
+    config.progress_steps = 10
+    assert_equal 1_024, MatryoshkaDemoNative.config.segment_bytes
+    assert_equal 78_498, MatryoshkaDemoNative.count_primes(1_000_000)
+    reports = 0
+    MatryoshkaDemoNative.count_primes(10**9, progress: ->(*) { reports += 1 })
+    assert_operator reports, :<=, 11
+
+    config.strict = false
+    assert MatryoshkaDemoNative.lenient?
+    assert_equal 0, MatryoshkaDemoNative.count_primes(-5)
+    MatryoshkaDemoNative.lenient = false
+    assert config.strict?
+
+    assert_raises(ArgumentError) { config.segment_bytes = 16 }
+    assert_raises(ArgumentError) { config.progress_steps = 0 }
+    assert_raises(ArgumentError) { config.threads = 0 }
+    assert_raises(TypeError) { config.cache_limit = 'big' }
+  ensure
+    config.segment_bytes = nil
+    config.cache_limit = defaults[:cache_limit]
+    config.progress_steps = defaults[:progress_steps]
+  end
+
+  def test_cancel_handle
+    assert_same MatryoshkaDemoNative::CancelToken, MatryoshkaDemoNative::Cancel
+    cancel = MatryoshkaDemoNative::Cancel.new
+    assert_equal [11, 13], MatryoshkaDemoNative.primes_in(10..15, cancel: cancel)
+
+    worker = Thread.new do
+      Thread.current.report_on_exception = false
+      File.open(File::NULL, 'w') { |null| MatryoshkaDemoNative.write_primes(null, 10**13, cancel: cancel) }
+    end
+    sleep 0.2
+    cancel.cancel!
+    assert cancel.cancelled?
+    assert_raises(MatryoshkaDemoNative::Cancelled) { worker.join }
+
+    [
+      -> { MatryoshkaDemoNative.count_primes_in(0..10**12, cancel: cancel) },
+      -> { MatryoshkaDemoNative.primes_in(0..10**8, cancel: cancel) },
+      -> { MatryoshkaDemoNative.primes_packed(10**8, cancel: cancel) },
+      -> { MatryoshkaDemoNative.count_primes_many([10**12], cancel: cancel) }
+    ].each { |call| assert_raises(MatryoshkaDemoNative::Cancelled, &call) }
+    assert_raises(TypeError) { MatryoshkaDemoNative.primes_packed(10, cancel: :nope) }
+  end
+
+  def test_count_primes_with_stats
+    stats = MatryoshkaDemoNative.count_primes_with_stats(1_000_000, algorithm: :segmented)
+    assert_kind_of Struct, stats
+    assert stats.frozen?
+    assert_equal 78_498, stats.count
+    assert_equal :segmented, stats.algorithm
+    assert_operator stats.segments, :>=, 1
+    assert_operator stats.peak_bytes, :>, 0
+    assert_kind_of Float, stats.elapsed_ms
+    assert_operator stats.elapsed_ms, :>=, 0
+
+    sublinear = MatryoshkaDemoNative.count_primes_with_stats(10**10)
+    assert_equal 455_052_511, sublinear.count
+    assert_equal :sublinear, sublinear.algorithm
+    assert_equal 0, sublinear.segments
+    assert_equal %i[count elapsed_ms peak_bytes algorithm segments], sublinear.to_h.keys
+    assert_equal 0, MatryoshkaDemoNative.count_primes_with_stats(1).count
+
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.count_primes_with_stats(-1) }
+    assert_raises(MatryoshkaDemoNative::ResourceLimit) { MatryoshkaDemoNative.count_primes_with_stats(10**15) }
+  end
+
+  def test_count_primes_nonblocking
+    skip 'count_primes_nonblocking needs Unix sockets' unless MatryoshkaDemoNative.respond_to?(:count_primes_nonblocking)
+
+    assert_equal 78_498, MatryoshkaDemoNative.count_primes_nonblocking(1_000_000)
+    assert_equal 0, MatryoshkaDemoNative.count_primes_nonblocking(1)
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.count_primes_nonblocking(-1) }
+    assert_raises(MatryoshkaDemoNative::Timeout) do
+      MatryoshkaDemoNative.count_primes_nonblocking(10**14, timeout: 0.05)
+    end
+
+    ticks = 0
+    ticker = Thread.new { loop { ticks += 1; sleep 0.001 } }
+    assert_equal 37_607_912_018, MatryoshkaDemoNative.count_primes_nonblocking(10**12)
+    ticker.kill
+    assert_operator ticks, :>, 0
+
+    token = MatryoshkaDemoNative::CancelToken.new
+    worker = Thread.new do
+      Thread.current.report_on_exception = false
+      MatryoshkaDemoNative.count_primes_nonblocking(10**14, cancel: token)
+    end
+    sleep 0.1
+    worker.raise(Interrupt)
+    assert_raises(Interrupt) { worker.join }
+    refute token.cancelled?
+    assert_equal 664_579, MatryoshkaDemoNative.count_primes(10**7, algorithm: :segmented, cancel: token)
+  end
+
+  def test_timeout_option
+    assert_equal 168, MatryoshkaDemoNative.count_primes(1_000, timeout: 5)
+    assert_equal 25, MatryoshkaDemoNative.count_primes(100, timeout: nil)
+    assert_equal [11, 13], MatryoshkaDemoNative.primes_in(10..15, timeout: 0.5)
+
+    [
+      -> { MatryoshkaDemoNative.count_primes(10**14, algorithm: :segmented, timeout: 0.05) },
+      -> { MatryoshkaDemoNative.count_primes_in(0..10**13, timeout: 0.05) },
+      -> { File.open(File::NULL, 'w') { |null| MatryoshkaDemoNative.write_primes(null, 10**13, timeout: 0.05) } },
+      -> { MatryoshkaDemoNative.count_primes_many([10**14], timeout: 0.05) }
+    ].each { |call| assert_raises(MatryoshkaDemoNative::Timeout, &call) }
+
+    token = MatryoshkaDemoNative::CancelToken.new
+    token.cancel
+    assert_raises(MatryoshkaDemoNative::Cancelled) { MatryoshkaDemoNative.count_primes(10**12, cancel: token, timeout: 60) }
+    refute MatryoshkaDemoNative::CancelToken.new.tap { |t| MatryoshkaDemoNative.count_primes(1_000, cancel: t, timeout: 1) }.cancelled?
+
+    [0, -1, Float::NAN].each do |timeout|
+      assert_raises(ArgumentError) { MatryoshkaDemoNative.count_primes(100, timeout: timeout) }
+    end
+    assert_raises(TypeError) { MatryoshkaDemoNative.count_primes(100, timeout: '1') }
+  end
+
+  def test_callable_from_ractors
+    skip 'Ractor not available' unless defined?(Ractor)
+
+    Warning[:experimental] = false
+    ractors = [1_000, 1_000_000, 10**12, 2_000_000].map do |limit|
+      Ractor.new(limit) do |n|
+        sieve = MatryoshkaDemoNative::Sieve.new(10_000)
+        [MatryoshkaDemoNative.count_primes(n), MatryoshkaDemoNative.prime?(n + 3), sieve.nth(100)]
+      end
+    end
+    results = ractors.map { |r| r.respond_to?(:value) ? r.value : r.take }
+    expected = [1_000, 1_000_000, 10**12, 2_000_000].map do |n|
+      [MatryoshkaDemoNative.count_primes(n), MatryoshkaDemoNative.prime?(n + 3), 541]
+    end
+    assert_equal expected, results
+  ensure
+    Warning[:experimental] = true
+  end
+
+  def test_count_primes_reuses_cache
+    MatryoshkaDemoNative.clear_prime_cache
+    assert_equal 78_498, MatryoshkaDemoNative.count_primes(1_000_000)
+    assert_equal 168, MatryoshkaDemoNative.count_primes(1_000)
+    assert_equal 664_579, MatryoshkaDemoNative.count_primes(10_000_000)
+    MatryoshkaDemoNative.clear_prime_cache
+    assert_equal 78_498, MatryoshkaDemoNative.count_primes(1_000_000)
+  end
+
+  def test_prime_pi_approx
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.prime_pi_approx(-1) }
+    assert_equal 78_627, MatryoshkaDemoNative.prime_pi_approx(1_000_000)
+    assert_in_delta 50_847_534, MatryoshkaDemoNative.prime_pi_approx(1_000_000_000), 5_000
+  end
+
+  def test_sieve_object
+    sieve = MatryoshkaDemoNative::Sieve.new(10_000_000)
+    assert_equal 10_000_000, sieve.limit
+    assert sieve.prime?(97)
+    refute sieve.prime?(91)
+    refute sieve.prime?(10_000_019)
+    assert_equal 664_579, sieve.count
+    assert_equal 3_571, sieve.nth(500)
+    assert_nil sieve.nth(1_000_000)
+    assert_raises(ArgumentError) { sieve.nth(0) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative::Sieve.new(-1) }
+
+    require 'objspace'
+    assert_operator ObjectSpace.memsize_of(sieve), :>=, 10_000_000 / 30
+    assert_equal ObjectSpace.memsize_of(sieve), sieve.memsize
+    assert_operator MatryoshkaDemoNative::Sieve.new(100).memsize, :<, sieve.memsize
+  end
+
+  def test_sieve_enumerable_and_comparable
+    sieve = MatryoshkaDemoNative::Sieve.new(30)
+    assert_equal [2, 3, 5, 7, 11, 13, 17, 19, 23, 29], sieve.to_a
+    assert_equal [11, 13], sieve.select { |p| p.between?(10, 15) }
+    assert_equal 29, sieve.max
+    assert_equal [2, 3], sieve.lazy.first(2)
+    assert_equal 10, sieve.count
+    assert_equal 9, sieve.count(&:odd?)
+    assert sieve.include?(29)
+    refute sieve.include?(31)
+    refute sieve.include?(2**70)
+    assert_kind_of Enumerator, sieve.each
+
+    bigger = MatryoshkaDemoNative::Sieve.new(100)
+    assert_operator sieve, :<, bigger
+    assert_equal bigger, [bigger, sieve].max
+    assert_nil sieve <=> 30
+  end
+
+  def test_sieve_primes_survive_compaction
+    skip 'GC compaction not available' unless GC.respond_to?(:verify_compaction_references)
+    sieves = Array.new(20) { |i| MatryoshkaDemoNative::Sieve.new(1_000 + (i * 100)) }
+    sieves.each(&:primes)
+    tokens = Array.new(20) { MatryoshkaDemoNative::CancelToken.new }
+    # Garbage between the objects leaves room for compaction to move them
+    10_000.times { Object.new }
+
+    begin
+      GC.verify_compaction_references(toward: :empty)
+    rescue NotImplementedError
+      skip 'GC compaction not supported on this platform'
+    end
+    sieves.each do |sieve|
+      primes = sieve.primes
+      assert primes.frozen?
+      assert_same primes, sieve.primes
+      assert_equal sieve.to_a, primes
+    end
+    refute tokens.any?(&:cancelled?)
+  end
+
+  def test_sieve_allocate_and_subclass
+    blank = MatryoshkaDemoNative::Sieve.allocate
+    assert_equal 0, blank.limit
+    assert_equal [], blank.to_a
+
+    subclass = Class.new(MatryoshkaDemoNative::Sieve) do
+      def twins = each_cons(2).select { |p, q| q - p == 2 }
+    end
+    sieve = subclass.new(20)
+    assert_equal [[3, 5], [5, 7], [11, 13], [17, 19]], sieve.twins
+    assert_instance_of subclass, sieve.dup
+    assert_equal sieve.to_a, sieve.clone.to_a
+
+    sieve.send(:initialize, 10)
+    assert_equal [2, 3, 5, 7], sieve.primes
+    assert_raises(RuntimeError) { sieve.each { sieve.send(:initialize, 100) } }
+  end
+
+  def test_sieve_dup_and_clone
+    sieve = MatryoshkaDemoNative::Sieve.new(100)
+    copy = sieve.dup
+    sieve.send(:initialize, 10)
+    assert_equal 100, copy.limit
+    assert_equal 25, copy.count
+    refute_same sieve.primes, copy.primes
+    assert_raises(TypeError) { copy.send(:initialize_copy, Object.new) }
+
+    sieve.freeze
+    assert sieve.prime?(7)
+    assert_equal [2, 3, 5, 7], sieve.primes
+    assert_raises(FrozenError) { sieve.send(:initialize, 20) }
+    assert_raises(FrozenError) { sieve.send(:initialize_copy, copy) }
+    assert_equal 10, sieve.limit
+
+    refute_predicate sieve.dup, :frozen?
+    frozen = sieve.clone
+    assert_predicate frozen, :frozen?
+    assert_equal [2, 3, 5, 7], frozen.to_a
+    thawed = sieve.clone(freeze: false)
+    refute_predicate thawed, :frozen?
+    thawed.send(:initialize, 20)
+    assert_equal 8, thawed.count
+    assert_equal 4, sieve.count
+  end
+
+  def test_sieve_inspect
+    sieve = MatryoshkaDemoNative::Sieve.new(1_000_000)
+    assert_match(/\A#<MatryoshkaDemoNative::Sieve limit=1000000 mem=\d+KiB>\z/, sieve.inspect)
+    sieve.primes
+    assert_match(/ limit=1000000 primes=78498 mem=33KiB>\z/, sieve.inspect)
+    assert_equal "#{sieve.inspect}\n", sieve.pretty_inspect
+    assert_match(/\A#<MatryoshkaDemoNative::Sieve limit=0 mem=\d+B>\z/, MatryoshkaDemoNative::Sieve.allocate.inspect)
+
+    subclass = Class.new(MatryoshkaDemoNative::Sieve)
+    assert_match(/\A#<#<Class:0x\h+> limit=10 /, subclass.new(10).inspect)
+  end
+
+  def test_sieve_marshal
+    sieve = MatryoshkaDemoNative::Sieve.new(100_000)
+    restored = Marshal.load(Marshal.dump(sieve))
+    assert_instance_of MatryoshkaDemoNative::Sieve, restored
+    assert_equal 100_000, restored.limit
+    assert_equal 9_592, restored.count
+    assert restored.prime?(99_991)
+    assert_raises(ArgumentError) { MatryoshkaDemoNative::Sieve._load('not a sieve') }
+  end
+
+  def test_sieve_memory_reported_to_gc
+    GC.disable
+    before = GC.stat(:malloc_increase_bytes)
+    sieve = MatryoshkaDemoNative::Sieve.new(300_000_000)
+    assert_operator GC.stat(:malloc_increase_bytes) - before, :>=, 300_000_000 / 30
+    assert_equal 300_000_000, sieve.limit
+  ensure
+    GC.enable
+  end
+
+  def test_prime_bitmap
+    bitmap = MatryoshkaDemoNative.prime_bitmap(1_000)
+    assert_equal Encoding::BINARY, bitmap.encoding
+    assert_equal 34, bitmap.bytesize
+    assert_equal 0b1111_1110, bitmap.getbyte(0)
+    popcount = bitmap.unpack1("b*").count("1")
+    assert_equal 168 - 3, popcount
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.prime_bitmap(-1) }
+  end
+
+  def test_nth_prime_after
+    assert_equal 1_000_003, MatryoshkaDemoNative.nth_prime_after(1_000_000, 1)
+    assert_equal 17, MatryoshkaDemoNative.nth_prime_after(7, 3)
+    assert_equal 2, MatryoshkaDemoNative.nth_prime_after(-10, 1)
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.nth_prime_after(10, 0) }
+  end
+
+  def test_sum_of_primes
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.sum_of_primes(-5) }
+    assert_equal 0, MatryoshkaDemoNative.sum_of_primes(1)
+    assert_equal 17, MatryoshkaDemoNative.sum_of_primes(10)
+    assert_equal 142_913_828_922, MatryoshkaDemoNative.sum_of_primes(2_000_000)
+  end
+
+  def test_primes_up_to
+    assert_equal [], MatryoshkaDemoNative.primes_up_to(1)
+    assert_equal [2, 3, 5, 7, 11, 13, 17, 19, 23, 29], MatryoshkaDemoNative.primes_up_to(30)
+    primes = MatryoshkaDemoNative.primes_up_to(1_000_000)
+    assert_equal 78_498, primes.size
+    assert_equal 999_983, primes.last
+    assert_raises(MatryoshkaDemoNative::ResourceLimit) { MatryoshkaDemoNative.primes_up_to(10**10) }
+  end
+
+  def test_primes_packed
+    packed = MatryoshkaDemoNative.primes_packed(1_000_000)
+    assert_equal Encoding::BINARY, packed.encoding
+    assert_equal 78_498 * 8, packed.bytesize
+    assert_equal MatryoshkaDemoNative.primes_up_to(1_000_000), packed.unpack('Q<*')
+    assert_equal [2, 3, 5, 7], MatryoshkaDemoNative.primes_packed(10, format: :u32).unpack('L<*')
+    assert_equal packed, MatryoshkaDemoNative.primes_packed(1_000_000, format: :u64)
+    assert_equal '', MatryoshkaDemoNative.primes_packed(1)
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.primes_packed(10, format: :u16) }
+    assert_raises(MatryoshkaDemoNative::ResourceLimit) { MatryoshkaDemoNative.primes_packed(10**10) }
+  end
+
+  def test_write_primes
+    io = StringIO.new
+    assert_equal 78_498, MatryoshkaDemoNative.write_primes(io, 1_000_000)
+    assert_equal MatryoshkaDemoNative.primes_up_to(1_000_000).map { |p| "#{p}\n" }.join, io.string
+
+    io = StringIO.new
+    assert_equal 4, MatryoshkaDemoNative.write_primes(io, 10, separator: ', ')
+    assert_equal '2, 3, 5, 7, ', io.string
+    assert_equal 0, MatryoshkaDemoNative.write_primes(StringIO.new, 1)
+
+    failing = Object.new
+    def failing.write(*) = raise(IOError, 'closed stream')
+    assert_raises(IOError) { MatryoshkaDemoNative.write_primes(failing, 10**8) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.write_primes(Object.new, 10) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.write_primes(StringIO.new, -1) }
+  end
+
+  def test_each_prime_with_block
+    seen = []
+    result = MatryoshkaDemoNative.each_prime(30) { |p| seen << p }
+    assert_equal MatryoshkaDemoNative, result
+    assert_equal MatryoshkaDemoNative.primes_up_to(30), seen
+
+    first = MatryoshkaDemoNative.each_prime(10**12) { |p| break p if p > 1_000 }
+    assert_equal 1_009, first
+    assert_raises(RuntimeError) { MatryoshkaDemoNative.each_prime(100) { raise "stop" } }
+  end
+
+  def test_each_prime_without_block
+    enum = MatryoshkaDemoNative.each_prime(100)
+    assert_kind_of Enumerator, enum
+    assert_equal 25, enum.count
+    assert_equal [2, 3, 5], MatryoshkaDemoNative.each_prime(10**12).first(3)
+  end
+
+  def test_each_prime_slice
+    slices = []
+    result = MatryoshkaDemoNative.each_prime_slice(100, batch_size: 10) { |slice| slices << slice }
+    assert_equal MatryoshkaDemoNative, result
+    assert_equal [10, 10, 5], slices.map(&:size)
+    assert_equal MatryoshkaDemoNative.primes_up_to(100), slices.flatten
+
+    assert_equal [MatryoshkaDemoNative.primes_up_to(1_000)],
+                 MatryoshkaDemoNative.each_prime_slice(1_000).to_a
+    assert_equal 78_498, MatryoshkaDemoNative.each_prime_slice(10**6, batch_size: 999).sum(&:size)
+    assert_equal [], MatryoshkaDemoNative.each_prime_slice(1).to_a
+    assert_equal [2, 3], MatryoshkaDemoNative.each_prime_slice(10**12, batch_size: 2).first
+
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.each_prime_slice(-1) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.each_prime_slice(10, batch_size: 0) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.each_prime_slice(10, batch_size: 2**30) }
+    assert_raises(RuntimeError) { MatryoshkaDemoNative.each_prime_slice(100) { raise "stop" } }
+  end
+
+  def test_primes
+    enum = MatryoshkaDemoNative.primes
+    assert_kind_of Enumerator, enum
+    assert_equal [2, 3, 5, 7, 11], enum.first(5)
+    assert_equal [2, 3], [enum.next, enum.next]
+    assert_equal [3, 13, 23, 43, 53], enum.lazy.select { |p| p % 10 == 3 }.first(5)
+    assert_equal 1_000_003, enum.lazy.reject { |p| p < 10**6 }.first
+    assert_equal 7_919, enum.each_with_index { |p, i| break p if i == 999 }
+    assert_equal 10_007, MatryoshkaDemoNative.primes { |p| break p if p > 10_000 }
+    assert_raises(RuntimeError) { MatryoshkaDemoNative.primes { raise "stop" } }
+  end
+
+  def test_twin_primes_up_to
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.twin_primes_up_to(-1) }
+    assert_equal [], MatryoshkaDemoNative.twin_primes_up_to(4)
+    assert_equal [[3, 5], [5, 7], [11, 13], [17, 19]], MatryoshkaDemoNative.twin_primes_up_to(20)
+    assert_equal 8_169, MatryoshkaDemoNative.twin_primes_up_to(1_000_000).size
+  end
+
+  def test_sophie_germain_primes_up_to
+    assert_equal [], MatryoshkaDemoNative.sophie_germain_primes_up_to(1)
+    assert_equal [2, 3, 5, 11, 23, 29, 41, 53, 83, 89], MatryoshkaDemoNative.sophie_germain_primes_up_to(100)
+  end
+
+  def test_primes_mod
+    assert_equal [5, 13, 17, 29, 37, 41], MatryoshkaDemoNative.primes_mod(50, 4, 1)
+    assert_equal [3, 7, 11, 19, 23, 31, 43, 47], MatryoshkaDemoNative.primes_mod(50, 4, -1)
+    assert_equal [5], MatryoshkaDemoNative.primes_mod(50, 10, 5)
+    assert_equal [], MatryoshkaDemoNative.primes_mod(50, 6, 4)
+    assert_equal MatryoshkaDemoNative.primes_up_to(50), MatryoshkaDemoNative.primes_mod(50, 1, 0)
+    assert_equal 39_175, MatryoshkaDemoNative.primes_mod(1_000_000, 4, 1).size
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.primes_mod(50, 0, 1) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.primes_mod(-1, 4, 1) }
+  end
+
+  def test_max_prime_gap
+    assert_nil MatryoshkaDemoNative.max_prime_gap(2)
+    assert_equal [887, 907], MatryoshkaDemoNative.max_prime_gap(1_000)
+  end
+
+  def test_prime_predicate
+    assert MatryoshkaDemoNative.prime?(2)
+    assert MatryoshkaDemoNative.prime?(1_000_000_007)
+    refute MatryoshkaDemoNative.prime?(1)
+    refute MatryoshkaDemoNative.prime?(-7)
+    refute MatryoshkaDemoNative.prime?(1_000_000_008)
+  end
+
+  def test_prime_predicate_matches_cached_sieve
+    MatryoshkaDemoNative.count_primes(100_000)
+    sieved = MatryoshkaDemoNative.primes_up_to(100_000)
+    assert_equal sieved, (0..100_000).select { |n| MatryoshkaDemoNative.prime?(n) }
+    assert MatryoshkaDemoNative.prime?(1_000_000_007)
+  end
+
+  def test_arguments_past_i64
+    assert MatryoshkaDemoNative.prime?(2**64 - 59)
+    assert MatryoshkaDemoNative.prime?(2**64 + 13)
+    assert_raises(RangeError) { MatryoshkaDemoNative.prime?(2**128) }
+    assert_equal 2**64 - 59, MatryoshkaDemoNative.prev_prime(2**64 - 1)
+    assert_nil MatryoshkaDemoNative.next_prime(2**64 - 59)
+    assert_raises(RangeError) { MatryoshkaDemoNative.next_prime(2**64) }
+    assert_equal 2**63 - 1, MatryoshkaDemoNative.gcd(2**64 - 2, -(2**63 - 1))
+    assert_raises(RangeError) { MatryoshkaDemoNative.gcd(2**64, 1) }
+    assert_equal 15_528_699_724_400_930_166, MatryoshkaDemoNative.pow_mod(2**63 + 5, 2**64 - 1, 2**64 - 59)
+    assert_equal 360_884, MatryoshkaDemoNative.inv_mod(-(2**63 + 7), 1_000_003)
+    assert_equal 2**32 - 1, MatryoshkaDemoNative.isqrt(2**64 - 1)
+    assert_raises(RangeError) { MatryoshkaDemoNative.count_primes(2**64) }
+    assert_raises(RangeError) { MatryoshkaDemoNative.nth_prime(2**70) }
+  end
+
+  # An Integer wrapper that is not a Numeric, like a typed id or unit value
+  Wrapped = Struct.new(:value) do
+    def to_int = value
+  end
+
+  def test_to_int_arguments
+    assert_equal 25, MatryoshkaDemoNative.count_primes(Wrapped.new(100))
+    assert_equal 29, MatryoshkaDemoNative.nth_prime(Wrapped.new(10))
+    assert_equal 6, MatryoshkaDemoNative.gcd(Wrapped.new(12), 18)
+    assert_equal 1_024, MatryoshkaDemoNative.checked_pow(2, Wrapped.new(10))
+    assert_equal [11, 13], MatryoshkaDemoNative.primes_in(Wrapped.new(10)..Wrapped.new(15))
+    assert MatryoshkaDemoNative.mersenne_prime?(Wrapped.new(61))
+
+    error = assert_raises(RangeError) { MatryoshkaDemoNative.count_primes(Wrapped.new(2**64)) }
+    assert_includes error.message, (2**64).to_s
+    error = assert_raises(RangeError) { MatryoshkaDemoNative.checked_pow(2, 2**32) }
+    assert_includes error.message, (2**32).to_s
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.count_primes(100.0) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.random_prime(bits: 2**70) }
+    assert_raises(TypeError) { MatryoshkaDemoNative.gcd(Object.new, 1) }
+  end
+
+  def test_mersenne_prime_predicate
+    assert MatryoshkaDemoNative.mersenne_prime?(2)
+    assert MatryoshkaDemoNative.mersenne_prime?(127)
+    refute MatryoshkaDemoNative.mersenne_prime?(11)
+    refute MatryoshkaDemoNative.mersenne_prime?(-3)
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.mersenne_prime?(128) }
+  end
+
+  def test_prime_u128_predicate
+    assert MatryoshkaDemoNative.prime_u128?(2**127 - 1)
+    assert MatryoshkaDemoNative.prime_u128?(1_000_000_007)
+    refute MatryoshkaDemoNative.prime_u128?(2**127 + 1)
+    refute MatryoshkaDemoNative.prime_u128?(-7)
+    assert_raises(RangeError) { MatryoshkaDemoNative.prime_u128?(2**128) }
+  end
+
+  def test_next_prime
+    assert_equal 2, MatryoshkaDemoNative.next_prime(-10)
+    assert_equal 29, MatryoshkaDemoNative.next_prime(24)
+    assert_equal 1_000_000_007, MatryoshkaDemoNative.next_prime(1_000_000_000)
+  end
+
+  def test_next_safe_prime
+    assert_equal 5, MatryoshkaDemoNative.next_safe_prime(-1)
+    assert_equal 107, MatryoshkaDemoNative.next_safe_prime(100)
+    assert_equal 1_000_000_007, MatryoshkaDemoNative.next_safe_prime(1_000_000_000)
+  end
+
+  def test_random_prime
+    20.times do
+      prime = MatryoshkaDemoNative.random_prime(bits: 32)
+      assert_equal 32, prime.bit_length
+      assert MatryoshkaDemoNative.prime?(prime)
+    end
+    assert_includes [2, 3], MatryoshkaDemoNative.random_prime(bits: 2)
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.random_prime(bits: 65) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.random_prime }
+  end
+
+  def test_factorize
+    assert_equal({ 2 => 2, 3 => 1 }, MatryoshkaDemoNative.factorize(12))
+    assert_equal({ 71 => 1, 839 => 1, 1471 => 1, 6857 => 1 }, MatryoshkaDemoNative.factorize(600_851_475_143))
+    assert_equal({ 4_294_967_291 => 2 }, MatryoshkaDemoNative.factorize(4_294_967_291**2))
+    assert_equal [3, 5, 17, 257, 641, 65_537, 6_700_417], MatryoshkaDemoNative.factorize(2**64 - 1).keys
+    assert_equal({}, MatryoshkaDemoNative.factorize(1))
+    assert_equal({ -1 => 1, 2 => 2, 3 => 1 }, MatryoshkaDemoNative.factorize(-12))
+    assert_raises(ZeroDivisionError) { MatryoshkaDemoNative.factorize(0) }
+    assert_raises(RangeError) { MatryoshkaDemoNative.factorize(2**64) }
+
+    require 'prime'
+    [2, 97, 360, 1_001, 65_536, -30].each do |n|
+      assert_equal Prime.prime_division(n), MatryoshkaDemoNative.factorize(n).to_a
+    end
+  end
+
+  def test_eratosthenes_generator
+    generator = MatryoshkaDemoNative::EratosthenesGenerator.new
+    assert_equal [2, 3, 5], [generator.succ, generator.next, generator.succ]
+    copy = generator.dup
+    generator.rewind
+    assert_equal 2, generator.succ
+    assert_equal 7, copy.succ
+    assert_nil generator.upper_bound
+
+    bounded = MatryoshkaDemoNative::EratosthenesGenerator.new(30)
+    assert_equal 30, bounded.upper_bound
+    assert_equal [2, 3, 5, 7, 11, 13, 17, 19, 23, 29], bounded.each.to_a
+    assert_equal :last, MatryoshkaDemoNative::EratosthenesGenerator.new(10).each { :last }
+    assert_equal [], MatryoshkaDemoNative::EratosthenesGenerator.new(-5).to_a
+    assert_equal [2, 3, 5, 7], MatryoshkaDemoNative::EratosthenesGenerator.new.lazy.take_while { |p| p < 10 }.to_a
+
+    require 'prime'
+    native = MatryoshkaDemoNative::EratosthenesGenerator
+    assert_equal Prime.each(10_000).to_a, Prime.each(10_000, native.new).to_a
+    assert_equal 1_229, Prime.each(10_000, native.new).count
+    assert Prime.prime?(1_000_003, native.new)
+    refute Prime.prime?(1_000_001, native.new)
+    assert_equal [[2, 4], [3, 2], [5, 1]], Prime.prime_division(720, native.new)
+  end
+
+  def test_patch_integer
+    assert_same Integer, MatryoshkaDemoNative.patch_integer!
+    assert_same Integer, MatryoshkaDemoNative.patch_integer!
+    assert 97.prime_native?
+    refute 1.prime_native?
+    assert (2**89 - 1).prime_native?
+    assert_equal [2, 2, 3], 12.prime_factors_native
+    assert_equal [-1, 2, 5], -10.prime_factors_native
+    assert_equal [], 1.prime_factors_native
+    assert_raises(ZeroDivisionError) { 0.prime_factors_native }
+    assert_raises(RangeError) { (2**64).prime_factors_native }
+  end
+
+  def test_goldbach_pair
+    assert_equal [5, 23], MatryoshkaDemoNative.goldbach_pair(28)
+    assert_equal [2, 2], MatryoshkaDemoNative.goldbach_pair(4)
+    assert_nil MatryoshkaDemoNative.goldbach_pair(27)
+    assert_nil MatryoshkaDemoNative.goldbach_pair(-8)
+  end
+
+  def test_pow_mod
+    assert_equal 24, MatryoshkaDemoNative.pow_mod(2, 10, 1_000)
+    assert_equal 1, MatryoshkaDemoNative.pow_mod(12_345, 1_000_000_006, 1_000_000_007)
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.pow_mod(2, 10, 0) }
+  end
+
+  def test_notifications_events
+    skip 'ActiveSupport is loaded' if defined?(ActiveSupport)
+    events = []
+    notifications = Module.new do
+      define_singleton_method(:instrumenter) { Struct.new(:id).new('test') }
+      define_singleton_method(:publish) { |*args| events << args }
+    end
+    Object.const_set(:ActiveSupport, Module.new)
+    ActiveSupport.const_set(:Notifications, notifications)
+
+    assert_equal 168, MatryoshkaDemoNative.count_primes(1_000)
+    assert_equal 25, MatryoshkaDemoNative.count_primes(100, algorithm: :sublinear)
+    name, started, finished, id, payload = events.first
+    assert_equal 'matryoshka_demo.count_primes', name
+    assert_operator started, :<=, finished
+    assert_equal 'test', id
+    assert_equal 1_000, payload[:limit]
+    assert_equal :cached, payload[:algorithm]
+    assert_kind_of Integer, payload[:memory]
+    assert_kind_of Float, payload[:duration]
+    assert_equal :sublinear, events.last.last[:algorithm]
+  ensure
+    Object.send(:remove_const, :ActiveSupport) if notifications
+  end
+
+  def test_logger_bridge
+    skip 'built without the log feature' unless MatryoshkaDemoNative.respond_to?(:logger=)
+    require 'logger'
+    output = StringIO.new
+    config = MatryoshkaDemoNative.config
+    assert_equal :warn, config.log_level
+    assert_raises(ArgumentError) { config.log_level = :loud }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.logger = Object.new }
+
+    MatryoshkaDemoNative.logger = Logger.new(output)
+    config.log_level = :debug
+    MatryoshkaDemoNative.count_primes_in(10..1_000)
+    assert_match(/matryoshka_demo.count_primes_in: limit 1000/, output.string)
+  ensure
+    config&.log_level = :warn
+    MatryoshkaDemoNative.logger = nil if MatryoshkaDemoNative.respond_to?(:logger=)
+  end
+
+  def test_inv_mod
+    assert_equal 4, MatryoshkaDemoNative.inv_mod(3, 11)
+    assert_equal 7, MatryoshkaDemoNative.inv_mod(-3, 11)
+    assert_nil MatryoshkaDemoNative.inv_mod(6, 9)
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.inv_mod(3, 0) }
+  end
+
+  def test_gcd_and_lcm
+    assert_equal 6, MatryoshkaDemoNative.gcd(12, -18)
+    assert_equal 0, MatryoshkaDemoNative.gcd(0, 0)
+    assert_equal 12, MatryoshkaDemoNative.lcm(-4, 6)
+    assert_equal (2**62 - 1) * (2**62 - 3), MatryoshkaDemoNative.lcm(2**62 - 1, 2**62 - 3)
+  end
+
+  def test_ext_gcd
+    assert_equal [2, -9, 47], MatryoshkaDemoNative.ext_gcd(240, 46)
+    g, x, y = MatryoshkaDemoNative.ext_gcd(-240, 46)
+    assert_equal 2, g
+    assert_equal g, -240 * x + 46 * y
+  end
+
+  def test_prev_prime
+    assert_nil MatryoshkaDemoNative.prev_prime(2)
+    assert_equal 2, MatryoshkaDemoNative.prev_prime(3)
+    assert_equal 29, MatryoshkaDemoNative.prev_prime(30)
+  end
+
+  def test_integer_math
+    assert_equal 2**31, MatryoshkaDemoNative.isqrt(2**62)
+    assert_equal 3, MatryoshkaDemoNative.isqrt(15)
+    assert_equal 10, MatryoshkaDemoNative.icbrt(1_330)
+    assert_equal 10, MatryoshkaDemoNative.ilog2(1_024)
+    assert_equal 81, MatryoshkaDemoNative.checked_pow(3, 4)
+    assert_nil MatryoshkaDemoNative.checked_pow(2, 64)
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.isqrt(-1) }
+    assert_raises(ArgumentError) { MatryoshkaDemoNative.ilog2(0) }
+  end
+end
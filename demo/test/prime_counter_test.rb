@@ -43,8 +43,19 @@ class PrimeCounterTest < Minitest::Test
     assert_equal 7919, MatryoshkaDemo.nth_prime(1000)
   end
 
-  def test_nth_prime_invalid
+  def test_invalid_arguments_raise
+    assert_raises(ArgumentError) { MatryoshkaDemo.count_primes(-5) }
+    assert_raises(ArgumentError) { MatryoshkaDemo.count_primes(10.5) }
+    assert_raises(ArgumentError) { MatryoshkaDemo.nth_prime(0) }
+    assert_raises(ArgumentError) { MatryoshkaDemo.nth_prime(-1) }
+  end
+
+  def test_lenient_mode
+    MatryoshkaDemo.lenient = true
+    assert_equal 0, MatryoshkaDemo.count_primes(-5)
     assert_nil MatryoshkaDemo.nth_prime(0)
     assert_nil MatryoshkaDemo.nth_prime(-1)
+  ensure
+    MatryoshkaDemo.lenient = false
   end
 end